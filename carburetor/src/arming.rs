@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use config::ArmingConfig;
+
+use crate::calibration::Calibration;
+
+/// The pulse widths a channel outputs before it is armed, one step after the other.
+#[derive(Debug)]
+pub(crate) struct ArmingSequence {
+    /// The pulse width of every remaining step, and how long it is held.
+    steps: VecDeque<(Duration, Duration)>,
+    /// When the current step started.
+    step_started: Instant,
+}
+
+impl ArmingSequence {
+    /// Creates the sequence described by `config`, including the calibration sweep if `sweep` is
+    /// set and the config enables it. The sequence always ends by holding neutral.
+    pub(crate) fn new(
+        config: &ArmingConfig,
+        calibration: &Calibration,
+        sweep: bool,
+        now: Instant,
+    ) -> Self {
+        let mut steps = VecDeque::new();
+        if sweep && config.sweep() {
+            let sweep_time = Duration::from_millis(config.sweep_ms());
+            steps.push_back((calibration.max_pulse, sweep_time));
+            steps.push_back((calibration.min_pulse, sweep_time));
+        }
+        steps.push_back((
+            calibration.neutral_pulse,
+            Duration::from_millis(config.hold_ms()),
+        ));
+
+        Self {
+            steps,
+            step_started: now,
+        }
+    }
+
+    /// Returns the pulse width to output at `now`, or [`None`] once the sequence has finished.
+    pub(crate) fn pulse_width(&mut self, now: Instant) -> Option<Duration> {
+        while let Some((_, duration)) = self.steps.front() {
+            if now.duration_since(self.step_started) < *duration {
+                break;
+            }
+
+            // Each step starts when the one before it should have ended, however late this is
+            // called, so the sequence does not drift.
+            self.step_started += *duration;
+            self.steps.pop_front();
+        }
+
+        self.steps.front().map(|(pulse_width, _)| *pulse_width)
+    }
+
+    /// Returns the time until the next step should be taken.
+    pub(crate) fn until_next_step(&self, now: Instant) -> Duration {
+        match self.steps.front() {
+            Some((_, duration)) => duration.saturating_sub(now.duration_since(self.step_started)),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Skips the rest of the calibration sweep, if any, and starts holding neutral at `now`.
+    pub(crate) fn abort_sweep(&mut self, now: Instant) {
+        let sweep_steps = self.steps.len().saturating_sub(1);
+        if sweep_steps > 0 {
+            self.steps.drain(..sweep_steps);
+            self.step_started = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use config::{ArmingConfig, ArmingInstructions, ChannelConfig};
+
+    use super::ArmingSequence;
+    use crate::calibration::Calibration;
+
+    #[test]
+    fn sweeps_then_holds_neutral() {
+        let calibration = Calibration::new(&ChannelConfig::new(0, 0)).unwrap();
+        let config = ArmingConfig::new(100, true, 50, ArmingInstructions::Ignore);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut sequence = ArmingSequence::new(&config, &calibration, true, start);
+
+        assert_eq!(sequence.pulse_width(at(0)), Some(calibration.max_pulse));
+        assert_eq!(sequence.pulse_width(at(50)), Some(calibration.min_pulse));
+        assert_eq!(
+            sequence.pulse_width(at(100)),
+            Some(calibration.neutral_pulse)
+        );
+        assert_eq!(sequence.until_next_step(at(150)), Duration::from_millis(50));
+        assert_eq!(sequence.pulse_width(at(200)), None);
+    }
+
+    #[test]
+    fn late_steps_do_not_drift() {
+        let calibration = Calibration::new(&ChannelConfig::new(0, 0)).unwrap();
+        let config = ArmingConfig::new(100, true, 50, ArmingInstructions::Ignore);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut sequence = ArmingSequence::new(&config, &calibration, true, start);
+
+        // The step to the minimum is taken 20ms late, but it still ends 50ms after it was due.
+        assert_eq!(sequence.pulse_width(at(70)), Some(calibration.min_pulse));
+        assert_eq!(sequence.until_next_step(at(70)), Duration::from_millis(30));
+        assert_eq!(
+            sequence.pulse_width(at(100)),
+            Some(calibration.neutral_pulse)
+        );
+        // Several steps can be overdue at once.
+        let mut sequence = ArmingSequence::new(&config, &calibration, true, start);
+        assert_eq!(
+            sequence.pulse_width(at(120)),
+            Some(calibration.neutral_pulse)
+        );
+        assert_eq!(sequence.pulse_width(at(200)), None);
+    }
+
+    #[test]
+    fn abort_sweep_holds_neutral() {
+        let calibration = Calibration::new(&ChannelConfig::new(0, 0)).unwrap();
+        let config = ArmingConfig::new(100, true, 50, ArmingInstructions::Ignore);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut sequence = ArmingSequence::new(&config, &calibration, true, start);
+
+        sequence.abort_sweep(at(10));
+        assert_eq!(
+            sequence.pulse_width(at(10)),
+            Some(calibration.neutral_pulse)
+        );
+        assert_eq!(sequence.pulse_width(at(110)), None);
+    }
+}
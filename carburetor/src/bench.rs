@@ -0,0 +1,407 @@
+//! Subcommands for checking the wiring of channels on the bench, without writing robot code.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use config::{CarburetorConfig, ChannelConfig};
+
+use crate::calibration::Calibration;
+use crate::control_channel::{ChannelCommand, ControlChannel};
+use crate::controls::Controls;
+use crate::estop::EStop;
+use crate::instruction::Speed;
+use crate::report::{ChannelStateHandle, Reporter};
+use crate::shutdown::{self, ChannelThread};
+
+/// The interval at which a new speed is sent during a sweep.
+const SWEEP_STEP: Duration = Duration::from_millis(20);
+
+/// Prints every configured channel, digital output and digital input.
+pub(crate) fn list_channels(config: &CarburetorConfig) -> Result<(), Box<dyn Error>> {
+    write_channels(config, &mut io::stdout().lock())?;
+    Ok(())
+}
+
+/// Writes the listing of [`list_channels`] to `out`.
+fn write_channels(config: &CarburetorConfig, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "Output backend: {:?}", config.backend())?;
+
+    for channel in config.channels() {
+        let mut line = format!(
+            "Channel {}: PWM channel {}, {} Hz, {}/{}/{} µs",
+            channel.channel(),
+            channel.pwm_channel(),
+            channel.frequency_hz(),
+            channel.min_pulse_us(),
+            channel.neutral_pulse_us(),
+            channel.max_pulse_us()
+        );
+        if channel.inverted() {
+            line.push_str(", inverted");
+        }
+        if let Some(max_acceleration) = channel.max_acceleration() {
+            line.push_str(&format!(", max acceleration {max_acceleration}/s"));
+        }
+        if let Some(servo) = channel.servo() {
+            line.push_str(&format!(
+                ", servo from {}° to {}°",
+                servo.min_angle_deg(),
+                servo.max_angle_deg()
+            ));
+        }
+        if channel.arming().is_enabled() {
+            line.push_str(&format!(", arms in {} ms", channel.arming().hold_ms()));
+            if channel.arming().sweep() {
+                line.push_str(" after a sweep");
+            }
+        }
+        writeln!(out, "{line}")?;
+    }
+
+    for output in config.digital_outputs() {
+        writeln!(
+            out,
+            "Digital output {}: GPIO pin {}, safe state {}",
+            output.pin(),
+            output.gpio_pin(),
+            if output.safe_state() { "on" } else { "off" }
+        )?;
+    }
+
+    for input in config.digital_inputs() {
+        writeln!(
+            out,
+            "Digital input {}: GPIO pin {}, pull {:?}, debounced for {} ms",
+            input.pin(),
+            input.gpio_pin(),
+            input.pull(),
+            input.debounce_ms()
+        )?;
+    }
+
+    for input in config.analog_inputs() {
+        writeln!(
+            out,
+            "Analog input {}: channel {} of IIO device {}, full scale at {}, reported on changes of {}",
+            input.pin(),
+            input.iio_channel(),
+            input.iio_device(),
+            input.max_raw(),
+            input.threshold()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Outputs `pulse_width` on `channel` until the process is interrupted.
+pub(crate) fn pulse(
+    config: &CarburetorConfig,
+    channel: u8,
+    pulse_width: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let channel_config = find_channel(config, channel)?;
+    let calibration = Calibration::new(channel_config)?;
+    if pulse_width < calibration.min_pulse || pulse_width > calibration.max_pulse {
+        return Err(format!(
+            "pulse width {pulse_width:?} is outside of the range {:?}..={:?} of channel {channel}",
+            calibration.min_pulse, calibration.max_pulse
+        )
+        .into());
+    }
+
+    run_pulse(BenchChannel::start(config, channel_config)?, pulse_width)
+}
+
+/// Outputs `pulse_width` on `bench` until it is interrupted, and puts it back in neutral.
+fn run_pulse(bench: BenchChannel, pulse_width: Duration) -> Result<(), Box<dyn Error>> {
+    let channel = bench.channel;
+    if bench.wait_until_armed() {
+        log::info!("Outputting {pulse_width:?} on channel {channel}. Press Ctrl-C to stop.");
+        bench.send(ChannelCommand::Pulse(pulse_width))?;
+
+        while !bench.is_interrupted() {
+            thread::park();
+        }
+    }
+
+    bench.stop()
+}
+
+/// Sweeps `channel` from neutral to `max_speed` forward, to `max_speed` reverse, and back to
+/// neutral, taking `duration` in total.
+pub(crate) fn sweep(
+    config: &CarburetorConfig,
+    channel: u8,
+    max_speed: f32,
+    duration: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let channel_config = find_channel(config, channel)?;
+    if !(0.0..=1.0).contains(&max_speed) {
+        return Err(format!("max speed {max_speed} is not within 0.0..=1.0").into());
+    }
+
+    run_sweep(
+        BenchChannel::start(config, channel_config)?,
+        max_speed,
+        duration,
+    )
+}
+
+/// Sweeps `bench` as described in [`sweep`], and puts it back in neutral.
+fn run_sweep(
+    bench: BenchChannel,
+    max_speed: f32,
+    duration: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let channel = bench.channel;
+    if bench.wait_until_armed() {
+        log::info!("Sweeping channel {channel} up to {max_speed} in both directions...");
+        let started = Instant::now();
+        while started.elapsed() < duration && !bench.is_interrupted() {
+            let progress = started.elapsed().as_secs_f32() / duration.as_secs_f32();
+            let speed =
+                Speed::new(max_speed * triangle(progress)).expect("sweep should stay in range");
+            bench.send(ChannelCommand::Speed(speed))?;
+            thread::sleep(SWEEP_STEP);
+        }
+    }
+
+    bench.stop()
+}
+
+fn find_channel(config: &CarburetorConfig, channel: u8) -> Result<&ChannelConfig, String> {
+    config
+        .channels()
+        .iter()
+        .find(|channel_config| channel_config.channel() == channel)
+        .ok_or_else(|| format!("channel {channel} is not configured"))
+}
+
+/// A single channel under test, which is put back in neutral when the test is stopped.
+struct BenchChannel {
+    channel: u8,
+    controls: Controls,
+    state: ChannelStateHandle,
+    thread: ChannelThread,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl BenchChannel {
+    /// Opens the channel described by `channel_config` and starts its control thread. When the
+    /// process is interrupted, the calling thread is unparked.
+    fn start(
+        config: &CarburetorConfig,
+        channel_config: &ChannelConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        // Bench tests must not release the latch, so it is always treated as surviving the
+        // restart.
+        let estop_path = config::carburetor_estop_path().ok();
+        let estop = EStop::new(estop_path, true);
+        if estop.is_latched() {
+            return Err(
+                "the emergency stop is latched, release it before testing on the bench".into(),
+            );
+        }
+
+        let control = ControlChannel::open(config, channel_config, Reporter::default())?;
+        let interrupted = shutdown::handle_signals({
+            let current = thread::current();
+            move || current.unpark()
+        });
+        Ok(Self::new(control, estop, interrupted))
+    }
+
+    /// Starts the control thread of `control`, which is stopped once `interrupted` is set.
+    fn new(control: ControlChannel, estop: EStop, interrupted: Arc<AtomicBool>) -> Self {
+        let channel = control.channel();
+        let state = control.state();
+
+        let (tx, rx) = mpsc::channel();
+        let controls = Controls {
+            channels: HashMap::from([(channel, control.handle(tx))]),
+            digital_outputs: Mutex::default(),
+            estop,
+        };
+        let thread = (channel, thread::spawn(move || control.run(rx)));
+
+        Self {
+            channel,
+            controls,
+            state,
+            thread,
+            interrupted,
+        }
+    }
+
+    fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until the channel has finished its arming sequence, if it has one. Returns whether
+    /// it did so without being interrupted.
+    fn wait_until_armed(&self) -> bool {
+        if self.state.lock().unwrap().arming {
+            log::info!("Waiting for the channel to arm...");
+        }
+        while self.state.lock().unwrap().arming && !self.is_interrupted() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        !self.is_interrupted()
+    }
+
+    fn send(&self, command: ChannelCommand) -> Result<(), Box<dyn Error>> {
+        self.controls.channels[&self.channel].send(command)?;
+        Ok(())
+    }
+
+    /// Puts the channel back in neutral, and waits for it to confirm that.
+    fn stop(self) -> Result<(), Box<dyn Error>> {
+        log::info!("Stopping channel {}...", self.channel);
+        shutdown::stop_channels(&self.controls, vec![self.thread])
+    }
+}
+
+/// A triangle wave that goes from 0.0 at `progress` 0.0, to 1.0 at 0.25, to -1.0 at 0.75, and back
+/// to 0.0 at 1.0.
+fn triangle(progress: f32) -> f32 {
+    match progress {
+        p if p < 0.25 => 4.0 * p,
+        p if p < 0.75 => 2.0 - 4.0 * p,
+        p => 4.0 * p - 4.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use config::{CarburetorConfig, ChannelConfig, WatchdogConfig};
+
+    use super::{run_pulse, run_sweep, triangle, write_channels, BenchChannel};
+    use crate::calibration::Calibration;
+    use crate::control_channel::ControlChannel;
+    use crate::estop::EStop;
+    use crate::instruction::Speed;
+    use crate::output::sim::SimPwm;
+    use crate::report::Reporter;
+
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Opens channel 0 on a simulated output, which is returned to inspect what was written.
+    fn bench_channel(interrupted: Arc<AtomicBool>) -> (BenchChannel, SimPwm, Calibration) {
+        let calibration = Calibration::new(&ChannelConfig::new(0, 0)).unwrap();
+        let pwm = SimPwm::open(0, calibration.period, calibration.neutral_pulse);
+        let control = ControlChannel::new(
+            0,
+            calibration.clone(),
+            Box::new(pwm.clone()),
+            Reporter::default(),
+            &WatchdogConfig::default(),
+        );
+        let bench = BenchChannel::new(control, EStop::new(None, false), interrupted);
+        (bench, pwm, calibration)
+    }
+
+    #[test]
+    fn pulse_holds_until_interrupted() {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let (bench, pwm, calibration) = bench_channel(Arc::clone(&interrupted));
+        let pulse_width = Duration::from_micros(1700);
+
+        // Interrupts the bench test like Ctrl-C would, once the pulse has been output.
+        let writes = pwm.watch();
+        let current = thread::current();
+        let interrupter = thread::spawn(move || {
+            let written = writes.recv_timeout(WRITE_TIMEOUT);
+            interrupted.store(true, Ordering::SeqCst);
+            current.unpark();
+            written
+        });
+
+        run_pulse(bench, pulse_width).unwrap();
+        assert_eq!(interrupter.join().unwrap(), Ok(pulse_width));
+        let state = pwm.state();
+        let state = state.lock().unwrap();
+        assert!(state.history.contains(&pulse_width));
+        assert_eq!(state.pulse_width(), Some(calibration.neutral_pulse));
+    }
+
+    #[test]
+    fn sweep_goes_both_ways_and_back_to_neutral() {
+        let (bench, pwm, calibration) = bench_channel(Arc::default());
+        let max_speed = 0.5;
+
+        run_sweep(bench, max_speed, Duration::from_millis(200)).unwrap();
+        let forward = calibration.pulse_width(Speed::new(max_speed).unwrap());
+        let reverse = calibration.pulse_width(Speed::new(-max_speed).unwrap());
+        let state = pwm.state();
+        let state = state.lock().unwrap();
+        assert!(state
+            .history
+            .iter()
+            .all(|pulse_width| (reverse..=forward).contains(pulse_width)));
+        // It goes forward first.
+        let first_forward = state
+            .history
+            .iter()
+            .position(|pulse_width| *pulse_width > calibration.neutral_pulse);
+        let first_reverse = state
+            .history
+            .iter()
+            .position(|pulse_width| *pulse_width < calibration.neutral_pulse);
+        assert!(matches!(
+            (first_forward, first_reverse),
+            (Some(forward), Some(reverse)) if forward < reverse
+        ));
+        assert_eq!(state.pulse_width(), Some(calibration.neutral_pulse));
+    }
+
+    #[test]
+    fn lists_everything_configured() {
+        let config: CarburetorConfig = serde_json::from_value(serde_json::json!({
+            "port": 12362,
+            "logger_port": 12363,
+            "backend": "sim",
+            "channels": [
+                { "channel": 0, "pwm_channel": 1, "inverted": true },
+                { "channel": 1, "pwm_channel": 0, "servo": { "max_angle_deg": 90.0 } },
+            ],
+            "digital_outputs": [{ "pin": 0, "gpio_pin": 17, "safe_state": true }],
+            "digital_inputs": [{ "pin": 1, "gpio_pin": 27 }],
+            "analog_inputs": [{ "pin": 2, "iio_channel": 3 }],
+        }))
+        .unwrap();
+
+        let mut out = Vec::new();
+        write_channels(&config, &mut out).unwrap();
+        let listing = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = listing.lines().collect();
+        assert_eq!(lines.len(), 6, "{listing}");
+        assert_eq!(lines[0], "Output backend: Sim");
+        assert!(lines[1].starts_with("Channel 0: PWM channel 1, 50 Hz, 1000/1500/2000 µs"));
+        assert!(lines[1].ends_with(", inverted"));
+        assert!(lines[2].ends_with(", servo from 0° to 90°"), "{}", lines[2]);
+        assert_eq!(lines[3], "Digital output 0: GPIO pin 17, safe state on");
+        assert!(lines[4].starts_with("Digital input 1: GPIO pin 27"));
+        assert!(lines[5].starts_with("Analog input 2: channel 3 of IIO device 0"));
+    }
+
+    #[test]
+    fn triangle_sweeps_both_directions() {
+        assert_eq!(triangle(0.0), 0.0);
+        assert_eq!(triangle(0.25), 1.0);
+        assert_eq!(triangle(0.5), 0.0);
+        assert_eq!(triangle(0.75), -1.0);
+        assert_eq!(triangle(1.0), 0.0);
+    }
+}
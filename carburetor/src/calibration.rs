@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+use config::ChannelConfig;
+use messaging::{FaultKind, ServoTarget};
+
+use crate::instruction::Speed;
+
+/// Converts [`Speed`]s into pulse widths for a single channel.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Calibration {
+    pub(crate) period: Duration,
+    pub(crate) min_pulse: Duration,
+    pub(crate) neutral_pulse: Duration,
+    pub(crate) max_pulse: Duration,
+    pub(crate) deadband: f32,
+    pub(crate) inverted: bool,
+    pub(crate) max_output: f32,
+    pub(crate) servo: Option<ServoCalibration>,
+}
+
+/// Converts servo angles into pulse widths, on top of a [`Calibration`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ServoCalibration {
+    pub(crate) min_angle: f32,
+    pub(crate) max_angle: f32,
+    pub(crate) us_per_degree: f32,
+}
+
+impl Calibration {
+    /// Creates a [`Calibration`] from a [`ChannelConfig`].
+    ///
+    /// Returns an error describing the problem if the configuration is not usable.
+    pub(crate) fn new(config: &ChannelConfig) -> Result<Self, String> {
+        let channel = config.channel();
+
+        if !config.frequency_hz().is_finite() || config.frequency_hz() <= 0.0 {
+            return Err(format!(
+                "channel {channel}: frequency_hz must be positive, got {}",
+                config.frequency_hz()
+            ));
+        }
+
+        if !(config.min_pulse_us() <= config.neutral_pulse_us()
+            && config.neutral_pulse_us() <= config.max_pulse_us())
+        {
+            return Err(format!(
+                "channel {channel}: expected min_pulse_us <= neutral_pulse_us <= max_pulse_us, got {} <= {} <= {}",
+                config.min_pulse_us(),
+                config.neutral_pulse_us(),
+                config.max_pulse_us()
+            ));
+        }
+
+        let period = Duration::from_nanos((1e9 / config.frequency_hz() as f64).round() as u64);
+        let max_pulse = Duration::from_micros(config.max_pulse_us() as u64);
+        if max_pulse > period {
+            return Err(format!(
+                "channel {channel}: max_pulse_us ({max_pulse:?}) does not fit in a period at {}Hz ({period:?})",
+                config.frequency_hz()
+            ));
+        }
+
+        if !(0.0..1.0).contains(&config.deadband()) {
+            return Err(format!(
+                "channel {channel}: deadband must be within 0.0..1.0, got {}",
+                config.deadband()
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&config.max_output()) {
+            return Err(format!(
+                "channel {channel}: max_output must be within 0.0..=1.0, got {}",
+                config.max_output()
+            ));
+        }
+
+        if let Some(max_acceleration) = config.max_acceleration() {
+            if !max_acceleration.is_finite() || max_acceleration <= 0.0 {
+                return Err(format!(
+                    "channel {channel}: max_acceleration must be positive, got {max_acceleration}"
+                ));
+            }
+        }
+
+        let servo = match config.servo() {
+            Some(servo) => {
+                let (min_angle, max_angle) = (servo.min_angle_deg(), servo.max_angle_deg());
+                if !(min_angle.is_finite() && max_angle.is_finite() && min_angle < max_angle) {
+                    return Err(format!(
+                        "channel {channel}: expected min_angle_deg < max_angle_deg, got {min_angle} < {max_angle}"
+                    ));
+                }
+
+                let pulse_range = (config.max_pulse_us() - config.min_pulse_us()) as f32;
+                let us_per_degree = servo
+                    .us_per_degree()
+                    .unwrap_or(pulse_range / (max_angle - min_angle));
+                if !us_per_degree.is_finite() || us_per_degree <= 0.0 {
+                    return Err(format!(
+                        "channel {channel}: us_per_degree must be positive, got {us_per_degree}"
+                    ));
+                }
+                if us_per_degree * (max_angle - min_angle) > pulse_range + 0.5 {
+                    return Err(format!(
+                        "channel {channel}: {min_angle}..={max_angle} degrees at {us_per_degree}µs per degree does not fit in min_pulse_us..=max_pulse_us"
+                    ));
+                }
+
+                Some(ServoCalibration {
+                    min_angle,
+                    max_angle,
+                    us_per_degree,
+                })
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            period,
+            min_pulse: Duration::from_micros(config.min_pulse_us() as u64),
+            neutral_pulse: Duration::from_micros(config.neutral_pulse_us() as u64),
+            max_pulse,
+            deadband: config.deadband(),
+            inverted: config.inverted(),
+            max_output: config.max_output(),
+            servo,
+        })
+    }
+
+    /// Returns the pulse width to output for `speed`.
+    ///
+    /// Speeds outside of the deadband are scaled onto the full range, so the output rises
+    /// smoothly from neutral at the edge of the deadband instead of jumping.
+    pub(crate) fn pulse_width(&self, speed: Speed) -> Duration {
+        let value = speed.value();
+
+        if value.abs() <= self.deadband {
+            return self.neutral_pulse;
+        }
+
+        let mut value = value.signum() * (value.abs() - self.deadband) / (1.0 - self.deadband);
+
+        if self.inverted {
+            value = -value;
+        }
+
+        let value = value.clamp(-self.max_output, self.max_output);
+        let range = if value >= 0.0 {
+            self.max_pulse - self.neutral_pulse
+        } else {
+            self.neutral_pulse - self.min_pulse
+        };
+
+        let micros = self.neutral_pulse.as_micros() as f32 + range.as_micros() as f32 * value;
+        Duration::from_micros(micros.round() as u64)
+    }
+
+    /// Returns the pulse width to output to move the servo to `target`.
+    ///
+    /// Returns [`FaultKind::NotAServo`] if this is not a servo channel, and
+    /// [`FaultKind::OutOfRangePosition`] if `target` is outside of its angle range.
+    pub(crate) fn servo_pulse_width(&self, target: ServoTarget) -> Result<Duration, FaultKind> {
+        let Some(servo) = &self.servo else {
+            return Err(FaultKind::NotAServo);
+        };
+
+        let angle = match target {
+            ServoTarget::Degrees(angle) => angle,
+            ServoTarget::Position(position) if (0.0..=1.0).contains(&position) => {
+                servo.min_angle + (servo.max_angle - servo.min_angle) * position
+            }
+            ServoTarget::Position(_) => return Err(FaultKind::OutOfRangePosition),
+        };
+        if !(servo.min_angle..=servo.max_angle).contains(&angle) {
+            return Err(FaultKind::OutOfRangePosition);
+        }
+
+        let mut offset = angle - servo.min_angle;
+        if self.inverted {
+            offset = servo.max_angle - angle;
+        }
+
+        let micros = self.min_pulse.as_micros() as f32 + offset * servo.us_per_degree;
+        let pulse_width = Duration::from_micros(micros.round() as u64);
+        Ok(pulse_width.clamp(self.min_pulse, self.max_pulse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use config::{ChannelConfig, ServoConfig};
+    use messaging::{FaultKind, ServoTarget};
+
+    use super::Calibration;
+    use crate::instruction::Speed;
+
+    fn spark() -> Calibration {
+        Calibration::new(&ChannelConfig::new(0, 0)).unwrap()
+    }
+
+    #[test]
+    fn spark_pulse_widths() {
+        let calibration = spark();
+        assert_eq!(calibration.period, Duration::from_millis(20));
+        assert_eq!(
+            calibration.pulse_width(Speed::backward()),
+            Duration::from_micros(1000)
+        );
+        assert_eq!(
+            calibration.pulse_width(Speed::neutral()),
+            Duration::from_micros(1500)
+        );
+        assert_eq!(
+            calibration.pulse_width(Speed::forward()),
+            Duration::from_micros(2000)
+        );
+    }
+
+    #[test]
+    fn asymmetric_range() {
+        let calibration = Calibration {
+            min_pulse: Duration::from_micros(1000),
+            neutral_pulse: Duration::from_micros(1600),
+            max_pulse: Duration::from_micros(2000),
+            ..spark()
+        };
+        let half = |value| calibration.pulse_width(Speed::new(value).unwrap());
+        assert_eq!(half(0.5), Duration::from_micros(1800));
+        assert_eq!(half(-0.5), Duration::from_micros(1300));
+    }
+
+    #[test]
+    fn inverted_deadband_and_max_output() {
+        let calibration = Calibration {
+            deadband: 0.1,
+            inverted: true,
+            max_output: 0.5,
+            ..spark()
+        };
+        let pulse = |value| calibration.pulse_width(Speed::new(value).unwrap());
+        assert_eq!(pulse(0.05), Duration::from_micros(1500));
+        assert_eq!(pulse(-0.05), Duration::from_micros(1500));
+        assert_eq!(pulse(1.0), Duration::from_micros(1250));
+        assert_eq!(pulse(-1.0), Duration::from_micros(1750));
+    }
+
+    #[test]
+    fn rescales_outside_deadband() {
+        let calibration = Calibration {
+            deadband: 0.2,
+            ..spark()
+        };
+        let pulse = |value| calibration.pulse_width(Speed::new(value).unwrap());
+        assert_eq!(pulse(0.2), Duration::from_micros(1500));
+        // Just outside of the deadband is still close to neutral.
+        assert_eq!(pulse(0.21), Duration::from_micros(1506));
+        assert_eq!(pulse(0.6), Duration::from_micros(1750));
+        assert_eq!(pulse(-0.6), Duration::from_micros(1250));
+        assert_eq!(pulse(1.0), Duration::from_micros(2000));
+    }
+
+    #[test]
+    fn servo_angles() {
+        let config = ChannelConfig::new(0, 0).with_servo(ServoConfig::new(-90.0, 90.0, None));
+        let calibration = Calibration::new(&config).unwrap();
+        let pulse = |target| calibration.servo_pulse_width(target);
+
+        assert_eq!(
+            pulse(ServoTarget::Degrees(-90.0)),
+            Ok(Duration::from_micros(1000))
+        );
+        assert_eq!(
+            pulse(ServoTarget::Degrees(45.0)),
+            Ok(Duration::from_micros(1750))
+        );
+        assert_eq!(
+            pulse(ServoTarget::Position(0.5)),
+            Ok(Duration::from_micros(1500))
+        );
+        assert_eq!(
+            pulse(ServoTarget::Degrees(91.0)),
+            Err(FaultKind::OutOfRangePosition)
+        );
+        assert_eq!(
+            pulse(ServoTarget::Position(1.5)),
+            Err(FaultKind::OutOfRangePosition)
+        );
+        assert_eq!(
+            spark().servo_pulse_width(ServoTarget::Position(0.5)),
+            Err(FaultKind::NotAServo)
+        );
+    }
+
+    #[test]
+    fn servo_range_must_fit() {
+        let config = ChannelConfig::new(0, 0).with_servo(ServoConfig::new(0.0, 180.0, Some(10.0)));
+        assert!(Calibration::new(&config).is_err());
+    }
+}
@@ -0,0 +1,765 @@
+//! Handling a connection from Linkage.
+
+use std::collections::HashSet;
+use std::io::{self, ErrorKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use config::MalformedFramePolicy;
+use messaging::{
+    CarburetorToLinkage, Component, FaultKind, FrameReader, Handshake, LinkageToCarburetor,
+    LinkageToCarburetorFrame, MessageError, MotorBatch, Stamp, CONNECTION_FAULT_CHANNEL,
+    LINKAGE_TO_CARBURETOR_ENCODING,
+};
+
+use crate::control_channel::ChannelCommand;
+use crate::controls::Controls;
+use crate::instruction::Speed;
+use crate::liveness::{Liveness, BEAT_INTERVAL};
+use crate::metrics::Metrics;
+use crate::report::Reporter;
+use crate::stream::{ListenAddress, Listener, Stream};
+
+/// No frame from Linkage is longer than a batch for every channel.
+pub(crate) const MAX_PAYLOAD_LEN: usize = 256 * MotorBatch::SPEED_LEN;
+
+/// How long Linkage gets to send its handshake after connecting.
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Returns the handshake Carburetor introduces itself with.
+pub(crate) fn own_handshake() -> Handshake {
+    Handshake::new(Component::Carburetor, env!("CARGO_PKG_VERSION"))
+}
+
+/// Exchanges handshakes with Linkage over `stream`, and returns the one of Linkage.
+///
+/// Returns an error if Linkage does not send a handshake in time, or speaks another version of the
+/// protocol.
+pub(crate) fn handshake(stream: &mut Stream) -> io::Result<Handshake> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let linkage = own_handshake().exchange(stream, Component::LinkageLib)?;
+    stream.set_read_timeout(None)?;
+    Ok(linkage)
+}
+
+/// A connection from Linkage that completed its handshake.
+pub(crate) struct Connected {
+    /// The number of the connection, counting every accepted stream.
+    pub(crate) n: usize,
+    pub(crate) stream: Stream,
+    pub(crate) linkage: Handshake,
+}
+
+/// Accepts the connections on `server` until `shutdown` is set, and exchanges handshakes with each
+/// of them on its own thread, so a client that stays silent does not hold up the others. Returns
+/// the connections that completed their handshake, and the thread that accepts them.
+pub(crate) fn start_acceptor(
+    server: Listener,
+    address: ListenAddress,
+    shutdown: Arc<AtomicBool>,
+    metrics: Arc<Metrics>,
+) -> (Receiver<Connected>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel();
+    let acceptor = thread::spawn(move || {
+        for (n, stream) in server.incoming().enumerate() {
+            if shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::error!("({n}) Failed to accept connection: {e}");
+                    continue;
+                }
+            };
+            match stream.peer_addr() {
+                Some(peer) => log::info!("({n}) Received stream from {peer} on {address}."),
+                None => log::info!("({n}) Received stream on {address}."),
+            }
+
+            let tx = tx.clone();
+            let metrics = Arc::clone(&metrics);
+            thread::spawn(move || match handshake(&mut stream) {
+                Ok(linkage) => _ = tx.send(Connected { n, stream, linkage }),
+                Err(e) => {
+                    log::error!("({n}) Refusing the connection: {e}.");
+                    if let Some(error) = e.get_ref().and_then(|e| e.downcast_ref::<MessageError>())
+                    {
+                        metrics.handshake_refused(error);
+                    }
+                }
+            });
+        }
+    });
+    (rx, acceptor)
+}
+
+/// Carries out the instructions Linkage sends over `stream`, until the connection is closed, and
+/// records them in `metrics`. While it waits for instructions, it beats `liveness` every
+/// [`BEAT_INTERVAL`].
+///
+/// Returns an error if reading from the stream fails for any other reason than the connection
+/// being closed.
+pub(crate) fn handle_connection(
+    stream: Stream,
+    controls: &Controls,
+    reporter: &Reporter,
+    policy: MalformedFramePolicy,
+    metrics: &Metrics,
+    liveness: &Liveness,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(BEAT_INTERVAL))?;
+    let mut reader = FrameReader::new(stream, LINKAGE_TO_CARBURETOR_ENCODING)
+        .with_max_payload_len(MAX_PAYLOAD_LEN);
+    loop {
+        liveness.beat(BEAT_INTERVAL);
+        let message = reader.read_message::<LinkageToCarburetorFrame>();
+        if let (Ok(_), Some(stamp)) = (&message, reader.stamp()) {
+            handle_stamp(stamp, reporter, metrics);
+        }
+
+        let e = match message {
+            Ok(LinkageToCarburetorFrame::Message(message)) => {
+                handle_message(message, controls, reporter, metrics);
+                continue;
+            }
+            Ok(LinkageToCarburetorFrame::MotorBatch(batch)) => {
+                handle_batch(batch, controls, reporter, metrics);
+                continue;
+            }
+            // The reader keeps what it read so far, so it can simply carry on.
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            // If the connection was closed, we are done.
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => e,
+        };
+
+        let Some(error) = e.get_ref().and_then(|e| e.downcast_ref::<MessageError>()) else {
+            return Err(e);
+        };
+        let count = metrics.malformed_frame(error);
+        log::warn!("Received malformed frame: {error} ({count} like it so far).");
+        reporter.fault(CONNECTION_FAULT_CHANNEL, FaultKind::MalformedFrame);
+
+        // The reader skips ahead to the next valid frame by itself.
+        if policy == MalformedFramePolicy::Disconnect {
+            log::warn!("Dropping the connection because of a malformed frame.");
+            return Ok(());
+        }
+    }
+}
+
+/// Records the `stamp` of a frame from Linkage in `metrics`, and echoes it back, so Linkage can
+/// measure the round trip.
+pub(crate) fn handle_stamp(stamp: Stamp, reporter: &Reporter, metrics: &Metrics) {
+    metrics.stamp_received(stamp);
+    reporter.report(CarburetorToLinkage::Echo {
+        sent_at_us: stamp.sent_at_us,
+    });
+}
+
+/// Carries out a `batch` of speeds from Linkage, and records it in `metrics`.
+///
+/// Every channel in the batch is checked before any of them is changed, and the ones that pass are
+/// sent to their control threads back to back. That is as close together as they change: the
+/// batch is not applied atomically, and a control thread that has stopped does not hold up the
+/// others. A channel that appears more than once is refused as a whole, as it is unclear which
+/// speed was meant.
+pub(crate) fn handle_batch(
+    batch: MotorBatch,
+    controls: &Controls,
+    reporter: &Reporter,
+    metrics: &Metrics,
+) {
+    log::trace!("Received batch: {batch:?}");
+    metrics.batch_received(batch.speeds.iter().map(|(channel, _)| *channel));
+
+    if controls.estop.is_latched() {
+        log::warn!("Refusing {batch:?}, because the emergency stop is latched.");
+        for (channel, _) in batch.speeds {
+            metrics.instruction_rejected();
+            reporter.fault(channel, FaultKind::EStopLatched);
+        }
+        return;
+    }
+
+    let mut seen = HashSet::new();
+    let duplicates: HashSet<_> = batch
+        .speeds
+        .iter()
+        .map(|(channel, _)| *channel)
+        .filter(|channel| !seen.insert(*channel))
+        .collect();
+    for channel in &duplicates {
+        log::error!("Channel {channel} appears more than once in a batch.");
+    }
+
+    let mut commands = Vec::with_capacity(batch.speeds.len());
+    for (channel, speed) in batch.speeds {
+        if duplicates.contains(&channel) {
+            metrics.instruction_rejected();
+            reporter.fault(channel, FaultKind::DuplicateChannel);
+            continue;
+        }
+
+        let Some(handle) = controls.channels.get(&channel) else {
+            metrics.instruction_rejected();
+            log::error!("Instruction channel {channel} does not exist.");
+            reporter.fault(channel, FaultKind::UnknownChannel);
+            continue;
+        };
+
+        let Some(speed) = Speed::new(speed) else {
+            metrics.instruction_rejected();
+            log::error!("Instruction speed {speed} for channel {channel} is out of range.");
+            reporter.fault(channel, FaultKind::OutOfRangeSpeed);
+            continue;
+        };
+
+        if handle.ignores_speeds() {
+            metrics.instruction_rejected();
+            log::warn!("Refusing speed {speed} for channel {channel}, which is still arming.");
+            reporter.fault(channel, FaultKind::ChannelArming);
+            continue;
+        }
+
+        commands.push((channel, handle, speed));
+    }
+
+    for (channel, handle, speed) in commands {
+        if handle.send(ChannelCommand::Speed(speed)).is_err() {
+            metrics.instruction_rejected();
+            log::error!("Control thread for channel {channel} is no longer running.");
+            reporter.fault(channel, FaultKind::BackendError);
+            continue;
+        }
+
+        reporter.report(CarburetorToLinkage::InstructionAck {
+            instruction: MotorBatch::OPCODE,
+            channel,
+        });
+    }
+}
+
+/// Carries out a single `message` from Linkage, and records it in `metrics`.
+pub(crate) fn handle_message(
+    message: LinkageToCarburetor,
+    controls: &Controls,
+    reporter: &Reporter,
+    metrics: &Metrics,
+) {
+    log::trace!("Received message: {message:?}");
+    match message {
+        LinkageToCarburetor::MotorInstruction { channel, .. }
+        | LinkageToCarburetor::ServoPosition { channel, .. } => {
+            metrics.message_received(Some(channel))
+        }
+        _ => metrics.message_received(None),
+    }
+
+    let latched = controls.estop.is_latched();
+    match message {
+        LinkageToCarburetor::MotorInstruction { channel, .. }
+        | LinkageToCarburetor::DigitalOutput { pin: channel, .. }
+        | LinkageToCarburetor::ServoPosition { channel, .. }
+            if latched =>
+        {
+            metrics.instruction_rejected();
+            log::warn!("Refusing {message:?}, because the emergency stop is latched.");
+            reporter.fault(channel, FaultKind::EStopLatched);
+        }
+        LinkageToCarburetor::MotorInstruction { channel, speed } => {
+            let Some(handle) = controls.channels.get(&channel) else {
+                metrics.instruction_rejected();
+                log::error!("Instruction channel {channel} does not exist.");
+                reporter.fault(channel, FaultKind::UnknownChannel);
+                return;
+            };
+
+            let Some(speed) = Speed::new(speed) else {
+                metrics.instruction_rejected();
+                log::error!("Instruction speed {speed} for channel {channel} is out of range.");
+                reporter.fault(channel, FaultKind::OutOfRangeSpeed);
+                return;
+            };
+
+            if handle.ignores_speeds() {
+                metrics.instruction_rejected();
+                log::warn!("Refusing speed {speed} for channel {channel}, which is still arming.");
+                reporter.fault(channel, FaultKind::ChannelArming);
+                return;
+            }
+
+            if handle.send(ChannelCommand::Speed(speed)).is_err() {
+                metrics.instruction_rejected();
+                log::error!("Control thread for channel {channel} is no longer running.");
+                reporter.fault(channel, FaultKind::BackendError);
+                return;
+            }
+
+            reporter.report(CarburetorToLinkage::InstructionAck {
+                instruction: message.opcode(),
+                channel,
+            });
+        }
+        LinkageToCarburetor::ServoPosition { channel, target } => {
+            let Some(handle) = controls.channels.get(&channel) else {
+                metrics.instruction_rejected();
+                log::error!("Servo channel {channel} does not exist.");
+                reporter.fault(channel, FaultKind::UnknownChannel);
+                return;
+            };
+
+            if let Err(kind) = handle.calibration.servo_pulse_width(target) {
+                metrics.instruction_rejected();
+                log::error!("Cannot move channel {channel} to {target:?}: {kind:?}.");
+                reporter.fault(channel, kind);
+                return;
+            }
+
+            // Servos hold neutral until they are armed, whatever the arming config says.
+            if handle.is_arming() {
+                metrics.instruction_rejected();
+                log::warn!("Refusing {message:?}, because channel {channel} is still arming.");
+                reporter.fault(channel, FaultKind::ChannelArming);
+                return;
+            }
+
+            if handle.send(ChannelCommand::Position(target)).is_err() {
+                metrics.instruction_rejected();
+                log::error!("Control thread for channel {channel} is no longer running.");
+                reporter.fault(channel, FaultKind::BackendError);
+                return;
+            }
+
+            reporter.report(CarburetorToLinkage::InstructionAck {
+                instruction: message.opcode(),
+                channel,
+            });
+        }
+        LinkageToCarburetor::DigitalOutput { pin, value } => {
+            if let Err(kind) = controls.digital_outputs.lock().unwrap().set(pin, value) {
+                metrics.instruction_rejected();
+                reporter.fault(pin, kind);
+                return;
+            }
+
+            reporter.report(CarburetorToLinkage::InstructionAck {
+                instruction: message.opcode(),
+                channel: pin,
+            });
+        }
+        LinkageToCarburetor::EStop => {
+            controls.estop.latch();
+            controls.stop();
+            report_estop(reporter, message, true);
+        }
+        LinkageToCarburetor::ResetEStop => {
+            controls.estop.reset();
+            report_estop(reporter, message, false);
+        }
+    }
+}
+
+/// Acknowledges `message`, and reports the new state of the emergency stop.
+fn report_estop(reporter: &Reporter, message: LinkageToCarburetor, latched: bool) {
+    reporter.report(CarburetorToLinkage::InstructionAck {
+        instruction: message.opcode(),
+        channel: 0,
+    });
+    reporter.report(CarburetorToLinkage::EStopStatus { latched });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc::channel;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Instant;
+
+    use config::{ArmingInstructions, ChannelConfig, MalformedFramePolicy, ServoConfig};
+    use messaging::{
+        CarburetorToLinkage, Component, FaultKind, FramedMessage, Handshake, LinkageToCarburetor,
+        Message, MotorBatch, ServoTarget, Stamp, CONNECTION_FAULT_CHANNEL,
+        LINKAGE_TO_CARBURETOR_ENCODING,
+    };
+
+    use super::{
+        handle_batch, handle_connection, handle_message, handle_stamp, handshake, own_handshake,
+    };
+    use crate::calibration::Calibration;
+    use crate::control_channel::ChannelCommand;
+    use crate::controls::{ChannelHandle, Controls};
+    use crate::estop::EStop;
+    use crate::instruction::Speed;
+    use crate::liveness::Liveness;
+    use crate::metrics::Metrics;
+    use crate::report::Reporter;
+    use crate::stream::Stream;
+
+    /// Sends two instructions from `linkage` to a connection on `stream`, and checks that both are
+    /// carried out.
+    fn check_instructions(stream: Stream, mut linkage: impl Write + Send + 'static) {
+        let client = thread::spawn(move || {
+            for speed in [0.5, -0.5] {
+                let message = LinkageToCarburetor::MotorInstruction { channel: 0, speed };
+                let bytes = message
+                    .to_frame()
+                    .encode(LINKAGE_TO_CARBURETOR_ENCODING)
+                    .unwrap();
+                linkage.write_all(&bytes).unwrap();
+            }
+        });
+
+        let (tx, rx) = channel();
+        let controls = Controls {
+            channels: HashMap::from([(0, tx.into())]),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        };
+        handle_connection(
+            stream,
+            &controls,
+            &Reporter::default(),
+            MalformedFramePolicy::Resync,
+            &Metrics::new(Instant::now()),
+            &Liveness::new(),
+        )
+        .unwrap();
+        client.join().unwrap();
+
+        let received: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            received,
+            [0.5, -0.5].map(|speed| ChannelCommand::Speed(Speed::new(speed).unwrap()))
+        );
+    }
+
+    #[test]
+    fn tcp_loopback() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let linkage = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        check_instructions(Stream::Tcp(stream), linkage);
+    }
+
+    #[test]
+    fn unix_socket() {
+        let (stream, linkage) = UnixStream::pair().unwrap();
+        check_instructions(Stream::Unix(stream), linkage);
+    }
+
+    #[test]
+    fn handshake_with_linkage() {
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let mut stream = Stream::Unix(stream);
+
+        let client = thread::spawn(move || {
+            Handshake::new(Component::LinkageLib, "0.1.0")
+                .exchange(&mut linkage, Component::Carburetor)
+        });
+        let received = handshake(&mut stream).unwrap();
+        assert_eq!(received.component, Component::LinkageLib);
+        assert_eq!(client.join().unwrap().unwrap(), own_handshake());
+    }
+
+    #[test]
+    fn refuses_linkage_without_handshake() {
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let mut stream = Stream::Unix(stream);
+
+        let message = LinkageToCarburetor::MotorInstruction {
+            channel: 0,
+            speed: 0.5,
+        };
+        linkage.write_all(&message.to_bytes()).unwrap();
+        let error = handshake(&mut stream).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn batch_checks_every_channel_first() {
+        let (tx_0, rx_0) = channel();
+        let (tx_1, rx_1) = channel();
+        let (tx_3, rx_3) = channel();
+        let (tx_4, rx_4) = channel();
+        let controls = Controls {
+            channels: HashMap::from([
+                (0, tx_0.into()),
+                (1, tx_1.into()),
+                (3, tx_3.into()),
+                (4, tx_4.into()),
+            ]),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        };
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let reporter = Reporter::default();
+        reporter.connect(Stream::Unix(stream));
+        let metrics = Metrics::new(Instant::now());
+
+        let batch = MotorBatch {
+            speeds: vec![(0, 0.5), (2, 0.5), (3, 0.2), (4, 2.0), (3, -0.2), (1, -0.5)],
+        };
+        handle_batch(batch, &controls, &reporter, &metrics);
+        assert_eq!(
+            rx_0.try_iter().collect::<Vec<_>>(),
+            [ChannelCommand::Speed(Speed::new(0.5).unwrap())]
+        );
+        assert_eq!(
+            rx_1.try_iter().collect::<Vec<_>>(),
+            [ChannelCommand::Speed(Speed::new(-0.5).unwrap())]
+        );
+        assert_eq!(rx_3.try_iter().count(), 0);
+        assert_eq!(rx_4.try_iter().count(), 0);
+
+        reporter.disconnect();
+        let mut bytes = Vec::new();
+        linkage.read_to_end(&mut bytes).unwrap();
+        let reports: Vec<_> = bytes
+            .chunks_exact(8)
+            .map(|chunk| {
+                CarburetorToLinkage::try_from(<[u8; 8]>::try_from(chunk).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            reports,
+            [
+                CarburetorToLinkage::Fault {
+                    channel: 2,
+                    kind: FaultKind::UnknownChannel
+                },
+                CarburetorToLinkage::Fault {
+                    channel: 3,
+                    kind: FaultKind::DuplicateChannel
+                },
+                CarburetorToLinkage::Fault {
+                    channel: 4,
+                    kind: FaultKind::OutOfRangeSpeed
+                },
+                CarburetorToLinkage::Fault {
+                    channel: 3,
+                    kind: FaultKind::DuplicateChannel
+                },
+                CarburetorToLinkage::InstructionAck {
+                    instruction: MotorBatch::OPCODE,
+                    channel: 0
+                },
+                CarburetorToLinkage::InstructionAck {
+                    instruction: MotorBatch::OPCODE,
+                    channel: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_frames_on_the_connection() {
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let (reports, mut linkage_reports) = UnixStream::pair().unwrap();
+        let reporter = Reporter::default();
+        reporter.connect(Stream::Unix(reports));
+
+        // An unknown opcode, whose first payload byte looks like channel 0.
+        let malformed = messaging::Frame::new(0x4f, [0, 0, 0, 0, 0, 0, 0]);
+        linkage
+            .write_all(&malformed.encode(LINKAGE_TO_CARBURETOR_ENCODING).unwrap())
+            .unwrap();
+        drop(linkage);
+        let controls = Controls {
+            channels: HashMap::new(),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        };
+        handle_connection(
+            Stream::Unix(stream),
+            &controls,
+            &reporter,
+            MalformedFramePolicy::Resync,
+            &Metrics::new(Instant::now()),
+            &Liveness::new(),
+        )
+        .unwrap();
+
+        reporter.disconnect();
+        let mut bytes = Vec::new();
+        linkage_reports.read_to_end(&mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            CarburetorToLinkage::Fault {
+                channel: CONNECTION_FAULT_CHANNEL,
+                kind: FaultKind::MalformedFrame
+            }
+            .to_bytes()
+        );
+    }
+
+    #[test]
+    fn echoes_stamps() {
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let reporter = Reporter::default();
+        reporter.connect(Stream::Unix(stream));
+        let metrics = Metrics::new(Instant::now());
+
+        for sequence in [0, 2] {
+            let stamp = Stamp {
+                sequence,
+                sent_at_us: 1_000 + sequence,
+                round_trip_us: 0,
+            };
+            handle_stamp(stamp, &reporter, &metrics);
+        }
+        let link = metrics.snapshot().linkage_link;
+        assert_eq!((link.received, link.dropped), (2, 1));
+
+        reporter.disconnect();
+        let mut bytes = Vec::new();
+        linkage.read_to_end(&mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            [1_000, 1_002]
+                .map(|sent_at_us| CarburetorToLinkage::Echo { sent_at_us }.to_bytes())
+                .concat()
+        );
+    }
+
+    #[test]
+    fn refuses_instructions_for_arming_channels() {
+        let (tx, rx) = channel();
+        let ignoring = ChannelHandle::from(tx);
+        ignoring.state.lock().unwrap().arming = true;
+        let (tx, queued) = channel();
+        let servo = ChannelConfig::new(1, 1).with_servo(ServoConfig::default());
+        let queueing = ChannelHandle {
+            calibration: Calibration::new(&servo).unwrap(),
+            arming_instructions: ArmingInstructions::Queue,
+            ..ChannelHandle::from(tx)
+        };
+        queueing.state.lock().unwrap().arming = true;
+        let controls = Controls {
+            channels: HashMap::from([(0, ignoring), (1, queueing)]),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        };
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let reporter = Reporter::default();
+        reporter.connect(Stream::Unix(stream));
+        let metrics = Metrics::new(Instant::now());
+
+        let speed = LinkageToCarburetor::MotorInstruction {
+            channel: 0,
+            speed: 0.5,
+        };
+        handle_message(speed, &controls, &reporter, &metrics);
+        let batch = MotorBatch {
+            speeds: vec![(0, 0.5), (1, 0.5)],
+        };
+        handle_batch(batch, &controls, &reporter, &metrics);
+        // Servos are not moved while arming, whatever the arming config says.
+        let position = LinkageToCarburetor::ServoPosition {
+            channel: 1,
+            target: ServoTarget::Position(0.5),
+        };
+        handle_message(position, &controls, &reporter, &metrics);
+
+        assert_eq!(rx.try_iter().count(), 0);
+        assert_eq!(
+            queued.try_iter().collect::<Vec<_>>(),
+            [ChannelCommand::Speed(Speed::new(0.5).unwrap())]
+        );
+
+        reporter.disconnect();
+        let mut bytes = Vec::new();
+        linkage.read_to_end(&mut bytes).unwrap();
+        let arming = |channel| CarburetorToLinkage::Fault {
+            channel,
+            kind: FaultKind::ChannelArming,
+        };
+        let expected = [
+            arming(0),
+            arming(0),
+            CarburetorToLinkage::InstructionAck {
+                instruction: MotorBatch::OPCODE,
+                channel: 1,
+            },
+            arming(1),
+        ];
+        assert_eq!(bytes, expected.map(|report| report.to_bytes()).concat());
+    }
+
+    #[test]
+    fn checks_servo_positions_before_acking() {
+        let (tx, motor) = channel();
+        let (tx_servo, servo) = channel();
+        let servo_config = ChannelConfig::new(1, 1).with_servo(ServoConfig::default());
+        let controls = Controls {
+            channels: HashMap::from([
+                (0, tx.into()),
+                (
+                    1,
+                    ChannelHandle {
+                        calibration: Calibration::new(&servo_config).unwrap(),
+                        ..ChannelHandle::from(tx_servo)
+                    },
+                ),
+            ]),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        };
+        let (stream, mut linkage) = UnixStream::pair().unwrap();
+        let reporter = Reporter::default();
+        reporter.connect(Stream::Unix(stream));
+        let metrics = Metrics::new(Instant::now());
+
+        for (channel, target) in [
+            (0, ServoTarget::Position(0.5)),
+            (1, ServoTarget::Position(1.5)),
+            (1, ServoTarget::Degrees(-10.0)),
+            (1, ServoTarget::Degrees(90.0)),
+        ] {
+            let message = LinkageToCarburetor::ServoPosition { channel, target };
+            handle_message(message, &controls, &reporter, &metrics);
+        }
+
+        assert_eq!(motor.try_iter().count(), 0);
+        assert_eq!(
+            servo.try_iter().collect::<Vec<_>>(),
+            [ChannelCommand::Position(ServoTarget::Degrees(90.0))]
+        );
+
+        reporter.disconnect();
+        let mut bytes = Vec::new();
+        linkage.read_to_end(&mut bytes).unwrap();
+        let expected = [
+            CarburetorToLinkage::Fault {
+                channel: 0,
+                kind: FaultKind::NotAServo,
+            },
+            CarburetorToLinkage::Fault {
+                channel: 1,
+                kind: FaultKind::OutOfRangePosition,
+            },
+            CarburetorToLinkage::Fault {
+                channel: 1,
+                kind: FaultKind::OutOfRangePosition,
+            },
+            CarburetorToLinkage::InstructionAck {
+                instruction: LinkageToCarburetor::ServoPosition {
+                    channel: 1,
+                    target: ServoTarget::Degrees(90.0),
+                }
+                .opcode(),
+                channel: 1,
+            },
+        ];
+        assert_eq!(bytes, expected.map(|report| report.to_bytes()).concat());
+    }
+}
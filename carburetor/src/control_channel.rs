@@ -1,49 +1,598 @@
 use std::error::Error;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-#[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
-use rppal::pwm::{Channel, Polarity, Pwm};
+use config::{ArmingConfig, ArmingInstructions, CarburetorConfig, ChannelConfig, WatchdogConfig};
+use messaging::{FaultKind, ServoTarget};
 
-use crate::Speed;
+use crate::arming::ArmingSequence;
+use crate::calibration::Calibration;
+use crate::controls::ChannelHandle;
+use crate::instruction::Speed;
+use crate::metrics::Metrics;
+use crate::output::{self, PwmOutput};
+use crate::report::{ChannelState, ChannelStateHandle, Reporter};
+use crate::slew::SlewLimiter;
+use crate::watchdog::Watchdog;
 
-#[cfg(not(all(target_arch = "arm", target_os = "linux", target_env = "gnu")))]
-pub(crate) enum Channel {
-    Pwm0,
-    Pwm1,
+/// An instruction for a [`ControlChannel`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ChannelCommand {
+    /// Move towards `speed`, as fast as the slew limiter allows.
+    Speed(Speed),
+    /// Go to neutral immediately, bypassing the slew limiter.
+    Stop,
+    /// Run the arming sequence again, including the calibration sweep if `sweep` is set. Does
+    /// nothing if the channel has no arming sequence, or is already arming.
+    Arm { sweep: bool },
+    /// Output `pulse_width` as is, bypassing the calibration and the slew limiter. Only meant for
+    /// testing the wiring on the bench.
+    Pulse(Duration),
+    /// Move the servo to `target`, bypassing the slew limiter. The position is held until the next
+    /// command, so the watchdog does not guard it.
+    Position(ServoTarget),
+    /// Write neutral immediately, and stop running.
+    Shutdown,
 }
 
-#[cfg(not(all(target_arch = "arm", target_os = "linux", target_env = "gnu")))]
-impl std::fmt::Display for Channel {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Pwm0 => write!(f, "Pwm0"),
-            Self::Pwm1 => write!(f, "Pwm1"),
+/// Drives a single channel, applying the speeds it receives to its output.
+pub(crate) struct ControlChannel {
+    channel: u8,
+    name: String,
+    calibration: Calibration,
+    output: Box<dyn PwmOutput>,
+    reporter: Reporter,
+    state: ChannelStateHandle,
+    watchdog: Watchdog<String>,
+    slew_limiter: Option<SlewLimiter>,
+    metrics: Option<Arc<Metrics>>,
+    arming_config: ArmingConfig,
+    /// The arming sequence that is running, if the channel is not armed yet.
+    arming: Option<ArmingSequence>,
+    /// The speed to apply once the channel is armed.
+    queued: Option<Speed>,
+    /// The speed that is currently being output.
+    speed: Speed,
+    /// The speed the channel should be at.
+    target: Speed,
+}
+
+impl ControlChannel {
+    /// Opens the PWM output of the channel described by `channel_config` on the backend selected
+    /// in `config`, and sets up its control with the options from both.
+    pub(crate) fn open(
+        config: &CarburetorConfig,
+        channel_config: &ChannelConfig,
+        reporter: Reporter,
+    ) -> Result<Self, Box<dyn Error>> {
+        let channel = channel_config.channel();
+        let calibration = Calibration::new(channel_config)?;
+
+        log::info!(
+            "Opening PWM channel {} for channel {channel}...",
+            channel_config.pwm_channel()
+        );
+        let pwm = output::open_pwm(
+            config,
+            channel_config.pwm_channel(),
+            calibration.period,
+            calibration.neutral_pulse,
+        )
+        .map_err(|error| {
+            format!(
+                "failed to open PWM channel {} for channel {channel}: {error}",
+                channel_config.pwm_channel()
+            )
+        })?;
+
+        let control = Self::new(channel, calibration, pwm, reporter, config.watchdog())
+            .with_max_acceleration(channel_config.max_acceleration())
+            .with_arming(channel_config.arming());
+
+        Ok(control)
+    }
+
+    pub(crate) fn new(
+        channel: u8,
+        calibration: Calibration,
+        output: Box<dyn PwmOutput>,
+        reporter: Reporter,
+        watchdog_config: &WatchdogConfig,
+    ) -> Self {
+        let name = format!("Channel {channel}");
+        let state = ChannelState {
+            pulse_width: calibration.neutral_pulse,
+            last_update: Instant::now(),
+            arming: false,
+        };
+
+        Self {
+            watchdog: Watchdog::new(name.clone(), watchdog_config),
+            channel,
+            name,
+            calibration,
+            output,
+            reporter,
+            state: Arc::new(Mutex::new(state)),
+            slew_limiter: None,
+            metrics: None,
+            arming_config: ArmingConfig::default(),
+            arming: None,
+            queued: None,
+            speed: Speed::neutral(),
+            target: Speed::neutral(),
         }
     }
-}
 
-pub(crate) fn control_channel(
-    pwm_channel: Channel,
-    receiver: Receiver<Speed>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    #[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
-    let pwm = Pwm::with_period(
-        pwm_channel,
-        std::time::Duration::from_millis(crate::PERIOD_MS),
-        std::time::Duration::from_micros(crate::PULSE_NEUTRAL_US),
-        Polarity::Normal,
-        true,
-    )?;
-
-    while let Ok(speed) = receiver.recv() {
-        let before = std::time::Instant::now();
-        #[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
-        pwm.set_pulse_width(speed.duration()).unwrap();
-        let after = std::time::Instant::now();
-        let delta = after - before;
+    /// Limits the change in speed to `max_acceleration` per second, if set.
+    pub(crate) fn with_max_acceleration(mut self, max_acceleration: Option<f32>) -> Self {
+        self.slew_limiter = max_acceleration.map(SlewLimiter::new);
+        self
+    }
+
+    /// Runs the arming sequence described by `arming` when the channel starts, and whenever it is
+    /// sent [`ChannelCommand::Arm`].
+    pub(crate) fn with_arming(mut self, arming: &ArmingConfig) -> Self {
+        self.arming_config = arming.clone();
+        // The channel is not armed until it has run.
+        self.state.lock().unwrap().arming = arming.is_enabled();
+        self
+    }
+
+    /// Records every write to the output in `metrics`.
+    pub(crate) fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Returns a handle to the state of this channel, which is kept up to date while it runs.
+    pub(crate) fn state(&self) -> ChannelStateHandle {
+        Arc::clone(&self.state)
+    }
+
+    /// Returns a handle for sending this channel the commands it receives from `sender`.
+    pub(crate) fn handle(&self, sender: Sender<ChannelCommand>) -> ChannelHandle {
+        ChannelHandle {
+            sender,
+            state: self.state(),
+            calibration: self.calibration.clone(),
+            arming_instructions: self.arming_config.instructions(),
+        }
+    }
+
+    /// Carries out the commands received over `receiver` until it is sent
+    /// [`ChannelCommand::Shutdown`], or all of its senders have been dropped.
+    ///
+    /// Returns whether the output is at neutral when the channel stops running.
+    pub(crate) fn run(mut self, receiver: Receiver<ChannelCommand>) -> bool {
+        self.start_arming(true);
+
+        loop {
+            let timeout = [
+                self.watchdog.poll_timeout(self.target),
+                self.slew_timeout(),
+                self.arming_timeout(),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+
+            let received = match timeout {
+                Some(timeout) => receiver.recv_timeout(timeout),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match received {
+                Ok(ChannelCommand::Speed(speed)) if self.arming.is_some() => {
+                    match self.arming_config.instructions() {
+                        ArmingInstructions::Ignore => {
+                            // The connection refuses these while the channel is arming, but the
+                            // channel may have started arming since.
+                            log::debug!("{}: still arming, ignoring {speed}.", self.name);
+                            self.reporter.fault(self.channel, FaultKind::ChannelArming);
+                        }
+                        ArmingInstructions::Queue => {
+                            self.watchdog.feed();
+                            self.queued = Some(speed);
+                        }
+                    }
+                }
+                Ok(ChannelCommand::Speed(speed)) => {
+                    self.watchdog.feed();
+                    self.target = speed;
+                    self.update(true);
+                }
+                Ok(ChannelCommand::Stop) => {
+                    self.target = Speed::neutral();
+                    self.queued = None;
+                    if let Some(limiter) = &mut self.slew_limiter {
+                        limiter.reset();
+                    }
+                    if let Some(arming) = &mut self.arming {
+                        arming.abort_sweep(Instant::now());
+                    }
+                    self.apply(Speed::neutral());
+                }
+                Ok(ChannelCommand::Arm { sweep }) => self.start_arming(sweep),
+                Ok(ChannelCommand::Pulse(pulse_width)) if self.arming.is_some() => {
+                    log::debug!("{}: still arming, ignoring {pulse_width:?}.", self.name);
+                }
+                Ok(ChannelCommand::Pulse(pulse_width)) => {
+                    self.write(pulse_width);
+                }
+                Ok(ChannelCommand::Position(target)) if self.arming.is_some() => {
+                    log::debug!("{}: still arming, ignoring {target:?}.", self.name);
+                }
+                Ok(ChannelCommand::Position(target)) => self.set_position(target),
+                Err(RecvTimeoutError::Timeout) => {
+                    // Instructions are not expected while arming, so the watchdog waits until then.
+                    if self.arming.is_none() {
+                        if let Some(speed) = self.watchdog.check(self.target) {
+                            self.target = speed;
+                        }
+                    }
+                    self.update(false);
+                }
+                Ok(ChannelCommand::Shutdown) => {
+                    self.arming = None;
+                    self.write(self.calibration.neutral_pulse);
+                    break;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            self.update_arming();
+        }
+
+        self.state.lock().unwrap().pulse_width == self.calibration.neutral_pulse
+    }
+
+    /// Returns the time until the slew limiter should take its next step, if it has to.
+    fn slew_timeout(&self) -> Option<Duration> {
+        match &self.slew_limiter {
+            Some(limiter) if self.speed != self.target => Some(limiter.until_next_step()),
+            _ => None,
+        }
+    }
+
+    /// Returns the time until the next step of the arming sequence, if it is running.
+    fn arming_timeout(&self) -> Option<Duration> {
+        self.arming
+            .as_ref()
+            .map(|arming| arming.until_next_step(Instant::now()))
+    }
+
+    /// Starts the arming sequence, unless there is none or it is already running.
+    fn start_arming(&mut self, sweep: bool) {
+        if !self.arming_config.is_enabled() || self.arming.is_some() {
+            return;
+        }
+
+        log::info!("{}: arming...", self.name);
+        self.target = Speed::neutral();
+        self.speed = Speed::neutral();
+        self.queued = None;
+        if let Some(limiter) = &mut self.slew_limiter {
+            limiter.reset();
+        }
+        self.arming = Some(ArmingSequence::new(
+            &self.arming_config,
+            &self.calibration,
+            sweep,
+            Instant::now(),
+        ));
+        self.state.lock().unwrap().arming = true;
+        self.update_arming();
+    }
+
+    /// Outputs the current step of the arming sequence, and finishes it once it is done.
+    fn update_arming(&mut self) {
+        let Some(arming) = &mut self.arming else {
+            return;
+        };
+
+        if let Some(pulse_width) = arming.pulse_width(Instant::now()) {
+            if pulse_width != self.state.lock().unwrap().pulse_width {
+                self.write(pulse_width);
+            }
+            return;
+        }
+
+        self.arming = None;
+        self.state.lock().unwrap().arming = false;
+        log::info!("{}: armed.", self.name);
+
+        if let Some(speed) = self.queued.take() {
+            self.watchdog.feed();
+            self.target = speed;
+            self.update(true);
+        }
+    }
+
+    /// Moves the servo to `target`, or reports why that is not possible.
+    fn set_position(&mut self, target: ServoTarget) {
+        // The connection has checked the position already, so this only fails for commands that
+        // did not come from Linkage.
+        let pulse_width = match self.calibration.servo_pulse_width(target) {
+            Ok(pulse_width) => pulse_width,
+            Err(kind) => {
+                log::error!("{}: cannot move to {target:?}: {kind:?}.", self.name);
+                self.reporter.fault(self.channel, kind);
+                return;
+            }
+        };
+
+        // The speed the channel is at no longer applies, so the next one starts from neutral.
+        self.target = Speed::neutral();
+        self.speed = Speed::neutral();
+        if let Some(limiter) = &mut self.slew_limiter {
+            limiter.reset();
+        }
+        self.write(pulse_width);
+    }
+
+    /// Moves the output towards the target speed. The output is written if its speed changed, or
+    /// if `received` is set.
+    fn update(&mut self, received: bool) {
+        let speed = match &mut self.slew_limiter {
+            Some(limiter) if limiter.until_next_step().is_zero() => {
+                limiter.step(self.speed, self.target)
+            }
+            Some(_) => self.speed,
+            None => self.target,
+        };
+
+        if speed == self.speed && !received {
+            return;
+        }
+
+        self.apply(speed);
+    }
+
+    /// Writes the pulse width for `speed` to the output.
+    fn apply(&mut self, speed: Speed) {
+        let before = Instant::now();
+        if !self.write(self.calibration.pulse_width(speed)) {
+            return;
+        }
+        self.speed = speed;
+        let delta = before.elapsed();
         let direction = speed.direction();
-        log::trace!("{pwm_channel}: received {speed} ({direction}). Executed in {delta:?}.");
+        log::trace!(
+            "{}: applied {speed} ({direction}), target {}. Executed in {delta:?}.",
+            self.name,
+            self.target
+        );
+    }
+
+    /// Writes `pulse_width` to the output, and returns whether that succeeded.
+    fn write(&mut self, pulse_width: Duration) -> bool {
+        let before = Instant::now();
+        if let Err(error) = self.output.set_pulse_width(pulse_width) {
+            log::error!(
+                "{}: failed to set pulse width to {pulse_width:?}: {error}",
+                self.name
+            );
+            self.reporter.fault(self.channel, FaultKind::BackendError);
+            return false;
+        }
+
+        let latency = before.elapsed();
+
+        let mut state = self.state.lock().unwrap();
+        let changed = state.pulse_width != pulse_width;
+        if changed {
+            state.pulse_width = pulse_width;
+            state.last_update = Instant::now();
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.output_written(self.channel, pulse_width, latency, changed);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use config::{
+        ArmingConfig, ArmingInstructions, ChannelConfig, ServoConfig, WatchdogConfig,
+        WatchdogPolicy,
+    };
+    use messaging::ServoTarget;
+
+    use super::{ChannelCommand, ControlChannel};
+    use crate::calibration::Calibration;
+    use crate::instruction::Speed;
+    use crate::output::sim::SimPwm;
+    use crate::report::Reporter;
+
+    /// How long a test waits for a write before it fails. Generous, so slow machines pass too.
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Returns the next pulse width written to the output `writes` watches.
+    fn next_write(writes: &Receiver<Duration>) -> Duration {
+        writes
+            .recv_timeout(WRITE_TIMEOUT)
+            .expect("no pulse width was written in time")
+    }
+
+    fn spark_channel(pwm: &SimPwm, watchdog_config: WatchdogConfig) -> ControlChannel {
+        let calibration = Calibration::new(&ChannelConfig::new(0, 0)).unwrap();
+        ControlChannel::new(
+            0,
+            calibration,
+            Box::new(pwm.clone()),
+            Reporter::default(),
+            &watchdog_config,
+        )
+    }
+
+    #[test]
+    fn applies_received_speeds() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let control = spark_channel(&pwm, WatchdogConfig::new(1000, WatchdogPolicy::Neutral, 0));
+        let (tx, rx) = channel();
+
+        let handle = thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Speed(Speed::forward())).unwrap();
+        tx.send(ChannelCommand::Speed(Speed::backward())).unwrap();
+        drop(tx);
+        handle.join().unwrap();
+
+        let state = pwm.state();
+        let history = &state.lock().unwrap().history;
+        assert_eq!(
+            history.iter().copied().collect::<Vec<_>>(),
+            vec![
+                Duration::from_micros(1500),
+                Duration::from_micros(2000),
+                Duration::from_micros(1000),
+            ]
+        );
+    }
+
+    #[test]
+    fn watchdog_neutralizes_silent_channel() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let writes = pwm.watch();
+        let control = spark_channel(&pwm, WatchdogConfig::new(10, WatchdogPolicy::Neutral, 0));
+        let (tx, rx) = channel();
+
+        thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Speed(Speed::forward())).unwrap();
+        assert_eq!(next_write(&writes), Duration::from_micros(2000));
+        assert_eq!(next_write(&writes), Duration::from_micros(1500));
+    }
+
+    #[test]
+    fn slew_limiter_ramps_towards_target() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let writes = pwm.watch();
+        let control = spark_channel(&pwm, WatchdogConfig::new(5000, WatchdogPolicy::Hold, 0))
+            .with_max_acceleration(Some(2.0));
+        let (tx, rx) = channel();
+
+        thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Speed(Speed::forward())).unwrap();
+        let mut ramp = vec![next_write(&writes)];
+        while *ramp.last().unwrap() != Duration::from_micros(2000) {
+            ramp.push(next_write(&writes));
+        }
+        assert!(ramp[0] > Duration::from_micros(1500));
+        assert!(ramp[0] < Duration::from_micros(2000));
+        assert!(ramp.windows(2).all(|steps| steps[0] < steps[1]));
+    }
+
+    #[test]
+    fn stop_bypasses_slew_limiter() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let writes = pwm.watch();
+        let control = spark_channel(&pwm, WatchdogConfig::new(5000, WatchdogPolicy::Hold, 0))
+            .with_max_acceleration(Some(2.0));
+        let (tx, rx) = channel();
+
+        thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Speed(Speed::forward())).unwrap();
+        let first_step = next_write(&writes);
+        tx.send(ChannelCommand::Stop).unwrap();
+        // Steps already taken towards full speed may come first, but nothing ramps down.
+        loop {
+            let pulse_width = next_write(&writes);
+            if pulse_width == Duration::from_micros(1500) {
+                break;
+            }
+            assert!(pulse_width >= first_step);
+        }
     }
 
-    Ok(())
+    #[test]
+    fn moves_servo_to_position() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let writes = pwm.watch();
+        let config = ChannelConfig::new(0, 0).with_servo(ServoConfig::default());
+        let calibration = Calibration::new(&config).unwrap();
+        let control = ControlChannel::new(
+            0,
+            calibration,
+            Box::new(pwm.clone()),
+            Reporter::default(),
+            &WatchdogConfig::new(10, WatchdogPolicy::Neutral, 0),
+        );
+        let (tx, rx) = channel();
+
+        let handle = thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Position(ServoTarget::Degrees(45.0)))
+            .unwrap();
+        tx.send(ChannelCommand::Position(ServoTarget::Degrees(200.0)))
+            .unwrap();
+        assert_eq!(next_write(&writes), Duration::from_micros(1250));
+        // The watchdog, which trips after 10ms, does not move the servo back.
+        assert_eq!(
+            writes.recv_timeout(Duration::from_millis(50)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(tx);
+        handle.join().unwrap();
+
+        assert_eq!(
+            pwm.state().lock().unwrap().pulse_width(),
+            Some(Duration::from_micros(1250))
+        );
+    }
+
+    #[test]
+    fn shutdown_writes_neutral() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let control = spark_channel(&pwm, WatchdogConfig::new(1000, WatchdogPolicy::Hold, 0))
+            .with_max_acceleration(Some(1.0));
+        let (tx, rx) = channel();
+
+        let handle = thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Pulse(Duration::from_micros(2000)))
+            .unwrap();
+        tx.send(ChannelCommand::Shutdown).unwrap();
+        assert!(handle.join().unwrap());
+
+        assert_eq!(
+            pwm.state().lock().unwrap().pulse_width(),
+            Some(Duration::from_micros(1500))
+        );
+        // The channel is no longer running.
+        assert!(tx.send(ChannelCommand::Stop).is_err());
+    }
+
+    #[test]
+    fn queues_instructions_while_arming() {
+        let pwm = SimPwm::open(0, Duration::from_millis(20), Duration::from_micros(1500));
+        let writes = pwm.watch();
+        let arming = ArmingConfig::new(50, false, 0, ArmingInstructions::Queue);
+        let control = spark_channel(&pwm, WatchdogConfig::new(5000, WatchdogPolicy::Hold, 0))
+            .with_arming(&arming);
+        let state = control.state();
+        let (tx, rx) = channel();
+
+        let started = Instant::now();
+        thread::spawn(move || control.run(rx));
+
+        tx.send(ChannelCommand::Speed(Speed::forward())).unwrap();
+        assert!(state.lock().unwrap().arming);
+        while next_write(&writes) != Duration::from_micros(2000) {}
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(!state.lock().unwrap().arming);
+    }
 }
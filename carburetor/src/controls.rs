@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{SendError, Sender};
+use std::sync::Mutex;
+
+use config::ArmingInstructions;
+
+use crate::calibration::Calibration;
+use crate::control_channel::ChannelCommand;
+use crate::digital::DigitalOutputs;
+use crate::estop::EStop;
+use crate::instruction::Speed;
+use crate::report::ChannelStateHandle;
+
+/// The control thread of a channel, and what is needed to check instructions before they are
+/// sent to it.
+pub(crate) struct ChannelHandle {
+    pub(crate) sender: Sender<ChannelCommand>,
+    pub(crate) state: ChannelStateHandle,
+    /// The calibration of the channel, to check servo positions with.
+    pub(crate) calibration: Calibration,
+    /// What the channel does with speeds while it is arming.
+    pub(crate) arming_instructions: ArmingInstructions,
+}
+
+impl ChannelHandle {
+    /// Returns whether the channel is running its arming sequence.
+    pub(crate) fn is_arming(&self) -> bool {
+        self.state.lock().unwrap().arming
+    }
+
+    /// Returns whether the channel would drop a speed it is sent now, because it is arming and
+    /// does not queue instructions.
+    pub(crate) fn ignores_speeds(&self) -> bool {
+        self.arming_instructions == ArmingInstructions::Ignore && self.is_arming()
+    }
+
+    pub(crate) fn send(&self, command: ChannelCommand) -> Result<(), SendError<ChannelCommand>> {
+        self.sender.send(command)
+    }
+}
+
+#[cfg(test)]
+impl From<Sender<ChannelCommand>> for ChannelHandle {
+    /// Wraps `sender` for a motor channel with the default calibration that is armed.
+    fn from(sender: Sender<ChannelCommand>) -> Self {
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        use config::ChannelConfig;
+
+        use crate::report::ChannelState;
+
+        let state = ChannelState {
+            pulse_width: Duration::ZERO,
+            last_update: Instant::now(),
+            arming: false,
+        };
+        Self {
+            sender,
+            state: Arc::new(Mutex::new(state)),
+            calibration: Calibration::new(&ChannelConfig::new(0, 0)).unwrap(),
+            arming_instructions: ArmingInstructions::default(),
+        }
+    }
+}
+
+/// Everything Linkage can control, shared between the connection and the status reporter.
+pub(crate) struct Controls {
+    /// The control thread of every channel.
+    pub(crate) channels: HashMap<u8, ChannelHandle>,
+    pub(crate) digital_outputs: Mutex<DigitalOutputs>,
+    pub(crate) estop: EStop,
+}
+
+impl Controls {
+    /// Brings every channel back to neutral, as fast as its slew limiter allows, and puts every
+    /// digital output into its safe state.
+    pub(crate) fn reset(&self) {
+        self.send_all(ChannelCommand::Speed(Speed::neutral()));
+        self.digital_outputs.lock().unwrap().reset();
+    }
+
+    /// Puts every channel into neutral immediately, and every digital output into its safe state.
+    pub(crate) fn stop(&self) {
+        self.send_all(ChannelCommand::Stop);
+        self.digital_outputs.lock().unwrap().reset();
+    }
+
+    /// Puts every channel into neutral immediately and stops its control thread, and puts every
+    /// digital output into its safe state.
+    pub(crate) fn shutdown(&self) {
+        self.send_all(ChannelCommand::Shutdown);
+        self.digital_outputs.lock().unwrap().reset();
+    }
+
+    /// Runs the arming sequence of every channel again, without the calibration sweep.
+    pub(crate) fn arm(&self) {
+        self.send_all(ChannelCommand::Arm { sweep: false });
+    }
+
+    fn send_all(&self, command: ChannelCommand) {
+        for (channel, handle) in &self.channels {
+            if handle.send(command).is_err() {
+                log::error!("Control thread for channel {channel} is no longer running.");
+            }
+        }
+    }
+}
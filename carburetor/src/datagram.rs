@@ -0,0 +1,349 @@
+//! Receiving instructions from Linkage as UDP datagrams, next to its TCP connection.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use messaging::{
+    FaultKind, LinkageToCarburetor, LinkageToCarburetorFrame, MotorBatch, Sequenced,
+    CONNECTION_FAULT_CHANNEL, LINKAGE_TO_CARBURETOR_ENCODING,
+};
+
+use crate::connection;
+use crate::controls::Controls;
+use crate::metrics::Metrics;
+use crate::report::Reporter;
+
+/// The length of the longest datagram Linkage sends.
+const MAX_DATAGRAM_LEN: usize =
+    LINKAGE_TO_CARBURETOR_ENCODING.max_frame_len(connection::MAX_PAYLOAD_LEN);
+
+/// What an instruction controls. Only the latest instruction for each of these is carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Target {
+    Channel(u8),
+    DigitalOutput(u8),
+    EStop,
+}
+
+/// Drops instructions that arrive after a newer instruction for the same target, and duplicates.
+#[derive(Debug, Default)]
+struct SequenceFilter {
+    latest: HashMap<Target, u32>,
+}
+
+impl SequenceFilter {
+    /// Returns what of `sequenced` should be carried out, if anything. Of a batch, only the
+    /// channels without a newer instruction are kept.
+    fn accept(
+        &mut self,
+        sequenced: Sequenced<LinkageToCarburetorFrame>,
+    ) -> Option<LinkageToCarburetorFrame> {
+        match &sequenced.message {
+            LinkageToCarburetorFrame::Message(message) => {
+                let target = match *message {
+                    LinkageToCarburetor::MotorInstruction { channel, .. }
+                    | LinkageToCarburetor::ServoPosition { channel, .. } => {
+                        Target::Channel(channel)
+                    }
+                    LinkageToCarburetor::DigitalOutput { pin, .. } => Target::DigitalOutput(pin),
+                    LinkageToCarburetor::EStop | LinkageToCarburetor::ResetEStop => Target::EStop,
+                };
+                let estop = *message == LinkageToCarburetor::EStop;
+                self.accept_target(target, &sequenced, estop)
+                    .then_some(sequenced.message)
+            }
+            LinkageToCarburetorFrame::MotorBatch(batch) => {
+                let speeds: Vec<_> = batch
+                    .speeds
+                    .iter()
+                    .copied()
+                    .filter(|(channel, _)| {
+                        self.accept_target(Target::Channel(*channel), &sequenced, false)
+                    })
+                    .collect();
+                (!speeds.is_empty()).then_some(MotorBatch { speeds }.into())
+            }
+        }
+    }
+
+    /// Returns whether the instruction for `target` in `sequenced` should be carried out.
+    fn accept_target<M>(&mut self, target: Target, sequenced: &Sequenced<M>, estop: bool) -> bool {
+        match self.latest.get(&target) {
+            // Latching the emergency stop is always safe, even after a newer release.
+            Some(latest) if !sequenced.follows(*latest) => estop,
+            _ => {
+                self.latest.insert(target, sequenced.sequence());
+                true
+            }
+        }
+    }
+}
+
+struct Session {
+    peer: IpAddr,
+    filter: SequenceFilter,
+}
+
+/// The Linkage that datagrams are accepted from, which is the one connected over TCP.
+#[derive(Default)]
+pub(crate) struct DatagramPeer {
+    session: Mutex<Option<Session>>,
+}
+
+impl DatagramPeer {
+    /// Accepts datagrams from `peer`, starting a new sequence.
+    pub(crate) fn connect(&self, peer: IpAddr) {
+        *self.session.lock().unwrap() = Some(Session {
+            peer,
+            filter: SequenceFilter::default(),
+        });
+    }
+
+    /// Stops accepting datagrams. Once this returns, no more instructions are carried out until the
+    /// next [`DatagramPeer::connect`].
+    pub(crate) fn disconnect(&self) {
+        *self.session.lock().unwrap() = None;
+    }
+
+    fn receive(
+        &self,
+        from: SocketAddr,
+        datagram: &[u8],
+        controls: &Controls,
+        reporter: &Reporter,
+        metrics: &Metrics,
+    ) {
+        // The lock is held while the instruction is carried out, so it cannot race a disconnect.
+        let mut session = self.session.lock().unwrap();
+        let Some(session) = session.as_mut().filter(|session| session.peer == from.ip()) else {
+            log::debug!("Ignoring datagram from {from}, which is not the connected Linkage.");
+            return;
+        };
+
+        let sequenced =
+            Sequenced::<LinkageToCarburetorFrame>::decode(datagram, LINKAGE_TO_CARBURETOR_ENCODING);
+        let sequenced = match sequenced {
+            Ok(sequenced) => {
+                connection::handle_stamp(sequenced.stamp, reporter, metrics);
+                sequenced
+            }
+            Err(error) => {
+                let count = metrics.malformed_frame(&error);
+                log::warn!(
+                    "Received malformed datagram {datagram:?}: {error} ({count} like it so far)."
+                );
+                reporter.fault(CONNECTION_FAULT_CHANNEL, FaultKind::MalformedFrame);
+                return;
+            }
+        };
+
+        let sequence = sequenced.sequence();
+        match session.filter.accept(sequenced) {
+            Some(LinkageToCarburetorFrame::Message(message)) => {
+                connection::handle_message(message, controls, reporter, metrics)
+            }
+            Some(LinkageToCarburetorFrame::MotorBatch(batch)) => {
+                connection::handle_batch(batch, controls, reporter, metrics)
+            }
+            None => log::debug!("Dropping stale datagram {sequence}."),
+        }
+    }
+}
+
+/// Carries out the instructions that the Linkage connected to `peer` sends to `socket`.
+pub(crate) fn start_receiver(
+    socket: UdpSocket,
+    peer: Arc<DatagramPeer>,
+    controls: Arc<Controls>,
+    reporter: Reporter,
+    metrics: Arc<Metrics>,
+) {
+    thread::spawn(move || {
+        // Fits a stamped batch for every channel. Anything longer is not a valid datagram, and is
+        // refused once it is truncated.
+        let mut buffer = [0; MAX_DATAGRAM_LEN];
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((length, from)) => {
+                    peer.receive(from, &buffer[..length], &controls, &reporter, &metrics)
+                }
+                Err(error) => log::error!("Failed to receive datagram: {error}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use messaging::{
+        LinkageToCarburetor, LinkageToCarburetorFrame, MotorBatch, Sequenced, Stamp,
+        LINKAGE_TO_CARBURETOR_ENCODING,
+    };
+
+    use super::{start_receiver, DatagramPeer, SequenceFilter};
+    use crate::control_channel::ChannelCommand;
+    use crate::controls::Controls;
+    use crate::estop::EStop;
+    use crate::instruction::Speed;
+    use crate::metrics::Metrics;
+    use crate::report::Reporter;
+
+    fn sequenced(
+        sequence: u32,
+        message: impl Into<LinkageToCarburetorFrame>,
+    ) -> Sequenced<LinkageToCarburetorFrame> {
+        Sequenced {
+            stamp: Stamp {
+                sequence,
+                sent_at_us: 0,
+                round_trip_us: 0,
+            },
+            message: message.into(),
+        }
+    }
+
+    fn motor(channel: u8, speed: f32) -> LinkageToCarburetor {
+        LinkageToCarburetor::MotorInstruction { channel, speed }
+    }
+
+    #[test]
+    fn latest_instruction_wins() {
+        let mut filter = SequenceFilter::default();
+        assert!(filter.accept(sequenced(5, motor(0, 0.5))).is_some());
+        assert!(filter.accept(sequenced(4, motor(0, 0.2))).is_none());
+        assert!(filter.accept(sequenced(5, motor(0, 0.5))).is_none());
+        // Other channels have their own latest instruction.
+        assert!(filter.accept(sequenced(3, motor(1, 0.2))).is_some());
+        assert!(filter.accept(sequenced(6, motor(0, 0.1))).is_some());
+    }
+
+    #[test]
+    fn stale_estop_still_latches() {
+        let mut filter = SequenceFilter::default();
+        assert!(filter
+            .accept(sequenced(2, LinkageToCarburetor::ResetEStop))
+            .is_some());
+        assert!(filter
+            .accept(sequenced(1, LinkageToCarburetor::EStop))
+            .is_some());
+        assert!(filter
+            .accept(sequenced(1, LinkageToCarburetor::ResetEStop))
+            .is_none());
+    }
+
+    #[test]
+    fn batch_keeps_channels_without_newer_instruction() {
+        let mut filter = SequenceFilter::default();
+        assert!(filter.accept(sequenced(5, motor(0, 0.5))).is_some());
+        let batch = MotorBatch {
+            speeds: vec![(0, 0.2), (1, 0.2)],
+        };
+        assert_eq!(
+            filter.accept(sequenced(4, batch.clone())),
+            Some(
+                MotorBatch {
+                    speeds: vec![(1, 0.2)]
+                }
+                .into()
+            )
+        );
+        assert_eq!(filter.accept(sequenced(4, batch)), None);
+    }
+
+    #[test]
+    fn loopback() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = socket.local_addr().unwrap();
+        let (tx, rx) = channel();
+        let controls = Arc::new(Controls {
+            channels: HashMap::from([(0, tx.into())]),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        });
+        let peer = Arc::new(DatagramPeer::default());
+        start_receiver(
+            socket,
+            Arc::clone(&peer),
+            controls,
+            Reporter::default(),
+            Arc::new(Metrics::new(Instant::now())),
+        );
+
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client.connect(address).unwrap();
+        let send = |sequence, speed| {
+            let bytes = sequenced(sequence, motor(0, speed))
+                .encode(LINKAGE_TO_CARBURETOR_ENCODING)
+                .unwrap();
+            client.send(&bytes).unwrap();
+        };
+
+        // Nothing is carried out before Linkage has connected.
+        send(1, 0.1);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        peer.connect(Ipv4Addr::LOCALHOST.into());
+        send(2, 0.5);
+        send(1, 0.1);
+        send(3, -0.5);
+        for speed in [0.5, -0.5] {
+            assert_eq!(
+                rx.recv_timeout(Duration::from_millis(500)),
+                Ok(ChannelCommand::Speed(Speed::new(speed).unwrap()))
+            );
+        }
+
+        peer.disconnect();
+        send(4, 0.1);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn receives_a_batch_for_every_channel() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = socket.local_addr().unwrap();
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..=u8::MAX).map(|_| channel()).unzip();
+        let controls = Arc::new(Controls {
+            channels: (0..=u8::MAX)
+                .zip(senders)
+                .map(|(channel, tx)| (channel, tx.into()))
+                .collect(),
+            digital_outputs: Mutex::default(),
+            estop: EStop::new(None, false),
+        });
+        let peer = Arc::new(DatagramPeer::default());
+        peer.connect(Ipv4Addr::LOCALHOST.into());
+        start_receiver(
+            socket,
+            peer,
+            controls,
+            Reporter::default(),
+            Arc::new(Metrics::new(Instant::now())),
+        );
+
+        let batch = MotorBatch {
+            speeds: (0..=u8::MAX).map(|channel| (channel, 0.5)).collect(),
+        };
+        let bytes = sequenced(1, batch)
+            .encode(LINKAGE_TO_CARBURETOR_ENCODING)
+            .unwrap();
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client.connect(address).unwrap();
+        client.send(&bytes).unwrap();
+
+        for rx in receivers {
+            assert_eq!(
+                rx.recv_timeout(Duration::from_millis(500)),
+                Ok(ChannelCommand::Speed(Speed::new(0.5).unwrap()))
+            );
+        }
+    }
+}
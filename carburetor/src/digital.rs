@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use messaging::FaultKind;
+
+use crate::output::GpioOutput;
+
+/// A digital output and the value it falls back to.
+struct DigitalOutput {
+    output: Box<dyn GpioOutput>,
+    safe_state: bool,
+}
+
+/// The digital outputs Linkage can control, by the pin number robot code uses.
+#[derive(Default)]
+pub(crate) struct DigitalOutputs {
+    outputs: HashMap<u8, DigitalOutput>,
+}
+
+impl DigitalOutputs {
+    /// Adds `output` as `pin`, which is put back into `safe_state` by [`DigitalOutputs::reset`].
+    ///
+    /// Returns an error if `pin` has already been added.
+    pub(crate) fn insert(
+        &mut self,
+        pin: u8,
+        output: Box<dyn GpioOutput>,
+        safe_state: bool,
+    ) -> Result<(), String> {
+        if self.outputs.contains_key(&pin) {
+            return Err(format!("digital output {pin} is configured more than once"));
+        }
+
+        self.outputs
+            .insert(pin, DigitalOutput { output, safe_state });
+        Ok(())
+    }
+
+    /// Sets `pin` to `value`.
+    ///
+    /// Returns the kind of fault to report to Linkage if that is not possible.
+    pub(crate) fn set(&mut self, pin: u8, value: bool) -> Result<(), FaultKind> {
+        let Some(digital) = self.outputs.get_mut(&pin) else {
+            log::error!("Digital output {pin} does not exist.");
+            return Err(FaultKind::UnknownPin);
+        };
+
+        if let Err(error) = digital.output.set(value) {
+            log::error!("Digital output {pin}: failed to set to {value}: {error}");
+            return Err(FaultKind::BackendError);
+        }
+
+        log::trace!("Digital output {pin}: set to {value}.");
+        Ok(())
+    }
+
+    /// Puts every output back into its safe state.
+    pub(crate) fn reset(&mut self) {
+        for (pin, digital) in &mut self.outputs {
+            if let Err(error) = digital.output.set(digital.safe_state) {
+                log::error!(
+                    "Digital output {pin}: failed to reset to {}: {error}",
+                    digital.safe_state
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use messaging::FaultKind;
+
+    use super::DigitalOutputs;
+    use crate::output::sim::SimGpio;
+
+    #[test]
+    fn set_and_reset() {
+        let normally_off = SimGpio::open(17, false);
+        let normally_on = SimGpio::open(27, true);
+        let mut outputs = DigitalOutputs::default();
+        outputs
+            .insert(0, Box::new(normally_off.clone()), false)
+            .unwrap();
+        outputs
+            .insert(1, Box::new(normally_on.clone()), true)
+            .unwrap();
+        assert!(outputs
+            .insert(1, Box::new(SimGpio::open(22, false)), false)
+            .is_err());
+
+        outputs.set(0, true).unwrap();
+        outputs.set(1, false).unwrap();
+        assert!(normally_off.value());
+        assert!(!normally_on.value());
+        assert_eq!(outputs.set(2, true), Err(FaultKind::UnknownPin));
+
+        outputs.reset();
+        assert!(!normally_off.value());
+        assert!(normally_on.value());
+    }
+}
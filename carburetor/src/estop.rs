@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The latch of the emergency stop.
+///
+/// While latched, a file is kept at `path`, so the latch can survive a restart, and other
+/// processes like Gauge can see it.
+#[derive(Debug)]
+pub(crate) struct EStop {
+    latched: AtomicBool,
+    path: Option<PathBuf>,
+}
+
+impl EStop {
+    /// Creates the latch, which starts out latched if the file at `path` exists and `survives_restart`
+    /// is set.
+    pub(crate) fn new(path: Option<PathBuf>, survives_restart: bool) -> Self {
+        let estop = Self {
+            latched: AtomicBool::new(false),
+            path,
+        };
+
+        let was_latched = estop.path.as_ref().is_some_and(|path| path.exists());
+        if was_latched && survives_restart {
+            log::warn!("The emergency stop is still latched from before the restart.");
+            estop.latched.store(true, Ordering::SeqCst);
+        } else if was_latched {
+            log::warn!("Releasing the emergency stop that was latched before the restart.");
+            estop.reset();
+        }
+
+        estop
+    }
+
+    pub(crate) fn is_latched(&self) -> bool {
+        self.latched.load(Ordering::SeqCst)
+    }
+
+    /// Latches the emergency stop.
+    pub(crate) fn latch(&self) {
+        self.latched.store(true, Ordering::SeqCst);
+        log::warn!("Emergency stop latched.");
+
+        if let Some(path) = &self.path {
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, ""));
+            if let Err(error) = written {
+                log::error!("Failed to write emergency stop latch file {path:?}: {error}");
+            }
+        }
+    }
+
+    /// Releases the emergency stop.
+    pub(crate) fn reset(&self) {
+        self.latched.store(false, Ordering::SeqCst);
+        log::info!("Emergency stop released.");
+
+        if let Some(path) = &self.path {
+            if let Err(error) = fs::remove_file(path) {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    log::error!("Failed to remove emergency stop latch file {path:?}: {error}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::EStop;
+
+    fn latch_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "carburetor-estop-test-{name}-{}",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn survives_restart() {
+        let path = latch_path("survives");
+        let estop = EStop::new(Some(path.clone()), true);
+        assert!(!estop.is_latched());
+
+        estop.latch();
+        assert!(path.exists());
+        assert!(EStop::new(Some(path.clone()), true).is_latched());
+
+        estop.reset();
+        assert!(!path.exists());
+        assert!(!EStop::new(Some(path), true).is_latched());
+    }
+
+    #[test]
+    fn released_by_restart() {
+        let path = latch_path("released");
+        EStop::new(Some(path.clone()), false).latch();
+
+        assert!(!EStop::new(Some(path.clone()), false).is_latched());
+        assert!(!path.exists());
+    }
+}
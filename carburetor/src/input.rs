@@ -0,0 +1,369 @@
+//! Sampling digital and analog inputs and reporting their values to Linkage.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use config::AnalogInputConfig;
+use messaging::{CarburetorToLinkage, FaultKind};
+
+use crate::output::{AdcInput, GpioInput};
+use crate::report::{Reporter, STATUS_INTERVAL};
+
+/// The interval at which inputs are sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Filters out the bouncing of mechanical switches, by only accepting a new value once every sample
+/// has had that value for the debounce time.
+#[derive(Debug)]
+pub(crate) struct Debouncer {
+    debounce: Duration,
+    value: bool,
+    /// When the current value was first sampled.
+    changed_at: Instant,
+    /// When the other value was first sampled, if every sample since has had it.
+    pending_since: Option<Instant>,
+}
+
+impl Debouncer {
+    pub(crate) fn new(value: bool, debounce: Duration, now: Instant) -> Self {
+        Self {
+            debounce,
+            value,
+            changed_at: now,
+            pending_since: None,
+        }
+    }
+
+    /// The debounced value.
+    pub(crate) fn value(&self) -> bool {
+        self.value
+    }
+
+    /// When the debounced value was first sampled.
+    pub(crate) fn changed_at(&self) -> Instant {
+        self.changed_at
+    }
+
+    /// Feeds a `sample` taken at `now`, and returns whether the debounced value changed.
+    pub(crate) fn update(&mut self, sample: bool, now: Instant) -> bool {
+        if sample == self.value {
+            self.pending_since = None;
+            return false;
+        }
+
+        let since = *self.pending_since.get_or_insert(now);
+        if now.duration_since(since) < self.debounce {
+            return false;
+        }
+
+        self.value = sample;
+        self.changed_at = since;
+        self.pending_since = None;
+        true
+    }
+}
+
+/// A digital input that is being sampled.
+struct DigitalInput {
+    pin: u8,
+    source: Box<dyn GpioInput>,
+    debouncer: Debouncer,
+    /// Whether the last read failed, so failures are only logged once.
+    failing: bool,
+}
+
+impl DigitalInput {
+    fn report(&self, reporter: &Reporter, started: Instant) {
+        let timestamp = self
+            .debouncer
+            .changed_at()
+            .saturating_duration_since(started);
+        reporter.report(CarburetorToLinkage::DigitalInput {
+            pin: self.pin,
+            value: self.debouncer.value(),
+            timestamp_ms: timestamp.as_millis() as u32,
+        });
+    }
+}
+
+/// An analog input that is being sampled.
+struct AnalogInput {
+    config: AnalogInputConfig,
+    source: Box<dyn AdcInput>,
+    /// The last sample, as a fraction of the full scale.
+    value: Option<f32>,
+    /// The value that was last reported.
+    reported: Option<f32>,
+    /// Whether the last read failed, so failures are only logged once.
+    failing: bool,
+}
+
+impl AnalogInput {
+    /// Samples the input, and returns whether its value moved by at least its threshold since it
+    /// was last reported.
+    fn sample(&mut self, reporter: &Reporter) -> bool {
+        let pin = self.config.pin();
+        let raw = match self.source.read() {
+            Ok(raw) => raw,
+            Err(error) => {
+                if !self.failing {
+                    log::error!("Analog input {pin}: failed to read: {error}");
+                    reporter.fault(pin, FaultKind::BackendError);
+                    self.failing = true;
+                }
+                return false;
+            }
+        };
+        self.failing = false;
+
+        let value = (raw as f32 / self.config.max_raw() as f32).clamp(0.0, 1.0);
+        self.value = Some(value);
+        match self.reported {
+            Some(reported) => (value - reported).abs() >= self.config.threshold(),
+            None => true,
+        }
+    }
+
+    fn report(&mut self, reporter: &Reporter) {
+        let Some(value) = self.value else {
+            return;
+        };
+        self.reported = Some(value);
+        reporter.report(CarburetorToLinkage::AnalogInput {
+            pin: self.config.pin(),
+            value,
+        });
+    }
+}
+
+/// Samples each of `inputs`, given as its pin, source and debounce time, and each of
+/// `analog_inputs`, and reports their values through `reporter` when they change and periodically.
+/// Timestamps are relative to `started`.
+pub(crate) fn start_input_sampler(
+    reporter: Reporter,
+    inputs: Vec<(u8, Box<dyn GpioInput>, Duration)>,
+    analog_inputs: Vec<(AnalogInputConfig, Box<dyn AdcInput>)>,
+    started: Instant,
+) {
+    if inputs.is_empty() && analog_inputs.is_empty() {
+        return;
+    }
+
+    let now = Instant::now();
+    let mut inputs: Vec<_> = inputs
+        .into_iter()
+        .map(|(pin, mut source, debounce)| {
+            let value = source.read().unwrap_or_else(|error| {
+                log::error!("Digital input {pin}: failed to read: {error}");
+                false
+            });
+
+            DigitalInput {
+                pin,
+                source,
+                debouncer: Debouncer::new(value, debounce, now),
+                failing: false,
+            }
+        })
+        .collect();
+    let mut analog_inputs: Vec<_> = analog_inputs
+        .into_iter()
+        .map(|(config, source)| AnalogInput {
+            config,
+            source,
+            value: None,
+            reported: None,
+            failing: false,
+        })
+        .collect();
+
+    thread::spawn(move || {
+        let mut last_status = Instant::now();
+        loop {
+            for input in &mut inputs {
+                let sample = match input.source.read() {
+                    Ok(sample) => sample,
+                    Err(error) => {
+                        if !input.failing {
+                            log::error!("Digital input {}: failed to read: {error}", input.pin);
+                            reporter.fault(input.pin, FaultKind::BackendError);
+                            input.failing = true;
+                        }
+                        continue;
+                    }
+                };
+                input.failing = false;
+
+                if input.debouncer.update(sample, Instant::now()) {
+                    log::debug!("Digital input {}: changed to {sample}.", input.pin);
+                    input.report(&reporter, started);
+                }
+            }
+
+            for input in &mut analog_inputs {
+                if input.sample(&reporter) {
+                    input.report(&reporter);
+                }
+            }
+
+            if last_status.elapsed() >= STATUS_INTERVAL {
+                for input in &inputs {
+                    input.report(&reporter, started);
+                }
+                for input in &mut analog_inputs {
+                    input.report(&reporter);
+                }
+                last_status = Instant::now();
+            }
+
+            thread::sleep(SAMPLE_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+    use std::time::{Duration, Instant};
+
+    use config::{AnalogInputConfig, InputPull};
+    use messaging::{Bytes, CarburetorToLinkage};
+
+    use super::{start_input_sampler, AnalogInput, Debouncer};
+    use crate::output::sim::{SimAdcInput, SimGpioInput};
+    use crate::report::Reporter;
+    use crate::stream::Stream;
+
+    const DEBOUNCE: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn ignores_bounces() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(false, DEBOUNCE, start);
+
+        assert!(!debouncer.update(true, at(5)));
+        assert!(!debouncer.update(false, at(10)));
+        assert!(!debouncer.update(true, at(15)));
+        assert!(!debouncer.update(true, at(30)));
+        assert!(!debouncer.value());
+    }
+
+    #[test]
+    fn accepts_stable_change() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(false, DEBOUNCE, start);
+
+        assert!(!debouncer.update(true, at(5)));
+        assert!(!debouncer.update(true, at(15)));
+        assert!(debouncer.update(true, at(25)));
+        assert!(debouncer.value());
+        assert_eq!(debouncer.changed_at(), at(5));
+        assert!(!debouncer.update(true, at(30)));
+    }
+
+    #[test]
+    fn sampler_reports_changes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let reporter = Reporter::default();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        reporter.connect(Stream::Tcp(stream));
+        let (mut linkage, _) = listener.accept().unwrap();
+
+        let switch = SimGpioInput::open(17, InputPull::None);
+        let started = Instant::now();
+        start_input_sampler(
+            reporter,
+            vec![(3, Box::new(switch.clone()), Duration::from_millis(10))],
+            Vec::new(),
+            started,
+        );
+        switch.set(true);
+
+        let mut bytes = Bytes::default();
+        loop {
+            linkage.read_exact(&mut bytes).unwrap();
+            if let CarburetorToLinkage::DigitalInput {
+                pin: 3,
+                value: true,
+                timestamp_ms,
+            } = CarburetorToLinkage::try_from(bytes).unwrap()
+            {
+                assert!(timestamp_ms as u128 <= started.elapsed().as_millis());
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn sampler_reports_analog_changes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let reporter = Reporter::default();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        reporter.connect(Stream::Tcp(stream));
+        let (mut linkage, _) = listener.accept().unwrap();
+
+        let potentiometer = SimAdcInput::open(0, 1);
+        potentiometer.set(1000);
+        start_input_sampler(
+            reporter,
+            Vec::new(),
+            vec![(
+                AnalogInputConfig::new(2, 0, 1, 4000, 0.1),
+                Box::new(potentiometer.clone()),
+            )],
+            Instant::now(),
+        );
+
+        let mut bytes = Bytes::default();
+        // Returns the next reported value that differs from `previous`, as the latest value is
+        // also reported every 100ms.
+        let mut next_change = |previous: Option<f32>| loop {
+            linkage.read_exact(&mut bytes).unwrap();
+            match CarburetorToLinkage::try_from(bytes).unwrap() {
+                CarburetorToLinkage::AnalogInput { pin: 2, value } if Some(value) != previous => {
+                    return value
+                }
+                CarburetorToLinkage::AnalogInput { pin: 2, .. } => {}
+                message => panic!("expected analog input 2, got {message:?}"),
+            }
+        };
+        assert_eq!(next_change(None), 0.25);
+
+        potentiometer.set(3600);
+        assert_eq!(next_change(Some(0.25)), 0.9);
+    }
+
+    #[test]
+    fn analog_changes_below_the_threshold_are_not_reported() {
+        let reporter = Reporter::default();
+        let potentiometer = SimAdcInput::open(0, 1);
+        potentiometer.set(1000);
+        let mut input = AnalogInput {
+            config: AnalogInputConfig::new(2, 0, 1, 4000, 0.25),
+            source: Box::new(potentiometer.clone()),
+            value: None,
+            reported: None,
+            failing: false,
+        };
+
+        assert!(input.sample(&reporter));
+        input.report(&reporter);
+
+        potentiometer.set(1900);
+        assert!(!input.sample(&reporter));
+        potentiometer.set(100);
+        assert!(!input.sample(&reporter));
+        potentiometer.set(2000);
+        assert!(input.sample(&reporter));
+        input.report(&reporter);
+
+        // Measured from the reported value, not the last sample.
+        potentiometer.set(1100);
+        assert!(!input.sample(&reporter));
+        potentiometer.set(1000);
+        assert!(input.sample(&reporter));
+    }
+}
@@ -1,15 +1,3 @@
-use std::time::Duration;
-
-use crate::{PULSE_DELTA_US, PULSE_NEUTRAL_US};
-
-/// A message type that has the following format:
-///
-///  - 0: u8 describing the instruction.
-///  - 1: u8 describing the channel.
-///  - 2..=3: empty (do not use, could be used for future additions).
-///  - 4..=7: when when sending a float, these four bytes together form a big-endian f32.
-pub(crate) type MessageBytes = [u8; 8];
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
 pub(crate) enum Direction {
@@ -68,11 +56,9 @@ impl Speed {
         Self::new(-1.0).unwrap()
     }
 
-    /// Returns the duration of this [`Speed`].
-    #[allow(dead_code)]
-    pub(crate) fn duration(&self) -> Duration {
-        let micros = PULSE_NEUTRAL_US as i32 + (PULSE_DELTA_US as f32 * self.0) as i32;
-        Duration::from_micros(micros as u64)
+    /// Returns the value of this [`Speed`], within -1.0..=1.0.
+    pub(crate) fn value(&self) -> f32 {
+        self.0
     }
 
     /// Returns the direction of this [`Speed`].
@@ -84,7 +70,7 @@ impl Speed {
         match self.0 {
             s if s < 0.0 => Direction::Backward,
             s if s > 0.0 => Direction::Forward,
-            s if s == 0.0 => Direction::Neutral,
+            0.0 => Direction::Neutral,
             s => panic!("invalid speed '{s}': not in range -1.0..=1.0"),
         }
     }
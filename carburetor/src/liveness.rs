@@ -0,0 +1,54 @@
+//! Tracking whether the loop that serves Linkage is still making progress, so the systemd watchdog
+//! is only fed while it is.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the loop that serves Linkage beats while it is waiting for a connection or a frame.
+pub(crate) const BEAT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How late a beat may be before the loop is considered wedged.
+const SLACK: Duration = Duration::from_millis(500);
+
+/// When the loop that serves Linkage promised to beat again.
+pub(crate) struct Liveness {
+    deadline: Mutex<Instant>,
+}
+
+impl Liveness {
+    /// Creates a liveness that is alive until the loop has had the time for its first beat.
+    pub(crate) fn new() -> Self {
+        Self {
+            deadline: Mutex::new(Instant::now() + BEAT_INTERVAL + SLACK),
+        }
+    }
+
+    /// Records that the loop is running, and will beat again within `next_beat`.
+    pub(crate) fn beat(&self, next_beat: Duration) {
+        *self.deadline.lock().unwrap() = Instant::now() + next_beat + SLACK;
+    }
+
+    /// Returns whether the loop beat as often as it promised.
+    pub(crate) fn is_alive(&self) -> bool {
+        Instant::now() < *self.deadline.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Liveness;
+
+    #[test]
+    fn dies_without_beats() {
+        let liveness = Liveness::new();
+        assert!(liveness.is_alive());
+        liveness.beat(Duration::from_secs(60));
+        assert!(liveness.is_alive());
+
+        // Promising the next beat in the past stands in for a loop that is late.
+        *liveness.deadline.lock().unwrap() -= Duration::from_secs(120);
+        assert!(!liveness.is_alive());
+    }
+}
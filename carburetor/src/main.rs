@@ -1,24 +1,46 @@
+use std::collections::HashMap;
 use std::error::Error;
-use std::io::{ErrorKind, Read};
-use std::net::TcpListener;
-use std::process::exit;
-use std::sync::mpsc::channel;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-#[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
-use rppal::pwm::Channel;
-use simple_signal::{self, Signal};
+use clap::{Parser, Subcommand, ValueEnum};
+use config::{AnalogInputConfig, CarburetorConfig, OutputBackend, Transport};
 
-use crate::control_channel::control_channel;
-use crate::instruction::{MessageBytes, Speed};
+use crate::connection::Connected;
+use crate::control_channel::ControlChannel;
+use crate::controls::Controls;
+use crate::datagram::DatagramPeer;
+use crate::digital::DigitalOutputs;
+use crate::estop::EStop;
+use crate::liveness::{Liveness, BEAT_INTERVAL};
+use crate::metrics::Metrics;
+use crate::report::Reporter;
+use crate::stream::{ListenAddress, Listener};
 
+mod arming;
+mod bench;
+mod calibration;
+mod connection;
 mod control_channel;
+mod controls;
+mod datagram;
+mod digital;
+mod estop;
+mod input;
 mod instruction;
-
-#[cfg(not(all(target_arch = "arm", target_os = "linux", target_env = "gnu")))]
-use control_channel::Channel;
-use messaging::LinkageToCarburetor;
+mod liveness;
+mod metrics;
+mod output;
+mod report;
+mod shutdown;
+mod slew;
+mod stream;
+mod watchdog;
 
 const WELCOME_MESSAGE: &str = r#"
                    _
@@ -31,101 +53,403 @@ const WELCOME_MESSAGE: &str = r#"
              By Koen & Bauke Westendorp, 2023.
 "#;
 
-#[allow(dead_code)]
-const PERIOD_MS: u64 = 20; // 20 ms = 50 Hz
-#[allow(dead_code)]
-const PULSE_DELTA_US: u64 = 500;
-#[allow(dead_code)]
-const PULSE_NEUTRAL_US: u64 = 1500;
+/// The motor controller daemon of Linkage.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Read the config from this file instead of `$XDG_CONFIG_HOME/linkage/config.toml`.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Listen for Linkage on TCP at this address and the configured port, instead of on the
+    /// configured socket or all interfaces.
+    #[arg(long, value_name = "ADDR", conflicts_with = "socket")]
+    bind: Option<IpAddr>,
+    /// Listen for Linkage on this Unix socket instead of the configured address.
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+    /// Use this output backend instead of the one in the config.
+    #[arg(long)]
+    backend: Option<Backend>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the configured channels, digital outputs, and digital and analog inputs.
+    ListChannels,
+    /// Output a fixed pulse width on a channel until interrupted, bypassing its calibration.
+    Pulse {
+        channel: u8,
+        /// The pulse width in microseconds. Must lie within the calibrated range of the channel.
+        pulse_us: u64,
+    },
+    /// Sweep a channel from neutral to forward, to reverse, and back to neutral.
+    Sweep {
+        channel: u8,
+        /// The largest speed to sweep to, in either direction.
+        #[arg(long, default_value_t = 0.3)]
+        max_speed: f32,
+        /// How long the whole sweep takes, in milliseconds.
+        #[arg(long, default_value_t = 4000)]
+        duration_ms: u64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Backend {
+    Sim,
+    Sysfs,
+    Rppal,
+}
+
+impl From<Backend> for OutputBackend {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Sim => OutputBackend::Sim,
+            Backend::Sysfs => OutputBackend::Sysfs,
+            Backend::Rppal => OutputBackend::Rppal,
+        }
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let config = config::config()?;
-    logging::Logger::new(config.carburetor().logger_port()).start();
+    let started = Instant::now();
+    let args = Args::parse();
 
-    log::info!("{WELCOME_MESSAGE}");
+    let config = match &args.config {
+        Some(path) => config::config_from_file(path)?,
+        None => config::config()?,
+    };
+    let mut config = config.carburetor().clone();
+    if let Some(backend) = args.backend {
+        config.set_backend(backend.into());
+    }
 
-    #[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
-    log::info!("Carburetor detected you are running on a Raspberry Pi!");
+    match args.command {
+        None => {
+            // The command line takes precedence over the config.
+            let address = match (args.socket, args.bind, config.socket_path()) {
+                (Some(path), _, _) => ListenAddress::Unix(path),
+                (None, None, Some(path)) => ListenAddress::Unix(path.to_path_buf()),
+                (None, bind, _) => {
+                    let port =
+                        u16::try_from(config.port()).map_err(|_| "invalid carburetor port")?;
+                    let ip = bind.unwrap_or(Ipv4Addr::UNSPECIFIED.into());
+                    ListenAddress::Tcp(SocketAddr::new(ip, port))
+                }
+            };
+            // Taken before the logger starts any threads, as this changes the environment.
+            let activated = systemd::take_listener();
+            start_logger(&config);
+            serve(&config, address, activated, started)
+        }
+        Some(Command::ListChannels) => bench::list_channels(&config),
+        Some(Command::Pulse { channel, pulse_us }) => {
+            start_logger(&config);
+            bench::pulse(&config, channel, Duration::from_micros(pulse_us))
+        }
+        Some(Command::Sweep {
+            channel,
+            max_speed,
+            duration_ms,
+        }) => {
+            start_logger(&config);
+            bench::sweep(
+                &config,
+                channel,
+                max_speed,
+                Duration::from_millis(duration_ms),
+            )
+        }
+    }
+}
+
+fn start_logger(config: &CarburetorConfig) {
+    logging::Logger::new(config.logger_port()).start();
+    log::info!("Using the {:?} output backend.", config.backend());
+}
 
-    let address = format!("0.0.0.0:{}", config.carburetor().port());
+/// Runs the daemon, listening for Linkage on `address`, or on the listener systemd `activated`
+/// Carburetor with.
+fn serve(
+    config: &CarburetorConfig,
+    address: ListenAddress,
+    activated: Option<systemd::Listener>,
+    started: Instant,
+) -> Result<(), Box<dyn Error>> {
+    log::info!("{WELCOME_MESSAGE}");
 
     log::info!("Setting up...");
-    let (tx0, rx0) = channel();
-    let (tx1, rx1) = channel();
+    let notifier = Arc::new(systemd::Notifier::from_env());
+    let reporter = Reporter::default();
+    let metrics = Arc::new(Metrics::new(started));
+    let mut channels = HashMap::new();
+    let mut pwm_channels = HashMap::new();
+    let mut control_channels = Vec::new();
+    for channel_config in config.channels() {
+        let channel = channel_config.channel();
+        if channels.contains_key(&channel) {
+            return Err(format!("channel {channel} is configured more than once").into());
+        }
+        // Two channels on one output would fight over it.
+        let pwm_channel = channel_config.pwm_channel();
+        if let Some(other) = pwm_channels.insert(pwm_channel, channel) {
+            return Err(format!(
+                "channels {other} and {channel} are both on PWM channel {pwm_channel}"
+            )
+            .into());
+        }
+
+        let control = ControlChannel::open(config, channel_config, reporter.clone())?
+            .with_metrics(Arc::clone(&metrics));
+
+        let (tx, rx) = mpsc::channel();
+        channels.insert(channel, control.handle(tx));
+        control_channels.push((control, rx));
+    }
+
+    let mut digital_outputs = DigitalOutputs::default();
+    for digital_config in config.digital_outputs() {
+        let pin = digital_config.pin();
+
+        log::info!(
+            "Opening GPIO pin {} for digital output {pin}...",
+            digital_config.gpio_pin()
+        );
+        let gpio = output::open_gpio(
+            config,
+            digital_config.gpio_pin(),
+            digital_config.safe_state(),
+        )
+        .map_err(|error| {
+            format!(
+                "failed to open GPIO pin {} for digital output {pin}: {error}",
+                digital_config.gpio_pin()
+            )
+        })?;
+
+        digital_outputs.insert(pin, gpio, digital_config.safe_state())?;
+    }
+
+    let estop_path = config::carburetor_estop_path()
+        .map_err(|error| log::error!("Cannot persist the emergency stop latch: {error}"))
+        .ok();
+    let controls = Arc::new(Controls {
+        channels,
+        digital_outputs: Mutex::new(digital_outputs),
+        estop: EStop::new(estop_path, config.estop_survives_restart()),
+    });
 
-    simple_signal::set_handler(&[Signal::Int, Signal::Term], {
-        let tx0 = tx0.clone();
-        let tx1 = tx1.clone();
-        move |signals| {
-            log::info!("Caught: {signals:?}");
+    let mut digital_inputs = Vec::new();
+    for input_config in config.digital_inputs() {
+        let pin = input_config.pin();
 
-            // Clean up by putting both at neutral.
-            log::info!("Cleaning up...");
-            tx0.send(Speed::neutral()).unwrap();
-            tx1.send(Speed::neutral()).unwrap();
+        if digital_inputs.iter().any(|(other, _, _)| *other == pin) {
+            return Err(format!("digital input {pin} is configured more than once").into());
+        }
+
+        log::info!(
+            "Opening GPIO pin {} for digital input {pin}...",
+            input_config.gpio_pin()
+        );
+        let gpio = output::open_gpio_input(config, input_config.gpio_pin(), input_config.pull())
+            .map_err(|error| {
+                format!(
+                    "failed to open GPIO pin {} for digital input {pin}: {error}",
+                    input_config.gpio_pin()
+                )
+            })?;
+
+        let debounce = Duration::from_millis(input_config.debounce_ms());
+        digital_inputs.push((pin, gpio, debounce));
+    }
+
+    let mut analog_inputs: Vec<(AnalogInputConfig, _)> = Vec::new();
+    for input_config in config.analog_inputs() {
+        let pin = input_config.pin();
+
+        if analog_inputs.iter().any(|(other, _)| other.pin() == pin) {
+            return Err(format!("analog input {pin} is configured more than once").into());
+        }
+        if input_config.max_raw() == 0 {
+            return Err(format!("analog input {pin}: max_raw must be positive").into());
+        }
+        if !(0.0..1.0).contains(&input_config.threshold()) {
+            return Err(format!(
+                "analog input {pin}: threshold must be within 0.0..1.0, got {}",
+                input_config.threshold()
+            )
+            .into());
+        }
+
+        log::info!(
+            "Opening channel {} of IIO device {} for analog input {pin}...",
+            input_config.iio_channel(),
+            input_config.iio_device()
+        );
+        let adc = output::open_adc_input(
+            config,
+            input_config.iio_device(),
+            input_config.iio_channel(),
+        )
+        .map_err(|error| {
+            format!(
+                "failed to open channel {} of IIO device {} for analog input {pin}: {error}",
+                input_config.iio_channel(),
+                input_config.iio_device()
+            )
+        })?;
 
-            // Here, we wait for 10 ms in order to give the motor control threads a chance to reset
-            // the pwm to neutral. Otherwise, we might exit _before_ the neutral instruction has
-            // been carried out.
-            thread::sleep(Duration::from_millis(10));
+        analog_inputs.push((input_config.clone(), adc));
+    }
 
-            log::info!("Bye!");
-            exit(0)
+    let (server, address) = match activated {
+        Some(listener) => {
+            let server = Listener::from(listener);
+            let address = server
+                .address()
+                .map_err(|error| format!("cannot use the socket passed by systemd: {error}"))?;
+            log::info!("Using the socket on {address} passed by systemd.");
+            (server, address)
+        }
+        None => {
+            let server = address
+                .bind()
+                .map_err(|error| format!("failed to listen on {address}: {error}"))?;
+            (server, address)
+        }
+    };
+    let datagram_socket =
+        match config.transport() {
+            Transport::Tcp => None,
+            Transport::Udp => Some(address.bind_datagrams().map_err(|error| {
+                format!("failed to listen for datagrams on {address}: {error}")
+            })?),
+        };
+    let shutdown = shutdown::handle_signals({
+        let reporter = reporter.clone();
+        let address = address.clone();
+        move || {
+            // Stop reading from Linkage, and wake up the listener, so the main thread can shut
+            // down the channels.
+            reporter.close();
+            address.wake();
         }
     });
 
     log::info!("Spawning device control threads...");
-    thread::spawn(|| control_channel(Channel::Pwm0, rx0));
-    thread::spawn(|| control_channel(Channel::Pwm1, rx1));
-
-    log::info!("Setup completed. Listening on {}...", address);
-    let server = TcpListener::bind(address).expect("address should be valid");
-    for (n, stream) in server.incoming().enumerate() {
-        let mut stream = stream?;
-        let peer = stream.peer_addr()?;
-        let local = stream.local_addr()?;
-        log::info!("({n}) Received stream from {peer} on {local}.",);
-
-        let mut message_bytes = MessageBytes::default();
-        loop {
-            // We read from this stream until the end of this connection into buf.
-            match stream.read_exact(&mut message_bytes) {
-                Ok(_) => {}
-                // If the connection was closed, break the loop.
-                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
-                // On any other error, propagate the error.
-                Err(e) => return Err(e)?,
+    let mut channel_states = Vec::new();
+    let mut channel_threads = Vec::new();
+    for (control, rx) in control_channels {
+        channel_states.push((control.channel(), control.state()));
+        channel_threads.push((control.channel(), thread::spawn(move || control.run(rx))));
+    }
+    let liveness = Arc::new(Liveness::new());
+    report::start_status_reporter(
+        reporter.clone(),
+        channel_states,
+        Arc::clone(&controls),
+        Arc::clone(&notifier),
+        Arc::clone(&liveness),
+    );
+    input::start_input_sampler(reporter.clone(), digital_inputs, analog_inputs, started);
+    let datagram_peer = datagram_socket.map(|socket| {
+        log::info!("Receiving instructions as datagrams on {address}.");
+        let peer = Arc::new(DatagramPeer::default());
+        datagram::start_receiver(
+            socket,
+            Arc::clone(&peer),
+            Arc::clone(&controls),
+            reporter.clone(),
+            Arc::clone(&metrics),
+        );
+        peer
+    });
+    match u16::try_from(config.metrics_port()) {
+        Ok(port) => {
+            if let Err(error) = metrics::start_metrics_endpoint(port, Arc::clone(&metrics)) {
+                log::error!("Failed to serve metrics on port {port}: {error}");
             }
+        }
+        Err(_) => log::error!("Invalid metrics port {}.", config.metrics_port()),
+    }
 
-            log::trace!("Received message: {message_bytes:?}");
-
-            match LinkageToCarburetor::try_from(message_bytes) {
-                Ok(message) => match message {
-                    LinkageToCarburetor::MotorInstruction { channel, speed } => {
-                        let sender = match channel {
-                            0 => tx0.clone(),
-                            1 => tx1.clone(),
-                            channel => {
-                                log::error!("Instruction channel {channel} does not exist.");
-                                continue;
-                            }
-                        };
-
-                        if let Some(speed) = Speed::new(speed) {
-                            sender.send(speed)?;
-                        }
-                    }
-                },
-                Err(e) => return Err(e)?,
-            }
+    let (connections, acceptor) = connection::start_acceptor(
+        server,
+        address.clone(),
+        Arc::clone(&shutdown),
+        Arc::clone(&metrics),
+    );
+
+    log::info!("Setup completed. Listening on {address}...");
+    notifier.ready();
+    let policy = config.on_malformed_frame();
+    let mut served = 0;
+    loop {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        liveness.beat(BEAT_INTERVAL);
+        let Connected { n, stream, linkage } = match connections.recv_timeout(BEAT_INTERVAL) {
+            Ok(connected) => connected,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        log::info!("({n}) {linkage} connected.");
+        match stream.try_clone() {
+            Ok(report_stream) => reporter.connect(report_stream),
+            Err(e) => log::warn!("({n}) Failed to clone stream, not reporting to Linkage: {e}"),
+        }
+        match (&datagram_peer, stream.peer_addr()) {
+            (Some(datagram_peer), Some(peer)) => datagram_peer.connect(peer.ip()),
+            (Some(_), None) => log::error!("({n}) Unknown peer, ignoring its datagrams."),
+            (None, _) => {}
         }
+        metrics.connected(linkage);
+        if served > 0 {
+            // The ESCs may have lost their arming while Linkage was away.
+            controls.arm();
+        }
+        served += 1;
 
-        // Clean up by putting both at neutral.
-        log::info!("({n}) Connection closed. Resetting motors to neutral.");
-        tx0.send(Speed::neutral()).unwrap();
-        tx1.send(Speed::neutral()).unwrap();
+        if let Err(e) =
+            connection::handle_connection(stream, &controls, &reporter, policy, &metrics, &liveness)
+        {
+            log::error!("({n}) Failed to read from stream: {e}");
+        }
+
+        // Clean up by putting all channels at neutral, and all digital outputs in their safe state.
+        if let Some(datagram_peer) = &datagram_peer {
+            datagram_peer.disconnect();
+        }
+        metrics.disconnected();
+        let snapshot = metrics.snapshot();
+        log::info!(
+            "({n}) Connection closed. Resetting motors to neutral and digital outputs to their safe state. So far, {} malformed frames were received and {} instructions were rejected.",
+            snapshot.malformed_frames,
+            snapshot.rejected_instructions
+        );
+        reporter.disconnect();
+        controls.reset();
+
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
         log::info!("Still listening...");
     }
 
-    unreachable!("server.incoming() cannot return None")
+    // The listener was woken up on shutdown, so the acceptor stops and removes its socket.
+    if acceptor.join().is_err() {
+        log::error!("The thread accepting connections panicked.");
+    }
+
+    // Clean up by putting all channels at neutral, and all digital outputs in their safe state.
+    log::info!("Cleaning up...");
+    notifier.stopping();
+    shutdown::stop_channels(&controls, channel_threads)?;
+
+    log::info!("Bye!");
+    Ok(())
 }
@@ -0,0 +1,340 @@
+//! Runtime metrics, served as JSON on a local endpoint that Gauge folds into its system info.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use messaging::{DecodeErrorCounts, Handshake, LinkMonitor, MessageError, Stamp};
+use system_info::{CarburetorMetrics, ChannelMetrics, LatencyHistogram, OutputSample};
+
+use crate::connection;
+
+/// The upper bounds of the buckets of the apply latency histogram, in µs.
+const LATENCY_BOUNDS_US: [u64; 8] = [50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// The number of output changes that are kept for every channel.
+const HISTORY_LENGTH: usize = 20;
+
+/// The window over which message rates are counted.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// If a client does not read the metrics within this time, it does not get them.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Counts events, and how many of them happened within the last [`RATE_WINDOW`].
+#[derive(Debug, Default)]
+struct RateCounter {
+    total: u64,
+    recent: VecDeque<Instant>,
+}
+
+impl RateCounter {
+    fn record(&mut self, now: Instant) {
+        self.total += 1;
+        self.recent.push_back(now);
+        self.trim(now);
+    }
+
+    fn recent(&mut self, now: Instant) -> u32 {
+        self.trim(now);
+        self.recent.len() as u32
+    }
+
+    fn trim(&mut self, now: Instant) {
+        while let Some(at) = self.recent.front() {
+            if now.duration_since(*at) < RATE_WINDOW {
+                break;
+            }
+            self.recent.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ChannelRecord {
+    messages: RateCounter,
+    history: VecDeque<OutputSample>,
+}
+
+#[derive(Debug, Default)]
+struct Records {
+    messages: RateCounter,
+    channels: BTreeMap<u8, ChannelRecord>,
+    latency_counts: [u64; LATENCY_BOUNDS_US.len() + 1],
+    malformed_frames: u64,
+    decode_errors: DecodeErrorCounts,
+    rejected_instructions: u64,
+    connections: u64,
+    connected: bool,
+    linkage: Option<Handshake>,
+    /// Measures the stamps of the frames from the Linkage that is connected.
+    linkage_link: LinkMonitor,
+}
+
+/// Collects the metrics of Carburetor, shared between the connection, the control threads and the
+/// metrics endpoint.
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    started: Instant,
+    records: Mutex<Records>,
+}
+
+impl Metrics {
+    /// Creates empty metrics, counting the uptime from `started`.
+    pub(crate) fn new(started: Instant) -> Self {
+        Self {
+            started,
+            records: Mutex::default(),
+        }
+    }
+
+    /// Records a message from Linkage, for `channel` if it is an instruction for a channel.
+    pub(crate) fn message_received(&self, channel: Option<u8>) {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap();
+        records.messages.record(now);
+        if let Some(channel) = channel {
+            records
+                .channels
+                .entry(channel)
+                .or_default()
+                .messages
+                .record(now);
+        }
+    }
+
+    /// Records a batch from Linkage as one message, and as a message for each of its `channels`.
+    pub(crate) fn batch_received(&self, channels: impl IntoIterator<Item = u8>) {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap();
+        records.messages.record(now);
+        for channel in channels {
+            records
+                .channels
+                .entry(channel)
+                .or_default()
+                .messages
+                .record(now);
+        }
+    }
+
+    /// Records a frame that could not be decoded because of `error`, and returns how many errors
+    /// of its kind there have been so far.
+    pub(crate) fn malformed_frame(&self, error: &MessageError) -> u64 {
+        let mut records = self.records.lock().unwrap();
+        records.malformed_frames += 1;
+        records.decode_errors.record(error)
+    }
+
+    /// Records a handshake that could not be decoded, or was refused because of `error`.
+    pub(crate) fn handshake_refused(&self, error: &MessageError) {
+        self.records.lock().unwrap().decode_errors.record(error);
+    }
+
+    /// Records the `stamp` of a frame from Linkage.
+    pub(crate) fn stamp_received(&self, stamp: Stamp) {
+        self.records.lock().unwrap().linkage_link.received(stamp);
+    }
+
+    /// Records a message that could not be carried out.
+    pub(crate) fn instruction_rejected(&self) {
+        self.records.lock().unwrap().rejected_instructions += 1;
+    }
+
+    /// Records that Linkage connected, introducing itself with `linkage`.
+    pub(crate) fn connected(&self, linkage: Handshake) {
+        let mut records = self.records.lock().unwrap();
+        records.connections += 1;
+        records.connected = true;
+        records.linkage = Some(linkage);
+        // Every Linkage numbers its frames from 0.
+        records.linkage_link = LinkMonitor::new();
+    }
+
+    /// Records that Linkage disconnected.
+    pub(crate) fn disconnected(&self) {
+        self.records.lock().unwrap().connected = false;
+    }
+
+    /// Records that writing `pulse_width` to the output of `channel` took `latency`. The write is
+    /// added to the history of the channel if it `changed` the output.
+    pub(crate) fn output_written(
+        &self,
+        channel: u8,
+        pulse_width: Duration,
+        latency: Duration,
+        changed: bool,
+    ) {
+        let latency_us = latency.as_micros() as u64;
+        let bucket = LATENCY_BOUNDS_US
+            .iter()
+            .position(|bound| latency_us < *bound)
+            .unwrap_or(LATENCY_BOUNDS_US.len());
+
+        let mut records = self.records.lock().unwrap();
+        records.latency_counts[bucket] += 1;
+
+        if changed {
+            let history = &mut records.channels.entry(channel).or_default().history;
+            if history.len() == HISTORY_LENGTH {
+                history.pop_front();
+            }
+            history.push_back(OutputSample {
+                at_ms: self.started.elapsed().as_millis() as u64,
+                pulse_width_us: pulse_width.as_micros() as u64,
+            });
+        }
+    }
+
+    /// Returns the metrics as they are right now.
+    pub(crate) fn snapshot(&self) -> CarburetorMetrics {
+        let now = Instant::now();
+        let mut records = self.records.lock().unwrap();
+
+        let channels = records
+            .channels
+            .iter_mut()
+            .map(|(channel, record)| ChannelMetrics {
+                channel: *channel,
+                messages_per_second: record.messages.recent(now),
+                messages: record.messages.total,
+                history: record.history.iter().cloned().collect(),
+            })
+            .collect();
+
+        CarburetorMetrics {
+            uptime_ms: now.duration_since(self.started).as_millis() as u64,
+            connections: records.connections,
+            connected: records.connected,
+            messages_per_second: records.messages.recent(now),
+            malformed_frames: records.malformed_frames,
+            decode_errors: records.decode_errors,
+            rejected_instructions: records.rejected_instructions,
+            apply_latency: LatencyHistogram {
+                bounds_us: LATENCY_BOUNDS_US.to_vec(),
+                counts: records.latency_counts.to_vec(),
+            },
+            channels,
+            handshake: Some(connection::own_handshake()),
+            linkage_handshake: records.linkage,
+            linkage_link: records.linkage_link.stats(),
+        }
+    }
+}
+
+/// Serves the metrics on `port` of localhost. Every client is sent the current metrics as a single
+/// line of JSON, after which the connection is closed.
+pub(crate) fn start_metrics_endpoint(port: u16, metrics: Arc<Metrics>) -> io::Result<()> {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    log::warn!("Failed to accept metrics client: {error}");
+                    continue;
+                }
+            };
+
+            let mut json = match serde_json::to_string(&metrics.snapshot()) {
+                Ok(json) => json,
+                Err(error) => {
+                    log::error!("Failed to encode metrics: {error}");
+                    continue;
+                }
+            };
+            json.push('\n');
+
+            let written = stream
+                .set_write_timeout(Some(WRITE_TIMEOUT))
+                .and_then(|_| stream.write_all(json.as_bytes()));
+            if let Err(error) = written {
+                log::debug!("Failed to send metrics: {error}");
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use messaging::{Component, Handshake, MessageError};
+
+    use super::{Metrics, HISTORY_LENGTH};
+
+    #[test]
+    fn snapshot_counts_messages_and_latency() {
+        let metrics = Metrics::new(Instant::now());
+        let linkage = Handshake::new(Component::LinkageLib, "0.1.0");
+        metrics.connected(linkage);
+        metrics.message_received(Some(1));
+        metrics.message_received(Some(1));
+        metrics.message_received(None);
+        metrics.instruction_rejected();
+        assert_eq!(
+            metrics.malformed_frame(&MessageError::UnknownOpcode(0x10)),
+            1
+        );
+        assert_eq!(
+            metrics.malformed_frame(&MessageError::UnknownOpcode(0x11)),
+            2
+        );
+        assert_eq!(
+            metrics.malformed_frame(&MessageError::UnknownFlags(0x80)),
+            1
+        );
+        metrics.output_written(
+            1,
+            Duration::from_micros(1500),
+            Duration::from_micros(10),
+            true,
+        );
+        metrics.output_written(
+            1,
+            Duration::from_micros(1500),
+            Duration::from_millis(20),
+            false,
+        );
+
+        let snapshot = metrics.snapshot();
+        assert!(snapshot.connected);
+        assert_eq!(snapshot.linkage_handshake, Some(linkage));
+        assert_eq!(snapshot.connections, 1);
+        assert_eq!(snapshot.messages_per_second, 3);
+        assert_eq!(snapshot.malformed_frames, 3);
+        assert_eq!(snapshot.decode_errors.unknown_opcode, 2);
+        assert_eq!(snapshot.decode_errors.corrupt_frame, 1);
+        assert_eq!(snapshot.rejected_instructions, 1);
+
+        let counts = &snapshot.apply_latency.counts;
+        assert_eq!(counts.len(), snapshot.apply_latency.bounds_us.len() + 1);
+        assert_eq!(counts.first(), Some(&1));
+        assert_eq!(counts.last(), Some(&1));
+
+        let channel = &snapshot.channels[0];
+        assert_eq!(channel.channel, 1);
+        assert_eq!(channel.messages_per_second, 2);
+        assert_eq!(channel.messages, 2);
+        assert_eq!(channel.history.len(), 1);
+        assert_eq!(channel.history[0].pulse_width_us, 1500);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let metrics = Metrics::new(Instant::now());
+        for us in 0..HISTORY_LENGTH as u64 + 5 {
+            metrics.output_written(0, Duration::from_micros(us), Duration::ZERO, true);
+        }
+
+        let history = &metrics.snapshot().channels[0].history;
+        assert_eq!(history.len(), HISTORY_LENGTH);
+        assert_eq!(history[0].pulse_width_us, 5);
+    }
+}
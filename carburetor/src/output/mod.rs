@@ -0,0 +1,127 @@
+//! Backends that turn pulse widths into actual PWM signals, drive digital outputs and read
+//! digital and analog inputs.
+
+use std::error::Error;
+use std::time::Duration;
+
+use config::{CarburetorConfig, InputPull, OutputBackend};
+
+#[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
+mod rppal;
+pub(crate) mod sim;
+mod sysfs;
+
+pub(crate) type OutputError = Box<dyn Error + Send + Sync>;
+
+/// A single hardware PWM output.
+pub(crate) trait PwmOutput: Send {
+    /// Sets the width of the pulse sent every period.
+    fn set_pulse_width(&mut self, pulse_width: Duration) -> Result<(), OutputError>;
+}
+
+/// A single digital output pin.
+pub(crate) trait GpioOutput: Send {
+    /// Drives the pin high (`true`) or low (`false`).
+    fn set(&mut self, value: bool) -> Result<(), OutputError>;
+}
+
+/// A single digital input pin.
+pub(crate) trait GpioInput: Send {
+    /// Returns whether the pin is high.
+    fn read(&mut self) -> Result<bool, OutputError>;
+}
+
+/// A single channel of an analog-to-digital converter.
+pub(crate) trait AdcInput: Send {
+    /// Returns the raw reading of the channel.
+    fn read(&mut self) -> Result<u32, OutputError>;
+}
+
+/// Opens hardware PWM channel `channel` on the backend selected in `config`, and enables it with
+/// the given `period` and initial `pulse_width`.
+pub(crate) fn open_pwm(
+    config: &CarburetorConfig,
+    channel: u8,
+    period: Duration,
+    pulse_width: Duration,
+) -> Result<Box<dyn PwmOutput>, OutputError> {
+    let output: Box<dyn PwmOutput> = match config.backend() {
+        #[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
+        OutputBackend::Rppal => Box::new(rppal::RppalPwm::open(channel, period, pulse_width)?),
+        #[cfg(not(all(target_arch = "arm", target_os = "linux", target_env = "gnu")))]
+        OutputBackend::Rppal => {
+            return Err("the rppal backend is only available when built for a Raspberry Pi".into())
+        }
+        OutputBackend::Sysfs => Box::new(sysfs::SysfsPwm::open(
+            config.sysfs_pwm_chip(),
+            channel,
+            period,
+            pulse_width,
+        )?),
+        OutputBackend::Sim => Box::new(sim::SimPwm::open(channel, period, pulse_width)),
+    };
+
+    Ok(output)
+}
+
+/// Opens GPIO pin `pin` as an output on the backend selected in `config`, and drives it to the
+/// initial `value`.
+pub(crate) fn open_gpio(
+    config: &CarburetorConfig,
+    pin: u32,
+    value: bool,
+) -> Result<Box<dyn GpioOutput>, OutputError> {
+    let output: Box<dyn GpioOutput> = match config.backend() {
+        #[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
+        OutputBackend::Rppal => Box::new(rppal::RppalGpio::open(pin, value)?),
+        #[cfg(not(all(target_arch = "arm", target_os = "linux", target_env = "gnu")))]
+        OutputBackend::Rppal => {
+            return Err("the rppal backend is only available when built for a Raspberry Pi".into())
+        }
+        OutputBackend::Sysfs => Box::new(sysfs::SysfsGpio::open(pin, value)?),
+        OutputBackend::Sim => Box::new(sim::SimGpio::open(pin, value)),
+    };
+
+    Ok(output)
+}
+
+/// Opens GPIO pin `pin` as an input on the backend selected in `config`, with the internal
+/// resistor selected by `pull`.
+pub(crate) fn open_gpio_input(
+    config: &CarburetorConfig,
+    pin: u32,
+    pull: InputPull,
+) -> Result<Box<dyn GpioInput>, OutputError> {
+    let input: Box<dyn GpioInput> = match config.backend() {
+        #[cfg(all(target_arch = "arm", target_os = "linux", target_env = "gnu"))]
+        OutputBackend::Rppal => Box::new(rppal::RppalGpioInput::open(pin, pull)?),
+        #[cfg(not(all(target_arch = "arm", target_os = "linux", target_env = "gnu")))]
+        OutputBackend::Rppal => {
+            return Err("the rppal backend is only available when built for a Raspberry Pi".into())
+        }
+        OutputBackend::Sysfs => Box::new(sysfs::SysfsGpioInput::open(pin, pull)?),
+        OutputBackend::Sim => Box::new(sim::SimGpioInput::open(pin, pull)),
+    };
+
+    Ok(input)
+}
+
+/// Opens channel `channel` of IIO device `device` as an analog input, on the backend selected in
+/// `config`.
+///
+/// A Raspberry Pi has no ADC of its own, so the `rppal` backend reads ADCs through IIO just like
+/// the `sysfs` backend.
+pub(crate) fn open_adc_input(
+    config: &CarburetorConfig,
+    device: u32,
+    channel: u32,
+) -> Result<Box<dyn AdcInput>, OutputError> {
+    let input: Box<dyn AdcInput> = match config.backend() {
+        OutputBackend::Rppal | OutputBackend::Sysfs => {
+            Box::new(sysfs::SysfsAdcInput::open(device, channel)?)
+        }
+        OutputBackend::Sim => Box::new(sim::SimAdcInput::open(device, channel)),
+    };
+
+    Ok(input)
+}
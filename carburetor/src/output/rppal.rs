@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use config::InputPull;
+use rppal::gpio::{Gpio, InputPin, OutputPin};
+use rppal::pwm::{Channel, Polarity, Pwm};
+
+use super::{GpioInput, GpioOutput, OutputError, PwmOutput};
+
+/// Hardware PWM on a Raspberry Pi through [`rppal`].
+pub(crate) struct RppalPwm(Pwm);
+
+impl RppalPwm {
+    pub(crate) fn open(
+        channel: u8,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Result<Self, OutputError> {
+        let channel = match channel {
+            0 => Channel::Pwm0,
+            1 => Channel::Pwm1,
+            channel => return Err(format!("Raspberry Pi has no PWM channel {channel}").into()),
+        };
+
+        let pwm = Pwm::with_period(channel, period, pulse_width, Polarity::Normal, true)?;
+        Ok(Self(pwm))
+    }
+}
+
+impl PwmOutput for RppalPwm {
+    fn set_pulse_width(&mut self, pulse_width: Duration) -> Result<(), OutputError> {
+        Ok(self.0.set_pulse_width(pulse_width)?)
+    }
+}
+
+/// A GPIO output pin on a Raspberry Pi through [`rppal`], using BCM pin numbers.
+pub(crate) struct RppalGpio(OutputPin);
+
+impl RppalGpio {
+    pub(crate) fn open(pin: u32, value: bool) -> Result<Self, OutputError> {
+        let pin = u8::try_from(pin).map_err(|_| format!("Raspberry Pi has no GPIO pin {pin}"))?;
+        let pin = Gpio::new()?.get(pin)?;
+        let output = if value {
+            pin.into_output_high()
+        } else {
+            pin.into_output_low()
+        };
+
+        Ok(Self(output))
+    }
+}
+
+impl GpioOutput for RppalGpio {
+    fn set(&mut self, value: bool) -> Result<(), OutputError> {
+        if value {
+            self.0.set_high();
+        } else {
+            self.0.set_low();
+        }
+
+        Ok(())
+    }
+}
+
+/// A GPIO input pin on a Raspberry Pi through [`rppal`], using BCM pin numbers.
+pub(crate) struct RppalGpioInput(InputPin);
+
+impl RppalGpioInput {
+    pub(crate) fn open(pin: u32, pull: InputPull) -> Result<Self, OutputError> {
+        let pin = u8::try_from(pin).map_err(|_| format!("Raspberry Pi has no GPIO pin {pin}"))?;
+        let pin = Gpio::new()?.get(pin)?;
+        let input = match pull {
+            InputPull::None => pin.into_input(),
+            InputPull::Up => pin.into_input_pullup(),
+            InputPull::Down => pin.into_input_pulldown(),
+        };
+
+        Ok(Self(input))
+    }
+}
+
+impl GpioInput for RppalGpioInput {
+    fn read(&mut self) -> Result<bool, OutputError> {
+        Ok(self.0.is_high())
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use config::InputPull;
+
+use super::{AdcInput, GpioInput, GpioOutput, OutputError, PwmOutput};
+
+/// The maximum number of values a [`SimPwm`] or [`SimGpio`] remembers.
+const MAX_HISTORY: usize = 1024;
+
+/// The recorded state of a [`SimPwm`].
+#[derive(Debug, Default)]
+pub(crate) struct SimPwmState {
+    pub(crate) period: Duration,
+    /// Every pulse width that has been set, oldest first, starting with the initial one.
+    pub(crate) history: VecDeque<Duration>,
+    /// Sent every pulse width that is set from now on, until their receivers are dropped.
+    watchers: Vec<Sender<Duration>>,
+}
+
+impl SimPwmState {
+    /// Returns the pulse width that is currently being output.
+    #[allow(dead_code)]
+    pub(crate) fn pulse_width(&self) -> Option<Duration> {
+        self.history.back().copied()
+    }
+}
+
+/// A simulated PWM output that records everything written to it in memory.
+///
+/// Clones share their recorded state, so a clone can be kept around to inspect what happened to
+/// an output that has been handed off.
+#[derive(Debug, Clone)]
+pub(crate) struct SimPwm {
+    channel: u8,
+    state: Arc<Mutex<SimPwmState>>,
+}
+
+impl SimPwm {
+    pub(crate) fn open(channel: u8, period: Duration, pulse_width: Duration) -> Self {
+        log::info!("Opened simulated PWM channel {channel}.");
+        let state = SimPwmState {
+            period,
+            history: VecDeque::from([pulse_width]),
+            watchers: Vec::new(),
+        };
+
+        Self {
+            channel,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Returns a handle to the recorded state of this output.
+    #[allow(dead_code)]
+    pub(crate) fn state(&self) -> Arc<Mutex<SimPwmState>> {
+        Arc::clone(&self.state)
+    }
+
+    /// Returns a receiver that is sent every pulse width set from now on, so tests can wait for
+    /// writes with a timeout instead of sleeping.
+    #[allow(dead_code)]
+    pub(crate) fn watch(&self) -> Receiver<Duration> {
+        let (sender, receiver) = channel();
+        self.state.lock().unwrap().watchers.push(sender);
+        receiver
+    }
+}
+
+impl PwmOutput for SimPwm {
+    fn set_pulse_width(&mut self, pulse_width: Duration) -> Result<(), OutputError> {
+        let mut state = self.state.lock().unwrap();
+        if state.history.len() == MAX_HISTORY {
+            state.history.pop_front();
+        }
+        state.history.push_back(pulse_width);
+        state
+            .watchers
+            .retain(|watcher| watcher.send(pulse_width).is_ok());
+
+        log::trace!(
+            "Simulated PWM channel {}: {pulse_width:?} every {:?}.",
+            self.channel,
+            state.period
+        );
+        Ok(())
+    }
+}
+
+/// A simulated digital output that records every value written to it in memory.
+///
+/// Like [`SimPwm`], clones share their recorded values.
+#[derive(Debug, Clone)]
+pub(crate) struct SimGpio {
+    pin: u32,
+    /// Every value that has been set, oldest first, starting with the initial one.
+    history: Arc<Mutex<VecDeque<bool>>>,
+}
+
+impl SimGpio {
+    pub(crate) fn open(pin: u32, value: bool) -> Self {
+        log::info!("Opened simulated GPIO pin {pin}.");
+        Self {
+            pin,
+            history: Arc::new(Mutex::new(VecDeque::from([value]))),
+        }
+    }
+
+    /// Returns the value that is currently being output.
+    #[allow(dead_code)]
+    pub(crate) fn value(&self) -> bool {
+        *self.history.lock().unwrap().back().unwrap()
+    }
+}
+
+impl GpioOutput for SimGpio {
+    fn set(&mut self, value: bool) -> Result<(), OutputError> {
+        let mut history = self.history.lock().unwrap();
+        if history.len() == MAX_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(value);
+
+        log::trace!("Simulated GPIO pin {}: {value}.", self.pin);
+        Ok(())
+    }
+}
+
+/// A simulated digital input, whose value is set through [`SimGpioInput::set`].
+///
+/// Clones share their value, so a clone can be kept around to drive an input that has been
+/// handed off.
+#[derive(Debug, Clone)]
+pub(crate) struct SimGpioInput {
+    value: Arc<AtomicBool>,
+}
+
+impl SimGpioInput {
+    /// Opens a simulated input, which starts out high if it is pulled up, and low otherwise.
+    pub(crate) fn open(pin: u32, pull: InputPull) -> Self {
+        log::info!("Opened simulated GPIO input pin {pin}.");
+        Self {
+            value: Arc::new(AtomicBool::new(pull == InputPull::Up)),
+        }
+    }
+
+    /// Sets the value read from this input.
+    #[allow(dead_code)]
+    pub(crate) fn set(&self, value: bool) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+}
+
+impl GpioInput for SimGpioInput {
+    fn read(&mut self) -> Result<bool, OutputError> {
+        Ok(self.value.load(Ordering::Relaxed))
+    }
+}
+
+/// A simulated analog input, whose raw reading is set through [`SimAdcInput::set`].
+///
+/// Like [`SimGpioInput`], clones share their reading.
+#[derive(Debug, Clone)]
+pub(crate) struct SimAdcInput {
+    raw: Arc<AtomicU32>,
+}
+
+impl SimAdcInput {
+    /// Opens a simulated input, which reads 0 until it is set.
+    pub(crate) fn open(device: u32, channel: u32) -> Self {
+        log::info!("Opened simulated ADC channel {channel} of IIO device {device}.");
+        Self {
+            raw: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Sets the raw reading of this input.
+    #[allow(dead_code)]
+    pub(crate) fn set(&self, raw: u32) {
+        self.raw.store(raw, Ordering::Relaxed);
+    }
+}
+
+impl AdcInput for SimAdcInput {
+    fn read(&mut self) -> Result<u32, OutputError> {
+        Ok(self.raw.load(Ordering::Relaxed))
+    }
+}
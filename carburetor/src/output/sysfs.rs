@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use config::InputPull;
+
+use super::{AdcInput, GpioInput, GpioOutput, OutputError, PwmOutput};
+
+const SYSFS_PWM_PATH: &str = "/sys/class/pwm";
+const SYSFS_GPIO_PATH: &str = "/sys/class/gpio";
+const SYSFS_IIO_PATH: &str = "/sys/bus/iio/devices";
+
+/// The number of times we check whether the kernel has created the channel or pin directory after
+/// exporting it, before giving up.
+const EXPORT_ATTEMPTS: u32 = 10;
+
+/// Hardware PWM through the Linux `/sys/class/pwm` interface, which most single-board computers
+/// support.
+pub(crate) struct SysfsPwm {
+    chip_path: PathBuf,
+    channel: u8,
+}
+
+impl SysfsPwm {
+    pub(crate) fn open(
+        chip: u32,
+        channel: u8,
+        period: Duration,
+        pulse_width: Duration,
+    ) -> Result<Self, OutputError> {
+        let pwm = Self {
+            chip_path: PathBuf::from(SYSFS_PWM_PATH).join(format!("pwmchip{chip}")),
+            channel,
+        };
+
+        if !pwm.channel_path().exists() {
+            fs::write(pwm.chip_path.join("export"), channel.to_string())?;
+        }
+
+        // The kernel creates the channel directory asynchronously, and udev may still need to
+        // fix up its permissions.
+        for _ in 0..EXPORT_ATTEMPTS {
+            if pwm.write("period", period.as_nanos()).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        pwm.write("period", period.as_nanos())?;
+        pwm.write("duty_cycle", pulse_width.as_nanos())?;
+        pwm.write("enable", 1)?;
+
+        Ok(pwm)
+    }
+
+    fn channel_path(&self) -> PathBuf {
+        self.chip_path.join(format!("pwm{}", self.channel))
+    }
+
+    fn write(&self, attribute: &str, value: impl ToString) -> std::io::Result<()> {
+        fs::write(self.channel_path().join(attribute), value.to_string())
+    }
+}
+
+impl PwmOutput for SysfsPwm {
+    fn set_pulse_width(&mut self, pulse_width: Duration) -> Result<(), OutputError> {
+        Ok(self.write("duty_cycle", pulse_width.as_nanos())?)
+    }
+}
+
+impl Drop for SysfsPwm {
+    fn drop(&mut self) {
+        _ = self.write("enable", 0);
+        _ = fs::write(self.chip_path.join("unexport"), self.channel.to_string());
+    }
+}
+
+/// A GPIO output pin through the Linux `/sys/class/gpio` interface.
+pub(crate) struct SysfsGpio {
+    pin: u32,
+}
+
+impl SysfsGpio {
+    pub(crate) fn open(pin: u32, value: bool) -> Result<Self, OutputError> {
+        let gpio = Self { pin };
+
+        if !gpio.pin_path().exists() {
+            fs::write(
+                PathBuf::from(SYSFS_GPIO_PATH).join("export"),
+                pin.to_string(),
+            )?;
+        }
+
+        // Setting the direction to "high" or "low" makes the pin an output with that value,
+        // without glitching through the other value.
+        let direction = if value { "high" } else { "low" };
+        for _ in 0..EXPORT_ATTEMPTS {
+            if gpio.write("direction", direction).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        gpio.write("direction", direction)?;
+
+        Ok(gpio)
+    }
+
+    fn pin_path(&self) -> PathBuf {
+        PathBuf::from(SYSFS_GPIO_PATH).join(format!("gpio{}", self.pin))
+    }
+
+    fn write(&self, attribute: &str, value: impl ToString) -> std::io::Result<()> {
+        fs::write(self.pin_path().join(attribute), value.to_string())
+    }
+}
+
+impl GpioOutput for SysfsGpio {
+    fn set(&mut self, value: bool) -> Result<(), OutputError> {
+        Ok(self.write("value", value as u8)?)
+    }
+}
+
+impl Drop for SysfsGpio {
+    fn drop(&mut self) {
+        _ = fs::write(
+            PathBuf::from(SYSFS_GPIO_PATH).join("unexport"),
+            self.pin.to_string(),
+        );
+    }
+}
+
+/// A GPIO input pin through the Linux `/sys/class/gpio` interface.
+///
+/// The sysfs interface cannot enable internal resistors, so inputs that need one must have it
+/// enabled some other way, for example through a device tree overlay.
+pub(crate) struct SysfsGpioInput(SysfsGpio);
+
+impl SysfsGpioInput {
+    pub(crate) fn open(pin: u32, pull: InputPull) -> Result<Self, OutputError> {
+        if pull != InputPull::None {
+            log::warn!("The sysfs backend cannot enable the {pull:?} resistor on GPIO pin {pin}.");
+        }
+
+        let gpio = SysfsGpio { pin };
+
+        if !gpio.pin_path().exists() {
+            fs::write(
+                PathBuf::from(SYSFS_GPIO_PATH).join("export"),
+                pin.to_string(),
+            )?;
+        }
+
+        for _ in 0..EXPORT_ATTEMPTS {
+            if gpio.write("direction", "in").is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        gpio.write("direction", "in")?;
+
+        Ok(Self(gpio))
+    }
+}
+
+impl GpioInput for SysfsGpioInput {
+    fn read(&mut self) -> Result<bool, OutputError> {
+        let value = fs::read_to_string(self.0.pin_path().join("value"))?;
+        Ok(value.trim() == "1")
+    }
+}
+
+/// A channel of an ADC through the Linux `/sys/bus/iio` interface, which the kernel drivers of
+/// most ADCs provide, like the MCP3008 and the ADS1115.
+pub(crate) struct SysfsAdcInput {
+    path: PathBuf,
+}
+
+impl SysfsAdcInput {
+    pub(crate) fn open(device: u32, channel: u32) -> Result<Self, OutputError> {
+        let path = PathBuf::from(SYSFS_IIO_PATH)
+            .join(format!("iio:device{device}"))
+            .join(format!("in_voltage{channel}_raw"));
+        let mut input = Self { path };
+        // Fail now if the channel does not exist, rather than on every read.
+        input.read()?;
+        Ok(input)
+    }
+}
+
+impl AdcInput for SysfsAdcInput {
+    fn read(&mut self) -> Result<u32, OutputError> {
+        let value = fs::read_to_string(&self.path)?;
+        Ok(value.trim().parse()?)
+    }
+}
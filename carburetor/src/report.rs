@@ -0,0 +1,122 @@
+//! Reporting acknowledgements, channel status and faults back to Linkage.
+
+use std::io::Write;
+use std::net::Shutdown;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use messaging::{Bytes, CarburetorToLinkage, FaultKind};
+use systemd::Notifier;
+
+use crate::controls::Controls;
+use crate::liveness::Liveness;
+use crate::stream::Stream;
+
+/// The interval at which the status of every channel and digital input is reported.
+pub(crate) const STATUS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// If Linkage does not read what we report within this time, we stop reporting to it, rather than
+/// blocking the threads that control the motors.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Sends [`CarburetorToLinkage`] messages to the Linkage that is currently connected, if any.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Reporter {
+    stream: Arc<Mutex<Option<Stream>>>,
+}
+
+impl Reporter {
+    /// Starts reporting to `stream`.
+    pub(crate) fn connect(&self, stream: Stream) {
+        if let Err(error) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+            log::warn!("Failed to set write timeout on report stream: {error}");
+        }
+
+        *self.stream.lock().unwrap() = Some(stream);
+    }
+
+    /// Stops reporting.
+    pub(crate) fn disconnect(&self) {
+        *self.stream.lock().unwrap() = None;
+    }
+
+    /// Closes the connection to Linkage, if any, which also ends reading instructions from it.
+    pub(crate) fn close(&self) {
+        if let Some(stream) = self.stream.lock().unwrap().take() {
+            if let Err(error) = stream.shutdown(Shutdown::Both) {
+                log::warn!("Failed to close the connection to Linkage: {error}");
+            }
+        }
+    }
+
+    /// Sends `message` to Linkage, if connected.
+    pub(crate) fn report(&self, message: CarburetorToLinkage) {
+        let mut stream = self.stream.lock().unwrap();
+        let Some(writer) = stream.as_mut() else {
+            return;
+        };
+
+        let bytes: Bytes = message.into();
+        if let Err(error) = writer.write_all(&bytes) {
+            log::warn!("Failed to report {message:?} to Linkage, no longer reporting: {error}");
+            *stream = None;
+        }
+    }
+
+    /// Reports a fault with `channel`.
+    pub(crate) fn fault(&self, channel: u8, kind: FaultKind) {
+        self.report(CarburetorToLinkage::Fault { channel, kind });
+    }
+}
+
+/// The state of a channel, as reported in [`CarburetorToLinkage::ChannelStatus`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChannelState {
+    /// The pulse width that is currently being output.
+    pub(crate) pulse_width: Duration,
+    /// When the output of the channel last changed.
+    pub(crate) last_update: Instant,
+    /// Whether the channel is running its arming sequence.
+    pub(crate) arming: bool,
+}
+
+pub(crate) type ChannelStateHandle = Arc<Mutex<ChannelState>>;
+
+/// Periodically reports the status of each of `channels`, and of the emergency stop in `controls`,
+/// through `reporter`.
+///
+/// It also feeds the systemd watchdog through `notifier`, for as long as `liveness` shows that the
+/// loop serving Linkage is still running, so systemd restarts Carburetor if either gets stuck.
+pub(crate) fn start_status_reporter(
+    reporter: Reporter,
+    channels: Vec<(u8, ChannelStateHandle)>,
+    controls: Arc<Controls>,
+    notifier: Arc<Notifier>,
+    liveness: Arc<Liveness>,
+) {
+    thread::spawn(move || loop {
+        reporter.report(CarburetorToLinkage::EStopStatus {
+            latched: controls.estop.is_latched(),
+        });
+
+        for (channel, state) in &channels {
+            let state = *state.lock().unwrap();
+            reporter.report(CarburetorToLinkage::ChannelStatus {
+                channel: *channel,
+                arming: state.arming,
+                pulse_width_us: state.pulse_width.as_micros().min(u16::MAX as u128) as u16,
+                since_update_ms: state
+                    .last_update
+                    .elapsed()
+                    .as_millis()
+                    .min(u16::MAX as u128) as u16,
+            });
+        }
+
+        if liveness.is_alive() {
+            notifier.feed_watchdog();
+        }
+        thread::sleep(STATUS_INTERVAL);
+    });
+}
@@ -0,0 +1,119 @@
+//! Shutting down in an orderly way when the process is interrupted or terminated.
+
+use std::error::Error;
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use simple_signal::{self, Signal};
+
+use crate::controls::Controls;
+
+/// How long the control threads get to confirm that their output reached neutral.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The control thread of a channel, which returns whether its output was left at neutral.
+pub(crate) type ChannelThread = (u8, JoinHandle<bool>);
+
+/// Calls `wake` when the process is interrupted or terminated, and returns a flag that is set from
+/// then on. When that happens a second time, the process exits right away.
+pub(crate) fn handle_signals(wake: impl Fn() + Send + 'static) -> Arc<AtomicBool> {
+    let requested = Arc::new(AtomicBool::new(false));
+
+    simple_signal::set_handler(&[Signal::Int, Signal::Term], {
+        let requested = Arc::clone(&requested);
+        move |signals| {
+            if requested.swap(true, Ordering::SeqCst) {
+                log::warn!("Caught {signals:?} again. Exiting without waiting for neutral!");
+                exit(1);
+            }
+
+            log::info!("Caught: {signals:?}");
+            log::info!("Shutting down...");
+            wake();
+        }
+    });
+
+    requested
+}
+
+/// Puts every channel in neutral and every digital output in its safe state, and waits for the
+/// control `threads` to confirm that their output reached neutral.
+///
+/// Returns an error if any channel did not confirm that in time.
+pub(crate) fn stop_channels(
+    controls: &Controls,
+    threads: Vec<ChannelThread>,
+) -> Result<(), Box<dyn Error>> {
+    controls.shutdown();
+
+    let failed = join_channels(threads, SHUTDOWN_TIMEOUT);
+    if !failed.is_empty() {
+        return Err(
+            format!("channels {failed:?} did not confirm that they reached neutral").into(),
+        );
+    }
+
+    log::info!("Every channel reached neutral.");
+    Ok(())
+}
+
+/// Waits at most `timeout` for all `threads` to finish, and returns the channels that did not reach
+/// neutral.
+fn join_channels(threads: Vec<ChannelThread>, timeout: Duration) -> Vec<u8> {
+    let deadline = Instant::now() + timeout;
+    let mut failed = Vec::new();
+
+    for (channel, handle) in threads {
+        while !handle.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        if !handle.is_finished() {
+            log::error!("Channel {channel} did not stop within {timeout:?}.");
+            failed.push(channel);
+            continue;
+        }
+
+        match handle.join() {
+            Ok(true) => {}
+            Ok(false) => {
+                log::error!("Channel {channel} failed to reach neutral.");
+                failed.push(channel);
+            }
+            Err(_) => {
+                log::error!("Control thread for channel {channel} panicked.");
+                failed.push(channel);
+            }
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::join_channels;
+
+    #[test]
+    fn reports_channels_that_did_not_reach_neutral() {
+        let threads = vec![
+            (0, thread::spawn(|| true)),
+            (1, thread::spawn(|| false)),
+            (
+                2,
+                thread::spawn(|| {
+                    thread::sleep(Duration::from_secs(5));
+                    true
+                }),
+            ),
+        ];
+
+        assert_eq!(join_channels(threads, Duration::from_millis(50)), [1, 2]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+use crate::instruction::Speed;
+
+/// The interval at which a [`SlewLimiter`] moves a channel towards its target speed.
+pub(crate) const SLEW_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Limits how fast the speed of a channel may change, by stepping it towards its target speed at
+/// a fixed interval, no matter how often new target speeds arrive.
+#[derive(Debug)]
+pub(crate) struct SlewLimiter {
+    /// The maximum change in speed per second.
+    max_acceleration: f32,
+    /// When the last step was taken, or [`None`] if the channel has reached its target.
+    last_step: Option<Instant>,
+}
+
+impl SlewLimiter {
+    pub(crate) fn new(max_acceleration: f32) -> Self {
+        Self {
+            max_acceleration,
+            last_step: None,
+        }
+    }
+
+    /// Returns the time until the next step should be taken.
+    pub(crate) fn until_next_step(&self) -> Duration {
+        match self.last_step {
+            Some(last_step) => SLEW_INTERVAL.saturating_sub(last_step.elapsed()),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Forgets about the last step, for when the channel has been moved to its target by other
+    /// means.
+    pub(crate) fn reset(&mut self) {
+        self.last_step = None;
+    }
+
+    /// Returns `current` moved towards `target` by as much as is allowed since the last step.
+    pub(crate) fn step(&mut self, current: Speed, target: Speed) -> Speed {
+        let now = Instant::now();
+        let elapsed = match self.last_step {
+            Some(last_step) => now - last_step,
+            None => SLEW_INTERVAL,
+        };
+
+        let max_delta = self.max_acceleration * elapsed.as_secs_f32();
+        let delta = target.value() - current.value();
+        if delta.abs() <= max_delta {
+            self.last_step = None;
+            return target;
+        }
+
+        self.last_step = Some(now);
+        Speed::new(current.value() + delta.signum() * max_delta).unwrap_or(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlewLimiter;
+    use crate::instruction::Speed;
+
+    #[test]
+    fn limits_change_per_step() {
+        // 10ms at 10.0/s is a step of 0.1.
+        let mut limiter = SlewLimiter::new(10.0);
+        let speed = limiter.step(Speed::neutral(), Speed::forward());
+        assert!((speed.value() - 0.1).abs() < 0.01);
+        assert!(!limiter.until_next_step().is_zero());
+    }
+
+    #[test]
+    fn reaches_target() {
+        let mut limiter = SlewLimiter::new(1000.0);
+        let speed = limiter.step(Speed::backward(), Speed::forward());
+        assert_eq!(speed, Speed::forward());
+        assert!(limiter.until_next_step().is_zero());
+    }
+}
@@ -0,0 +1,221 @@
+//! The connection with Linkage, over TCP or over a Unix socket when both run on the same machine.
+
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket,
+};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Who may connect to a Unix socket Carburetor binds itself: its own user and group, like
+/// `SocketMode` in `carburetor.socket`.
+const SOCKET_MODE: u32 = 0o660;
+
+/// Where Carburetor listens for Linkage.
+#[derive(Debug, Clone)]
+pub(crate) enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl ListenAddress {
+    /// Starts listening on this address.
+    pub(crate) fn bind(&self) -> io::Result<Listener> {
+        match self {
+            Self::Tcp(address) => TcpListener::bind(address).map(Listener::Tcp),
+            Self::Unix(path) => {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        "another process is listening on it",
+                    ));
+                }
+                // The socket is left behind if Carburetor did not shut down in an orderly way.
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                let listener = UnixListener::bind(path)?;
+                // Anyone who can connect can drive the motors.
+                fs::set_permissions(path, fs::Permissions::from_mode(SOCKET_MODE))?;
+                Ok(Listener::Unix(listener, Some(path.clone())))
+            }
+        }
+    }
+
+    /// Binds a UDP socket to this address, for receiving instructions as datagrams.
+    pub(crate) fn bind_datagrams(&self) -> io::Result<UdpSocket> {
+        match self {
+            Self::Tcp(address) => UdpSocket::bind(address),
+            Self::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "datagrams need a TCP address, not a Unix socket",
+            )),
+        }
+    }
+
+    /// Connects to this address from this machine, to wake up a thread that is waiting for a
+    /// connection on it.
+    pub(crate) fn wake(&self) {
+        match self {
+            Self::Tcp(address) => {
+                _ = TcpStream::connect_timeout(
+                    &local_address(*address),
+                    Duration::from_millis(100),
+                );
+            }
+            Self::Unix(path) => _ = UnixStream::connect(path),
+        }
+    }
+}
+
+impl Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{address}"),
+            Self::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Returns an address to connect to `address` on this machine.
+fn local_address(address: SocketAddr) -> SocketAddr {
+    match address.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => (Ipv4Addr::LOCALHOST, address.port()).into(),
+        IpAddr::V6(ip) if ip.is_unspecified() => (Ipv6Addr::LOCALHOST, address.port()).into(),
+        _ => address,
+    }
+}
+
+/// Listens for Linkage. A Unix socket that was bound by Carburetor itself is removed again when
+/// the listener is dropped.
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener, Option<PathBuf>),
+}
+
+impl Listener {
+    /// Returns the address this listener is listening on.
+    pub(crate) fn address(&self) -> io::Result<ListenAddress> {
+        match self {
+            Self::Tcp(listener) => listener.local_addr().map(ListenAddress::Tcp),
+            Self::Unix(listener, _) => match listener.local_addr()?.as_pathname() {
+                Some(path) => Ok(ListenAddress::Unix(path.to_path_buf())),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the Unix socket has no path",
+                )),
+            },
+        }
+    }
+
+    /// Waits for Linkage to connect.
+    pub(crate) fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Self::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+            Self::Unix(listener, _) => listener.accept().map(|(stream, _)| Stream::Unix(stream)),
+        }
+    }
+
+    /// Returns an iterator over the connections from Linkage, like [`TcpListener::incoming`].
+    pub(crate) fn incoming(&self) -> impl Iterator<Item = io::Result<Stream>> + '_ {
+        std::iter::repeat_with(|| self.accept())
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Self::Unix(_, Some(path)) = self {
+            if let Err(error) = fs::remove_file(&path) {
+                log::warn!("Failed to remove socket {path:?}: {error}");
+            }
+        }
+    }
+}
+
+impl From<systemd::Listener> for Listener {
+    /// Takes over a listener passed by systemd, which stays in charge of its socket.
+    fn from(listener: systemd::Listener) -> Self {
+        match listener {
+            systemd::Listener::Tcp(listener) => Self::Tcp(listener),
+            systemd::Listener::Unix(listener) => Self::Unix(listener, None),
+        }
+    }
+}
+
+/// A connection with Linkage.
+#[derive(Debug)]
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Stream {
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Self::Tcp(stream) => stream.try_clone().map(Self::Tcp),
+            Self::Unix(stream) => stream.try_clone().map(Self::Unix),
+        }
+    }
+
+    /// Returns the address of Linkage, if it is connected over TCP.
+    pub(crate) fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(stream) => stream.peer_addr().ok(),
+            Self::Unix(_) => None,
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout),
+            Self::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_write_timeout(timeout),
+            Self::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    pub(crate) fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.shutdown(how),
+            Self::Unix(stream) => stream.shutdown(how),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
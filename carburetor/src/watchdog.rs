@@ -0,0 +1,157 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use config::{WatchdogConfig, WatchdogPolicy};
+
+use crate::instruction::Speed;
+
+/// The interval at which a tripped watchdog steps a channel towards neutral when using
+/// [`WatchdogPolicy::RampDown`].
+const RAMP_DOWN_STEP: Duration = Duration::from_millis(20);
+
+/// Keeps track of when a channel last received an instruction, and decides what to do with the
+/// channel when instructions stop arriving.
+pub(crate) struct Watchdog<C: Display> {
+    channel: C,
+    timeout: Duration,
+    policy: WatchdogPolicy,
+    ramp_down: Duration,
+    last_fed: Instant,
+    tripped: bool,
+}
+
+impl<C: Display> Watchdog<C> {
+    pub(crate) fn new(channel: C, config: &WatchdogConfig) -> Self {
+        Self {
+            channel,
+            timeout: Duration::from_millis(config.timeout_ms()),
+            policy: config.policy(),
+            ramp_down: Duration::from_millis(config.ramp_down_ms()),
+            last_fed: Instant::now(),
+            tripped: false,
+        }
+    }
+
+    /// Tells the watchdog the channel has just received an instruction.
+    pub(crate) fn feed(&mut self) {
+        if self.tripped {
+            log::info!("{}: instructions resumed, watchdog reset.", self.channel);
+        }
+
+        self.last_fed = Instant::now();
+        self.tripped = false;
+    }
+
+    /// Returns how long the channel may wait for its next instruction before
+    /// [`Watchdog::check`] has to be called, or [`None`] if it may wait indefinitely.
+    ///
+    /// A channel in neutral has nothing to guard, so it may always wait indefinitely.
+    pub(crate) fn poll_timeout(&self, speed: Speed) -> Option<Duration> {
+        if speed == Speed::neutral() {
+            return None;
+        }
+
+        match (self.tripped, self.policy) {
+            (false, _) => Some(self.timeout.saturating_sub(self.last_fed.elapsed())),
+            (true, WatchdogPolicy::RampDown) => Some(RAMP_DOWN_STEP),
+            (true, _) => None,
+        }
+    }
+
+    /// Checks whether the watchdog has tripped, and if so, returns the speed the channel should
+    /// be set to according to the configured [`WatchdogPolicy`].
+    ///
+    /// Returns [`None`] if the speed of the channel should not change.
+    pub(crate) fn check(&mut self, speed: Speed) -> Option<Speed> {
+        if !self.tripped {
+            let elapsed = self.last_fed.elapsed();
+            if elapsed < self.timeout {
+                return None;
+            }
+
+            self.tripped = true;
+            log::warn!(
+                "{}: watchdog tripped, no instruction received for {elapsed:?} (at {speed}). Applying {:?} policy.",
+                self.channel,
+                self.policy
+            );
+        }
+
+        match self.policy {
+            WatchdogPolicy::Neutral => Some(Speed::neutral()),
+            WatchdogPolicy::Hold => None,
+            WatchdogPolicy::RampDown => Some(self.ramp_down_step(speed)),
+        }
+    }
+
+    /// Returns `speed` moved one [`RAMP_DOWN_STEP`] closer to neutral.
+    fn ramp_down_step(&self, speed: Speed) -> Speed {
+        if self.ramp_down.is_zero() {
+            return Speed::neutral();
+        }
+
+        let step = RAMP_DOWN_STEP.as_secs_f32() / self.ramp_down.as_secs_f32();
+        let value = speed.value();
+        if value.abs() <= step + f32::EPSILON {
+            return Speed::neutral();
+        }
+
+        Speed::new(value - value.signum() * step).unwrap_or_else(Speed::neutral)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use config::{WatchdogConfig, WatchdogPolicy};
+
+    use super::Watchdog;
+    use crate::instruction::Speed;
+
+    fn watchdog(policy: WatchdogPolicy) -> Watchdog<&'static str> {
+        Watchdog::new("test", &WatchdogConfig::new(0, policy, 100))
+    }
+
+    #[test]
+    fn neutral_channel_waits_indefinitely() {
+        let watchdog = watchdog(WatchdogPolicy::Neutral);
+        assert_eq!(watchdog.poll_timeout(Speed::neutral()), None);
+        assert_eq!(
+            watchdog.poll_timeout(Speed::forward()),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn neutral_policy_stops_channel() {
+        let mut watchdog = watchdog(WatchdogPolicy::Neutral);
+        assert_eq!(watchdog.check(Speed::forward()), Some(Speed::neutral()));
+    }
+
+    #[test]
+    fn hold_policy_keeps_speed() {
+        let mut watchdog = watchdog(WatchdogPolicy::Hold);
+        assert_eq!(watchdog.check(Speed::forward()), None);
+        assert_eq!(watchdog.poll_timeout(Speed::forward()), None);
+    }
+
+    #[test]
+    fn ramp_down_policy_steps_towards_neutral() {
+        let mut watchdog = watchdog(WatchdogPolicy::RampDown);
+        let mut speed = Speed::backward();
+        for _ in 0..5 {
+            assert!(watchdog.poll_timeout(speed).is_some());
+            speed = watchdog.check(speed).unwrap();
+        }
+        assert_eq!(speed, Speed::neutral());
+    }
+
+    #[test]
+    fn feeding_resets_trip() {
+        let mut watchdog = watchdog(WatchdogPolicy::Hold);
+        watchdog.check(Speed::forward());
+        watchdog.feed();
+        assert!(watchdog.poll_timeout(Speed::forward()).is_some());
+    }
+}
@@ -52,8 +52,7 @@ pub fn start_gamepad_event_listener<R: Runtime>(
         let mut rx = state.gamepad_event_bus.lock().unwrap().add_rx();
 
         move || loop {
-            if let Ok(Some(message)) = rx.recv() {
-                let CockpitToLinkage::GamepadInputEvent { .. } = message;
+            if let Ok(Some(message @ CockpitToLinkage::GamepadInputEvent { .. })) = rx.recv() {
                 app.emit_all(EVENT_GAMEPAD_EVENT, message).unwrap();
             }
         }
@@ -74,7 +73,7 @@ pub fn start_gamepad_event_listener<R: Runtime>(
                             gamepad_id: gamepad_id_into_u8(event.id),
                             event_type: EventType::ButtonChanged as u8,
                             control: button as u8,
-                            value: (value.clamp(0.0, 1.0) * 255.0) as u8,
+                            value: value.clamp(0.0, 1.0),
                         };
 
                         bus.lock().unwrap().broadcast(Some(message));
@@ -84,7 +83,7 @@ pub fn start_gamepad_event_listener<R: Runtime>(
                             gamepad_id: gamepad_id_into_u8(event.id),
                             event_type: EventType::AxisChanged as u8,
                             control: axis as u8,
-                            value: (127.0 + (value.clamp(-1.0, 1.0)) * 255.0) as u8,
+                            value: value.clamp(-1.0, 1.0),
                         };
 
                         bus.lock().unwrap().broadcast(Some(message));
@@ -94,7 +93,7 @@ pub fn start_gamepad_event_listener<R: Runtime>(
                             gamepad_id: gamepad_id_into_u8(event.id),
                             event_type: EventType::Connected as u8,
                             control: 0,
-                            value: 0,
+                            value: 0.0,
                         };
 
                         bus.lock().unwrap().broadcast(Some(message));
@@ -104,7 +103,7 @@ pub fn start_gamepad_event_listener<R: Runtime>(
                             gamepad_id: gamepad_id_into_u8(event.id),
                             event_type: EventType::Disconnected as u8,
                             control: 0,
-                            value: 0,
+                            value: 0.0,
                         };
 
                         bus.lock().unwrap().broadcast(Some(message));
@@ -53,6 +53,13 @@ pub fn start_gauge_connection<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Re
     Ok(())
 }
 
+/// Returns the protocol version this Cockpit speaks, to compare with the versions of the programs
+/// on the robot that Gauge reports.
+#[tauri::command]
+pub fn get_protocol_version() -> u8 {
+    messaging::PROTOCOL_VERSION
+}
+
 fn on_receive_system_info<R: tauri::Runtime>(
     app: &tauri::AppHandle<R>,
     system_info: SystemInfo,
@@ -1,22 +1,33 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::{
-    io::{ErrorKind, Read, Write},
+    io::{ErrorKind, Read},
     net::TcpStream,
-    sync::Arc,
+    sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use bus::BusReader;
 use tauri::{Manager, Runtime};
 
 use config::LinkageConfig;
-use messaging::{CockpitToLinkage, Message};
+use messaging::{
+    CockpitToLinkage, Component, DecodeErrorCounts, Encoding, FrameReader, FrameWriter,
+    FramedMessage, Handshake, LinkMonitor, LinkStats, LinkageToCockpit, MessageError,
+    COCKPIT_TO_LINKAGE_ENCODING,
+};
 
 use crate::commands::gamepad::GamepadState;
 
 const EVENT_LINKAGE_LIB_STATE_CHANGE: &str = "linkage_lib_state_change";
 
+/// How long Linkage gets to answer our handshake. Versions from before handshakes never do.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// When nothing else was sent for this long, a heartbeat is sent. Linkage disables the robot when it
+/// hears nothing for 500 ms.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(serde::Serialize, Clone, Copy)]
 enum LinkageLibStateChange {
     Enabled,
@@ -25,12 +36,18 @@ enum LinkageLibStateChange {
 
 pub struct LinkageLibState {
     disabled: Arc<AtomicBool>,
+    /// How many handshakes from Linkage could not be decoded or were refused, for every reason.
+    decode_errors: Arc<Mutex<DecodeErrorCounts>>,
+    /// Stamps the frames sent to Linkage, and measures the echoes of their stamps.
+    link: Arc<Mutex<LinkMonitor>>,
 }
 
 impl LinkageLibState {
     pub fn new() -> Self {
         Self {
             disabled: Arc::new(AtomicBool::new(false)),
+            decode_errors: Arc::default(),
+            link: Arc::default(),
         }
     }
 }
@@ -48,6 +65,8 @@ pub fn enable<R: Runtime>(
 
     thread::spawn({
         let disabled = linkage_lib_state.disabled.clone();
+        let decode_errors = linkage_lib_state.decode_errors.clone();
+        let link = linkage_lib_state.link.clone();
         let gamepad_event_bus_rx = gamepad_state.gamepad_event_bus.lock().unwrap().add_rx();
 
         move || {
@@ -70,7 +89,13 @@ pub fn enable<R: Runtime>(
                 thread::spawn({
                     let disabled = disabled.clone();
                     move || {
-                        start_linkage_lib_communication(config, gamepad_event_bus_rx, disabled);
+                        start_linkage_lib_communication(
+                            config,
+                            gamepad_event_bus_rx,
+                            disabled,
+                            decode_errors,
+                            link,
+                        );
                     }
                 });
 
@@ -96,36 +121,83 @@ fn start_linkage_lib_communication(
     config: LinkageConfig,
     mut gamepad_event_bus_rx: BusReader<Option<CockpitToLinkage>>,
     disabled: Arc<AtomicBool>,
+    decode_errors: Arc<Mutex<DecodeErrorCounts>>,
+    link: Arc<Mutex<LinkMonitor>>,
 ) {
     let linkage_lib_address = config.cockpit().linkage_lib_address();
     log::debug!("Starting Linkage-lib communication on '{linkage_lib_address}'.");
 
     match TcpStream::connect(linkage_lib_address.to_string()) {
         Ok(mut linkage_communication_stream) => {
-            // We are connected to the Linkage communication socket.
+            // We are connected to the Linkage communication socket, but only talk to a Linkage that
+            // speaks our protocol version.
+            let handshake = Handshake::new(Component::Cockpit, env!("CARGO_PKG_VERSION"));
+            _ = linkage_communication_stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+            match handshake.exchange(&mut linkage_communication_stream, Component::LinkageLib) {
+                Ok(linkage) => log::info!("Connected to {linkage}."),
+                Err(err) => {
+                    match err.get_ref().and_then(|e| e.downcast_ref::<MessageError>()) {
+                        Some(error) => {
+                            let count = decode_errors.lock().unwrap().record(error);
+                            log::error!(
+                                "Refusing Linkage on '{linkage_lib_address}': {error} ({count} like it so far). Disabling..."
+                            );
+                        }
+                        None => log::error!(
+                            "Refusing Linkage on '{linkage_lib_address}': {err}. Disabling..."
+                        ),
+                    }
+                    disabled.store(true, Ordering::Relaxed);
+                }
+            }
+
+            // Every connection numbers its frames from 0.
+            *link.lock().unwrap() = LinkMonitor::new();
+            match linkage_communication_stream.try_clone() {
+                Ok(stream) => {
+                    thread::spawn({
+                        let disabled = disabled.clone();
+                        let link = link.clone();
+                        move || receive_echoes(stream, disabled, link)
+                    });
+                }
+                Err(err) => log::warn!(
+                    "Cannot measure the round trip to Linkage, as its stream cannot be cloned: {err}"
+                ),
+            }
+            let mut writer =
+                FrameWriter::new(&linkage_communication_stream, COCKPIT_TO_LINKAGE_ENCODING);
+
+            let mut last_sent = Instant::now();
             loop {
                 if disabled.load(Ordering::Relaxed) {
                     log::debug!("Received disable message. Breaking out of loop.");
                     break;
                 }
 
-                if let Ok(Some(message)) =
-                    gamepad_event_bus_rx.recv_timeout(Duration::from_millis(50))
-                {
-                    if let Err(err) = linkage_communication_stream.write(&message.to_bytes()) {
-                        match err.kind() {
-                            ErrorKind::BrokenPipe => {
-                                // If the pipe is broken, we can't use this connection anymore
-                                // so let's just break out of the loop, so we can disconnect this stream and close this thread.
-                                log::debug!("Linkage communication stream pipe is broken. Breaking out of loop.");
-                                break;
-                            }
-                            _ => log::error!(
-                                "Failed to write to Linkage communication stream on '{}': {}",
-                                linkage_lib_address.to_string(),
-                                err
-                            ),
+                let message = match gamepad_event_bus_rx.recv_timeout(Duration::from_millis(50)) {
+                    Ok(Some(message)) => message,
+                    _ if last_sent.elapsed() >= HEARTBEAT_INTERVAL => CockpitToLinkage::Heartbeat,
+                    _ => continue,
+                };
+                last_sent = Instant::now();
+
+                let frame = message.to_frame().with_stamp(link.lock().unwrap().stamp());
+                if let Err(err) = writer.write_frame(&frame) {
+                    match err.kind() {
+                        ErrorKind::BrokenPipe => {
+                            // If the pipe is broken, we can't use this connection anymore
+                            // so let's just break out of the loop, so we can disconnect this stream and close this thread.
+                            log::debug!(
+                                "Linkage communication stream pipe is broken. Breaking out of loop."
+                            );
+                            break;
                         }
+                        _ => log::error!(
+                            "Failed to write to Linkage communication stream on '{}': {}",
+                            linkage_lib_address.to_string(),
+                            err
+                        ),
                     }
                 }
             }
@@ -140,6 +212,28 @@ fn start_linkage_lib_communication(
     }
 }
 
+/// Measures the round trip to Linkage from the echoes of our stamps on `stream`, until disabled.
+fn receive_echoes(stream: TcpStream, disabled: Arc<AtomicBool>, link: Arc<Mutex<LinkMonitor>>) {
+    let mut reader = FrameReader::new(stream, Encoding::Fixed);
+    loop {
+        match reader.read_message::<LinkageToCockpit>() {
+            Ok(LinkageToCockpit::Echo { sent_at_us }) => link.lock().unwrap().echoed(sent_at_us),
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                log::warn!("Failed to decode message from Linkage: {err}")
+            }
+            Err(err) => {
+                log::debug!("Stopped receiving echoes from Linkage: {err}");
+                break;
+            }
+        }
+
+        if disabled.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+}
+
 fn block_until_disable(socket: &mut TcpStream, disabled: Arc<AtomicBool>) {
     socket
         .set_read_timeout(Some(Duration::from_millis(100)))
@@ -172,3 +266,10 @@ pub fn disable(state: tauri::State<'_, LinkageLibState>) {
 
     state.disabled.store(true, Ordering::Relaxed);
 }
+
+/// Returns what was measured of the link to Linkage: how many frames were sent, and their round
+/// trip.
+#[tauri::command]
+pub fn get_linkage_link_stats(state: tauri::State<'_, LinkageLibState>) -> LinkStats {
+    state.link.lock().unwrap().stats()
+}
@@ -8,8 +8,8 @@ use crate::commands::gamepad::GamepadState;
 use commands::config::get_config;
 use commands::config::set_cockpit_config;
 use commands::gamepad::start_gamepad_event_listener;
-use commands::gauge::start_gauge_connection;
-use commands::linkage_lib::{disable, enable, LinkageLibState};
+use commands::gauge::{get_protocol_version, start_gauge_connection};
+use commands::linkage_lib::{disable, enable, get_linkage_link_stats, LinkageLibState};
 use std::sync::{Arc, Mutex};
 
 fn main() {
@@ -20,7 +20,8 @@ fn main() {
             .unwrap()
             .cockpit()
             .cockpit_backend_logger_address()
-            .port,
+            .port()
+            .expect("the Cockpit backend logger address should be a TCP address"),
     )
     .start();
 
@@ -34,7 +35,9 @@ fn main() {
             set_cockpit_config,
             get_config,
             start_gamepad_event_listener,
-            start_gauge_connection
+            start_gauge_connection,
+            get_protocol_version,
+            get_linkage_link_stats
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,5 +1,5 @@
 import { writable } from 'svelte/store';
-import type { SystemInfo } from '$lib/types/system-info';
+import type { LinkStats, SystemInfo } from '$lib/types/system-info';
 import { listen } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/tauri';
 import {
@@ -12,6 +12,9 @@ import { loggerState } from '$lib/state/loggers';
 import { gamepadState } from '$lib/state/gamepad';
 
 export const systemInfo = writable<SystemInfo | undefined>(undefined);
+export const protocolVersion = writable<number | undefined>(undefined);
+/** What Cockpit measured of the link to Linkage. */
+export const linkageLinkStats = writable<LinkStats | undefined>(undefined);
 
 export async function enableRobotCode() {
 	robotCodeState.update($robotCode => {
@@ -30,9 +33,21 @@ export async function disableRobotCode() {
 }
 
 export function initializeListeners() {
+	invoke('get_protocol_version').then(version =>
+		protocolVersion.set(version as number)
+	);
 	initializeSystemInfoListener();
 	initializeGamepadEventListener();
 	initializeLinkageLibStateListener();
+	initializeLinkageLinkStatsPoller();
+}
+
+function initializeLinkageLinkStatsPoller() {
+	setInterval(() => {
+		invoke('get_linkage_link_stats').then(stats =>
+			linkageLinkStats.set(stats as LinkStats)
+		);
+	}, 1000);
 }
 
 function initializeLinkageLibStateListener() {
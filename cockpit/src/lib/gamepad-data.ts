@@ -66,14 +66,14 @@ export function parseGamepadInputEvent(
 
 export class GamepadData {
 	public axis: Record<AxisControl, number> = {
-		[AxisControl.LEFT_STICK_X]: 127.0,
-		[AxisControl.LEFT_STICK_Y]: 127.0,
-		[AxisControl.LEFT_Z]: 127.0,
-		[AxisControl.RIGHT_STICK_X]: 127.0,
-		[AxisControl.RIGHT_STICK_Y]: 127.0,
-		[AxisControl.RIGHT_Z]: 127.0,
-		[AxisControl.DPAD_X]: 127.0,
-		[AxisControl.DPAD_Y]: 127.0
+		[AxisControl.LEFT_STICK_X]: 0.0,
+		[AxisControl.LEFT_STICK_Y]: 0.0,
+		[AxisControl.LEFT_Z]: 0.0,
+		[AxisControl.RIGHT_STICK_X]: 0.0,
+		[AxisControl.RIGHT_STICK_Y]: 0.0,
+		[AxisControl.RIGHT_Z]: 0.0,
+		[AxisControl.DPAD_X]: 0.0,
+		[AxisControl.DPAD_Y]: 0.0
 	};
 	public buttons: Record<ButtonControl, number> = {
 		[ButtonControl.SOUTH]: 0.0,
@@ -4,6 +4,18 @@ export interface SystemInfo {
 	service_info: SystemServiceInfo;
 	uptime?: number;
 	robot_code_exists: boolean;
+	carburetor_metrics?: CarburetorMetrics;
+	components?: Handshake[];
+}
+
+export interface Handshake {
+	protocol_version: number;
+	component: 'Cockpit' | 'LinkageLib' | 'Carburetor' | 'Gauge';
+	version: {
+		major: number;
+		minor: number;
+		patch: number;
+	};
 }
 
 export interface SystemCpuInfo {
@@ -28,4 +40,51 @@ export interface SystemServiceInfo {
 	carburetor_status: boolean;
 	gauge_status: boolean;
 	linkage_socket_status: boolean;
+	carburetor_estop_latched?: boolean;
+}
+
+export interface CarburetorMetrics {
+	uptime_ms: number;
+	connections: number;
+	connected: boolean;
+	messages_per_second: number;
+	malformed_frames: number;
+	decode_errors: {
+		unknown_opcode: number;
+		reserved_bytes: number;
+		out_of_range: number;
+		invalid_float: number;
+		unsupported_version: number;
+		invalid_length: number;
+		corrupt_frame: number;
+	};
+	rejected_instructions: number;
+	apply_latency: {
+		bounds_us: number[];
+		counts: number[];
+	};
+	channels: CarburetorChannelMetrics[];
+	handshake?: Handshake;
+	linkage_handshake?: Handshake;
+	linkage_link?: LinkStats;
+}
+
+export interface LinkStats {
+	sent: number;
+	received: number;
+	dropped: number;
+	reordered: number;
+	round_trip_us?: number;
+	one_way_us?: number;
+	jitter_us: number;
+}
+
+export interface CarburetorChannelMetrics {
+	channel: number;
+	messages_per_second: number;
+	messages: number;
+	history: {
+		at_ms: number;
+		pulse_width_us: number;
+	}[];
 }
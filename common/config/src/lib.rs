@@ -1,6 +1,6 @@
 use std::error::Error;
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use xdg::BaseDirectoriesError;
@@ -8,15 +8,48 @@ use xdg::BaseDirectoriesError;
 pub type AddressHost = String;
 pub type AddressPort = usize;
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Address {
-    pub host: AddressHost,
-    pub port: AddressPort,
+/// The Unix socket Carburetor listens on by default, which keeps it off the network. Its directory
+/// is only writable by root, so no other user can put a socket of their own in its place.
+pub const DEFAULT_CARBURETOR_SOCKET_PATH: &str = "/run/linkage/carburetor.sock";
+
+/// Where a component can be reached.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Address {
+    /// A host and TCP port, like `{ host = "raspberrypi.local", port = 48862 }`.
+    Inet {
+        host: AddressHost,
+        port: AddressPort,
+    },
+    /// A Unix socket, like `{ path = "/run/linkage/carburetor.sock" }`, for components that run on
+    /// the same machine.
+    Unix { path: PathBuf },
+}
+
+impl Address {
+    /// Creates an [`Address::Inet`].
+    pub fn inet(host: impl Into<AddressHost>, port: AddressPort) -> Self {
+        Self::Inet {
+            host: host.into(),
+            port,
+        }
+    }
+
+    /// Returns the TCP port, if this is not a Unix socket.
+    pub fn port(&self) -> Option<AddressPort> {
+        match self {
+            Self::Inet { port, .. } => Some(*port),
+            Self::Unix { .. } => None,
+        }
+    }
 }
 
 impl Display for Address {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}:{}", self.host, self.port)
+        match self {
+            Self::Inet { host, port } => write!(f, "{host}:{port}"),
+            Self::Unix { path } => write!(f, "{}", path.display()),
+        }
     }
 }
 
@@ -42,7 +75,7 @@ impl LinkageConfig {
     }
 
     pub fn set_cockpit(&mut self, cockpit_config: CockpitConfig) {
-        self.cockpit = Box::new(cockpit_config);
+        *self.cockpit = cockpit_config;
     }
 
     pub fn gauge(&self) -> &GaugeConfig {
@@ -55,41 +88,35 @@ impl Default for LinkageConfig {
         Self {
             linkage_lib: Box::new(LinkageLibConfig {
                 port: 12362,
-                carburetor_address: Address {
-                    host: "0.0.0.0".to_string(),
-                    port: 48862,
+                carburetor_address: Address::Unix {
+                    path: PathBuf::from(DEFAULT_CARBURETOR_SOCKET_PATH),
                 },
                 logger_port: 7640,
+                carburetor_transport: Transport::default(),
             }),
             carburetor: Box::new(CarburetorConfig {
                 port: 48862,
                 logger_port: 7644,
+                metrics_port: default_metrics_port(),
+                watchdog: WatchdogConfig::default(),
+                backend: OutputBackend::default(),
+                sysfs_pwm_chip: 0,
+                channels: default_channels(),
+                on_malformed_frame: MalformedFramePolicy::default(),
+                digital_outputs: Vec::new(),
+                digital_inputs: Vec::new(),
+                analog_inputs: Vec::new(),
+                estop_survives_restart: true,
+                transport: Transport::default(),
+                socket_path: Some(PathBuf::from(DEFAULT_CARBURETOR_SOCKET_PATH)),
             }),
             cockpit: Box::new(CockpitConfig {
-                linkage_lib_address: Address {
-                    host: "raspberrypi.local".to_string(),
-                    port: 12362,
-                },
-                linkage_socket_address: Address {
-                    host: "raspberrypi.local".to_string(),
-                    port: 9999,
-                },
-                gauge_address: Address {
-                    host: "raspberrypi.local".to_string(),
-                    port: 4226,
-                },
-                cockpit_backend_logger_address: Address {
-                    host: "0.0.0.0".to_string(),
-                    port: 7642,
-                },
-                linkage_lib_logger_address: Address {
-                    host: "raspberrypi.local".to_string(),
-                    port: 7640,
-                },
-                carburetor_logger_address: Address {
-                    host: "raspberrypi.local".to_string(),
-                    port: 7644,
-                },
+                linkage_lib_address: Address::inet("raspberrypi.local", 12362),
+                linkage_socket_address: Address::inet("raspberrypi.local", 9999),
+                gauge_address: Address::inet("raspberrypi.local", 4226),
+                cockpit_backend_logger_address: Address::inet("0.0.0.0", 7642),
+                linkage_lib_logger_address: Address::inet("raspberrypi.local", 7640),
+                carburetor_logger_address: Address::inet("raspberrypi.local", 7644),
             }),
             gauge: Box::new(GaugeConfig { port: 4226 }),
         }
@@ -101,6 +128,8 @@ pub struct LinkageLibConfig {
     port: AddressPort,
     carburetor_address: Address,
     logger_port: AddressPort,
+    #[serde(default)]
+    carburetor_transport: Transport,
 }
 
 impl LinkageLibConfig {
@@ -115,12 +144,41 @@ impl LinkageLibConfig {
     pub fn logger_port(&self) -> &AddressPort {
         &self.logger_port
     }
+
+    /// How instructions are sent to Carburetor, which must match [`CarburetorConfig::transport`].
+    pub fn carburetor_transport(&self) -> Transport {
+        self.carburetor_transport
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CarburetorConfig {
     port: AddressPort,
     logger_port: AddressPort,
+    #[serde(default = "default_metrics_port")]
+    metrics_port: AddressPort,
+    #[serde(default)]
+    watchdog: WatchdogConfig,
+    #[serde(default)]
+    backend: OutputBackend,
+    #[serde(default)]
+    sysfs_pwm_chip: u32,
+    #[serde(default = "default_channels")]
+    channels: Vec<ChannelConfig>,
+    #[serde(default)]
+    on_malformed_frame: MalformedFramePolicy,
+    #[serde(default)]
+    digital_outputs: Vec<DigitalOutputConfig>,
+    #[serde(default)]
+    digital_inputs: Vec<DigitalInputConfig>,
+    #[serde(default)]
+    analog_inputs: Vec<AnalogInputConfig>,
+    #[serde(default = "default_estop_survives_restart")]
+    estop_survives_restart: bool,
+    #[serde(default)]
+    transport: Transport,
+    #[serde(default)]
+    socket_path: Option<PathBuf>,
 }
 
 impl CarburetorConfig {
@@ -131,6 +189,621 @@ impl CarburetorConfig {
     pub fn logger_port(&self) -> AddressPort {
         self.logger_port
     }
+
+    /// The port of the metrics endpoint, which only listens on localhost.
+    pub fn metrics_port(&self) -> AddressPort {
+        self.metrics_port
+    }
+
+    pub fn watchdog(&self) -> &WatchdogConfig {
+        &self.watchdog
+    }
+
+    pub fn backend(&self) -> OutputBackend {
+        self.backend
+    }
+
+    pub fn set_backend(&mut self, backend: OutputBackend) {
+        self.backend = backend;
+    }
+
+    /// The `/sys/class/pwm/pwmchipN` chip used by [`OutputBackend::Sysfs`].
+    pub fn sysfs_pwm_chip(&self) -> u32 {
+        self.sysfs_pwm_chip
+    }
+
+    pub fn channels(&self) -> &[ChannelConfig] {
+        &self.channels
+    }
+
+    /// What to do with a Linkage connection that sends a frame that cannot be decoded.
+    pub fn on_malformed_frame(&self) -> MalformedFramePolicy {
+        self.on_malformed_frame
+    }
+
+    pub fn digital_outputs(&self) -> &[DigitalOutputConfig] {
+        &self.digital_outputs
+    }
+
+    pub fn digital_inputs(&self) -> &[DigitalInputConfig] {
+        &self.digital_inputs
+    }
+
+    pub fn analog_inputs(&self) -> &[AnalogInputConfig] {
+        &self.analog_inputs
+    }
+
+    /// Whether a latched emergency stop is still latched after Carburetor restarts. If not,
+    /// restarting Carburetor releases it.
+    pub fn estop_survives_restart(&self) -> bool {
+        self.estop_survives_restart
+    }
+
+    /// How Linkage sends instructions, which must match [`LinkageLibConfig::carburetor_transport`].
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    /// The Unix socket to listen on for Linkage instead of [`CarburetorConfig::port`], if any.
+    pub fn socket_path(&self) -> Option<&Path> {
+        self.socket_path.as_deref()
+    }
+}
+
+/// Maps a channel number used by robot code to a hardware PWM channel, and describes the pulses
+/// the device connected to it expects.
+///
+/// All fields except `channel` and `pwm_channel` default to the values of a Spark motor
+/// controller: 1000µs to 2000µs pulses at 50Hz.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelConfig {
+    channel: u8,
+    pwm_channel: u8,
+    #[serde(default = "ChannelConfig::default_frequency_hz")]
+    frequency_hz: f32,
+    #[serde(default = "ChannelConfig::default_min_pulse_us")]
+    min_pulse_us: u32,
+    #[serde(default = "ChannelConfig::default_neutral_pulse_us")]
+    neutral_pulse_us: u32,
+    #[serde(default = "ChannelConfig::default_max_pulse_us")]
+    max_pulse_us: u32,
+    #[serde(default)]
+    deadband: f32,
+    #[serde(default)]
+    inverted: bool,
+    #[serde(default = "ChannelConfig::default_max_output")]
+    max_output: f32,
+    #[serde(default)]
+    max_acceleration: Option<f32>,
+    #[serde(default)]
+    arming: ArmingConfig,
+    #[serde(default)]
+    servo: Option<ServoConfig>,
+}
+
+impl ChannelConfig {
+    /// Creates a [`ChannelConfig`] for a Spark motor controller on `pwm_channel`.
+    pub fn new(channel: u8, pwm_channel: u8) -> Self {
+        Self {
+            channel,
+            pwm_channel,
+            frequency_hz: Self::default_frequency_hz(),
+            min_pulse_us: Self::default_min_pulse_us(),
+            neutral_pulse_us: Self::default_neutral_pulse_us(),
+            max_pulse_us: Self::default_max_pulse_us(),
+            deadband: 0.0,
+            inverted: false,
+            max_output: Self::default_max_output(),
+            max_acceleration: None,
+            arming: ArmingConfig::default(),
+            servo: None,
+        }
+    }
+
+    /// The channel number robot code uses to address this channel.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// The hardware PWM channel this channel is output on.
+    pub fn pwm_channel(&self) -> u8 {
+        self.pwm_channel
+    }
+
+    pub fn frequency_hz(&self) -> f32 {
+        self.frequency_hz
+    }
+
+    /// The pulse width at full reverse.
+    pub fn min_pulse_us(&self) -> u32 {
+        self.min_pulse_us
+    }
+
+    /// The pulse width at neutral.
+    pub fn neutral_pulse_us(&self) -> u32 {
+        self.neutral_pulse_us
+    }
+
+    /// The pulse width at full forward.
+    pub fn max_pulse_us(&self) -> u32 {
+        self.max_pulse_us
+    }
+
+    /// Speeds within `-deadband..=deadband` are output as neutral, and the speeds outside of it are
+    /// scaled to start at neutral.
+    pub fn deadband(&self) -> f32 {
+        self.deadband
+    }
+
+    /// Whether forward and reverse are swapped.
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// The largest speed, in either direction, that is ever output.
+    pub fn max_output(&self) -> f32 {
+        self.max_output
+    }
+
+    /// The maximum change in speed per second, if limited. For example, `4.0` means it takes at
+    /// least 250ms to go from neutral to full speed, and 500ms from full reverse to full forward.
+    pub fn max_acceleration(&self) -> Option<f32> {
+        self.max_acceleration
+    }
+
+    pub fn arming(&self) -> &ArmingConfig {
+        &self.arming
+    }
+
+    /// Sets the arming sequence of this channel.
+    pub fn with_arming(mut self, arming: ArmingConfig) -> Self {
+        self.arming = arming;
+        self
+    }
+
+    /// The angle range of this channel, if it drives a servo.
+    pub fn servo(&self) -> Option<&ServoConfig> {
+        self.servo.as_ref()
+    }
+
+    /// Makes this channel drive a servo.
+    pub fn with_servo(mut self, servo: ServoConfig) -> Self {
+        self.servo = Some(servo);
+        self
+    }
+
+    fn default_frequency_hz() -> f32 {
+        50.0
+    }
+
+    fn default_min_pulse_us() -> u32 {
+        1000
+    }
+
+    fn default_neutral_pulse_us() -> u32 {
+        1500
+    }
+
+    fn default_max_pulse_us() -> u32 {
+        2000
+    }
+
+    fn default_max_output() -> f32 {
+        1.0
+    }
+}
+
+/// What Carburetor does with instructions for a channel that is still arming.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArmingInstructions {
+    /// Drop them.
+    #[default]
+    Ignore,
+    /// Remember the last one, and apply it once the channel is armed.
+    Queue,
+}
+
+/// The sequence a channel goes through before it accepts instructions, for ESCs that need to see
+/// neutral for a while before they accept throttle.
+///
+/// By default, there is no arming sequence.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArmingConfig {
+    #[serde(default)]
+    hold_ms: u64,
+    #[serde(default)]
+    sweep: bool,
+    #[serde(default = "ArmingConfig::default_sweep_ms")]
+    sweep_ms: u64,
+    #[serde(default)]
+    instructions: ArmingInstructions,
+}
+
+impl ArmingConfig {
+    pub fn new(hold_ms: u64, sweep: bool, sweep_ms: u64, instructions: ArmingInstructions) -> Self {
+        Self {
+            hold_ms,
+            sweep,
+            sweep_ms,
+            instructions,
+        }
+    }
+
+    /// The time the neutral pulse is held before the channel is armed.
+    pub fn hold_ms(&self) -> u64 {
+        self.hold_ms
+    }
+
+    /// Whether to output the maximum and then the minimum pulse on startup, before holding neutral,
+    /// to calibrate the ESC.
+    pub fn sweep(&self) -> bool {
+        self.sweep
+    }
+
+    /// The time each end of the calibration sweep is held.
+    pub fn sweep_ms(&self) -> u64 {
+        self.sweep_ms
+    }
+
+    pub fn instructions(&self) -> ArmingInstructions {
+        self.instructions
+    }
+
+    /// Whether there is an arming sequence at all.
+    pub fn is_enabled(&self) -> bool {
+        self.hold_ms > 0 || self.sweep
+    }
+
+    fn default_sweep_ms() -> u64 {
+        2000
+    }
+}
+
+impl Default for ArmingConfig {
+    fn default() -> Self {
+        Self::new(
+            0,
+            false,
+            Self::default_sweep_ms(),
+            ArmingInstructions::Ignore,
+        )
+    }
+}
+
+/// Makes a channel drive a servo, which can be moved to an angle.
+///
+/// The pulse width at `min_angle_deg` is the `min_pulse_us` of the channel, and it grows by
+/// `us_per_degree` for every degree. By default, the angle range is spread over
+/// `min_pulse_us..=max_pulse_us`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServoConfig {
+    #[serde(default)]
+    min_angle_deg: f32,
+    #[serde(default = "ServoConfig::default_max_angle_deg")]
+    max_angle_deg: f32,
+    #[serde(default)]
+    us_per_degree: Option<f32>,
+}
+
+impl ServoConfig {
+    pub fn new(min_angle_deg: f32, max_angle_deg: f32, us_per_degree: Option<f32>) -> Self {
+        Self {
+            min_angle_deg,
+            max_angle_deg,
+            us_per_degree,
+        }
+    }
+
+    /// The smallest angle the servo can be moved to.
+    pub fn min_angle_deg(&self) -> f32 {
+        self.min_angle_deg
+    }
+
+    /// The largest angle the servo can be moved to.
+    pub fn max_angle_deg(&self) -> f32 {
+        self.max_angle_deg
+    }
+
+    /// The change in pulse width per degree, if it is not derived from the angle range.
+    pub fn us_per_degree(&self) -> Option<f32> {
+        self.us_per_degree
+    }
+
+    fn default_max_angle_deg() -> f32 {
+        180.0
+    }
+}
+
+impl Default for ServoConfig {
+    fn default() -> Self {
+        Self::new(0.0, Self::default_max_angle_deg(), None)
+    }
+}
+
+/// Maps a digital output pin number used by robot code to a GPIO pin, for driving solenoids,
+/// relays and LEDs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DigitalOutputConfig {
+    pin: u8,
+    gpio_pin: u32,
+    #[serde(default)]
+    safe_state: bool,
+}
+
+impl DigitalOutputConfig {
+    pub fn new(pin: u8, gpio_pin: u32, safe_state: bool) -> Self {
+        Self {
+            pin,
+            gpio_pin,
+            safe_state,
+        }
+    }
+
+    /// The pin number used by robot code.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// The GPIO pin, as numbered by the output backend: BCM numbering for
+    /// [`OutputBackend::Rppal`], and the kernel GPIO number for [`OutputBackend::Sysfs`].
+    pub fn gpio_pin(&self) -> u32 {
+        self.gpio_pin
+    }
+
+    /// The value the output is set to on startup, when Linkage disconnects and on shutdown.
+    pub fn safe_state(&self) -> bool {
+        self.safe_state
+    }
+}
+
+/// The internal resistor enabled on a digital input.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InputPull {
+    /// No internal resistor, for inputs that are always driven or have an external resistor.
+    #[default]
+    None,
+    /// Pull the input high, for switches that connect it to ground.
+    Up,
+    /// Pull the input low, for switches that connect it to 3.3V.
+    Down,
+}
+
+/// Maps a digital input pin number used by robot code to a GPIO pin, for reading limit switches
+/// and beam breaks.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DigitalInputConfig {
+    pin: u8,
+    gpio_pin: u32,
+    #[serde(default)]
+    pull: InputPull,
+    #[serde(default = "DigitalInputConfig::default_debounce_ms")]
+    debounce_ms: u64,
+}
+
+impl DigitalInputConfig {
+    pub fn new(pin: u8, gpio_pin: u32, pull: InputPull, debounce_ms: u64) -> Self {
+        Self {
+            pin,
+            gpio_pin,
+            pull,
+            debounce_ms,
+        }
+    }
+
+    /// The pin number used by robot code.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// The GPIO pin, numbered like [`DigitalOutputConfig::gpio_pin`].
+    pub fn gpio_pin(&self) -> u32 {
+        self.gpio_pin
+    }
+
+    pub fn pull(&self) -> InputPull {
+        self.pull
+    }
+
+    /// The time the input has to keep a new value before the change is reported.
+    pub fn debounce_ms(&self) -> u64 {
+        self.debounce_ms
+    }
+
+    fn default_debounce_ms() -> u64 {
+        20
+    }
+}
+
+/// Maps an analog input pin number used by robot code to a channel of an ADC, for reading
+/// potentiometers and other analog sensors.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AnalogInputConfig {
+    pin: u8,
+    #[serde(default)]
+    iio_device: u32,
+    iio_channel: u32,
+    #[serde(default = "AnalogInputConfig::default_max_raw")]
+    max_raw: u32,
+    #[serde(default = "AnalogInputConfig::default_threshold")]
+    threshold: f32,
+}
+
+impl AnalogInputConfig {
+    pub fn new(pin: u8, iio_device: u32, iio_channel: u32, max_raw: u32, threshold: f32) -> Self {
+        Self {
+            pin,
+            iio_device,
+            iio_channel,
+            max_raw,
+            threshold,
+        }
+    }
+
+    /// The pin number used by robot code.
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    /// The ADC, as the Linux IIO device `/sys/bus/iio/devices/iio:deviceN`.
+    pub fn iio_device(&self) -> u32 {
+        self.iio_device
+    }
+
+    /// The channel of the ADC the input is read from, as `in_voltageN_raw`.
+    pub fn iio_channel(&self) -> u32 {
+        self.iio_channel
+    }
+
+    /// The raw reading at the full scale of the ADC, like 4095 for a 12-bit ADC.
+    pub fn max_raw(&self) -> u32 {
+        self.max_raw
+    }
+
+    /// How much the value, as a fraction of the full scale, has to change before it is reported.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    fn default_max_raw() -> u32 {
+        4095
+    }
+
+    fn default_threshold() -> f32 {
+        0.01
+    }
+}
+
+fn default_channels() -> Vec<ChannelConfig> {
+    vec![ChannelConfig::new(0, 0), ChannelConfig::new(1, 1)]
+}
+
+fn default_metrics_port() -> AddressPort {
+    48863
+}
+
+fn default_estop_survives_restart() -> bool {
+    true
+}
+
+/// The way Carburetor drives its outputs.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputBackend {
+    /// Raspberry Pi hardware PWM through the rppal crate.
+    Rppal,
+    /// Linux `/sys/class/pwm` interface, for other single-board computers.
+    Sysfs,
+    /// Simulated outputs that only record what is written to them.
+    Sim,
+}
+
+impl Default for OutputBackend {
+    /// Defaults to [`OutputBackend::Rppal`] when built for a Raspberry Pi, and to
+    /// [`OutputBackend::Sim`] otherwise.
+    fn default() -> Self {
+        if cfg!(all(
+            target_arch = "arm",
+            target_os = "linux",
+            target_env = "gnu"
+        )) {
+            Self::Rppal
+        } else {
+            Self::Sim
+        }
+    }
+}
+
+/// How Linkage sends instructions to Carburetor.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    /// Over the TCP connection. Instructions arrive in order, so a lost segment holds up every
+    /// instruction after it.
+    #[default]
+    Tcp,
+    /// Motor speeds as UDP datagrams to the same port, with sequence numbers so stale and
+    /// reordered speeds are dropped. The TCP connection is still used for everything else, like
+    /// the emergency stop, so it cannot be lost.
+    Udp,
+}
+
+/// What Carburetor does when it receives a frame that cannot be decoded.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MalformedFramePolicy {
+    /// Drop the frame and skip over the incoming bytes one at a time until they form a valid
+    /// message again.
+    #[default]
+    Resync,
+    /// Drop the connection, which puts all channels into neutral.
+    Disconnect,
+}
+
+/// What Carburetor does with a channel when its watchdog trips.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchdogPolicy {
+    /// Immediately put the channel into neutral.
+    #[default]
+    Neutral,
+    /// Keep the channel at its last received speed.
+    Hold,
+    /// Linearly bring the channel back to neutral over `ramp_down_ms`.
+    RampDown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchdogConfig {
+    #[serde(default = "WatchdogConfig::default_timeout_ms")]
+    timeout_ms: u64,
+    #[serde(default)]
+    policy: WatchdogPolicy,
+    #[serde(default = "WatchdogConfig::default_ramp_down_ms")]
+    ramp_down_ms: u64,
+}
+
+impl WatchdogConfig {
+    pub fn new(timeout_ms: u64, policy: WatchdogPolicy, ramp_down_ms: u64) -> Self {
+        Self {
+            timeout_ms,
+            policy,
+            ramp_down_ms,
+        }
+    }
+
+    /// The time a channel may go without receiving an instruction before the watchdog trips.
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms
+    }
+
+    pub fn policy(&self) -> WatchdogPolicy {
+        self.policy
+    }
+
+    /// The time it takes to get from full speed to neutral when using [`WatchdogPolicy::RampDown`].
+    pub fn ramp_down_ms(&self) -> u64 {
+        self.ramp_down_ms
+    }
+
+    fn default_timeout_ms() -> u64 {
+        500
+    }
+
+    fn default_ramp_down_ms() -> u64 {
+        250
+    }
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self::new(
+            Self::default_timeout_ms(),
+            WatchdogPolicy::default(),
+            Self::default_ramp_down_ms(),
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -186,6 +859,12 @@ fn config_path() -> Result<PathBuf, BaseDirectoriesError> {
     Ok(dir.get_config_file("config.toml"))
 }
 
+/// The file that exists while the emergency stop of Carburetor is latched.
+pub fn carburetor_estop_path() -> Result<PathBuf, BaseDirectoriesError> {
+    let dir = xdg::BaseDirectories::with_prefix("linkage")?;
+    Ok(dir.get_state_file("carburetor-estop"))
+}
+
 pub fn config() -> Result<LinkageConfig, Box<dyn Error>> {
     let config_path = config_path()?;
     if !config_path.exists() {
@@ -195,7 +874,13 @@ pub fn config() -> Result<LinkageConfig, Box<dyn Error>> {
         return Ok(LinkageConfig::default());
     }
 
-    let file_content = std::fs::read_to_string(config_path)?;
+    config_from_file(&config_path)
+}
+
+/// Reads the config from the file at `path`, instead of the default location.
+pub fn config_from_file(path: &Path) -> Result<LinkageConfig, Box<dyn Error>> {
+    let file_content = std::fs::read_to_string(path)
+        .map_err(|error| format!("failed to read config file {path:?}: {error}"))?;
 
     let config = toml::from_str(file_content.as_str())?;
 
@@ -1,66 +1,293 @@
+//! `#[derive(Message)]` for the enums and structs in `messaging`, generating their conversions from
+//! and to `Bytes`.
+//!
+//! Every variant of an enum needs an opcode, which is byte 0 of its message, and so does a struct,
+//! which is a single message. Every field needs the offset of its first byte. Fields are read and written through their `Field`
+//! implementation, and multi-byte fields are big-endian unless `endian = "little"` is given. Every
+//! byte that is not covered by the opcode or a field is reserved: it is written as 0, and a message
+//! in which it is not 0 is refused with `MessageError::ReservedBytes`. A field that cannot be read
+//! refuses the message with the `MessageError` for its `FieldError`, and an opcode without a
+//! variant with `MessageError::UnknownOpcode`.
+//!
+//! ```ignore
+//! #[derive(Message)]
+//! pub enum LinkageToCarburetor {
+//!     #[message(opcode = 0x40)]
+//!     MotorInstruction {
+//!         #[message(offset = 1)]
+//!         channel: u8,
+//!         // Bytes 2 and 3 are reserved.
+//!         #[message(offset = 4, endian = "big", map = "round_speed")]
+//!         speed: f32,
+//!     },
+//!     #[message(opcode = 0x42)]
+//!     EStop,
+//! }
+//! ```
+//!
+//! ```ignore
+//! #[derive(Message)]
+//! #[message(opcode = 0x01)]
+//! pub struct Handshake {
+//!     #[message(offset = 1)]
+//!     pub protocol_version: u8,
+//!     // ...
+//! }
+//! ```
+//!
+//! `map` names a function that is applied to the value of a field after it has been read.
+//!
+//! The generated code refers to `crate::`, so the derive is only meant for `messaging` itself.
+
 use proc_macro::TokenStream;
-use quote::{__private::TokenStream as QuoteTokenStream, quote};
-use syn::{self, Data, Ident, Variant};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, Ident, Lit, Meta, MetaNameValue, NestedMeta, Path,
+    Type,
+};
 
 #[proc_macro_derive(Message, attributes(message))]
 pub fn derive_message(input: TokenStream) -> TokenStream {
-    let ast: syn::DeriveInput = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as DeriveInput);
     impl_message_macro(&ast)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
 }
 
-fn bytes_messages(ast: &syn::DeriveInput) -> (Vec<&QuoteTokenStream>, Vec<&Ident>) {
-    let variants: Vec<&Variant> = match &ast.data {
-        Data::Enum(ref data_enum) => data_enum.variants.iter().collect(),
-        _ => unimplemented!("#[derive(Message)] expects enum"),
-    };
+/// A variant of the enum or the struct, with the fields it carries.
+struct MessageVariant {
+    /// The path of the variant, or the name of the struct.
+    path: TokenStream2,
+    opcode: u8,
+    fields: Vec<MessageField>,
+}
 
-    variants.iter().map(|variant| {
-        let message : &Ident= &variant.ident;
-        let bytes = variant
-            .attrs
-            .iter()
-            .find_map(|attr| match attr.path.is_ident("message") {
-                true => Some(&attr.tokens),
-                false => None,
-            })
-            .expect("#[derive(Message)] expects attribute macro #[message(...)] on each variant, found none");
-        (bytes, message)
-    }).unzip()
+/// A field of a variant, and where it goes in the message.
+struct MessageField {
+    ident: Ident,
+    ty: Type,
+    offset: usize,
+    endian: TokenStream2,
+    map: Option<Path>,
+}
+
+/// Returns the `name = value` options in the `#[message(...)]` attributes among `attrs`.
+fn options(attrs: &[Attribute]) -> syn::Result<Vec<MetaNameValue>> {
+    let mut options = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("message")) {
+        let Meta::List(list) = attr.parse_meta()? else {
+            return Err(Error::new_spanned(attr, "expected #[message(...)]"));
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(option)) => options.push(option),
+                nested => return Err(Error::new_spanned(nested, "expected `name = value`")),
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<MessageField> {
+    let mut offset = None;
+    let mut endian = quote!(crate::Endian::Big);
+    let mut map = None;
+    for option in options(&field.attrs)? {
+        match (option.path.get_ident().map(Ident::to_string).as_deref(), &option.lit) {
+            (Some("offset"), Lit::Int(int)) => offset = Some(int.base10_parse()?),
+            (Some("endian"), Lit::Str(string)) => {
+                endian = match string.value().as_str() {
+                    "big" => quote!(crate::Endian::Big),
+                    "little" => quote!(crate::Endian::Little),
+                    _ => return Err(Error::new_spanned(string, "expected \"big\" or \"little\"")),
+                }
+            }
+            (Some("map"), Lit::Str(string)) => map = Some(string.parse()?),
+            _ => {
+                return Err(Error::new_spanned(
+                    option,
+                    "expected `offset = <byte>`, `endian = \"big\" | \"little\"` or `map = \"<function>\"`",
+                ))
+            }
+        }
+    }
+
+    Ok(MessageField {
+        ident: field.ident.clone().expect("named fields have an ident"),
+        ty: field.ty.clone(),
+        offset: offset.ok_or_else(|| {
+            Error::new_spanned(field, "expected #[message(offset = ...)] on every field")
+        })?,
+        endian,
+        map,
+    })
+}
+
+/// Returns the opcode in the `#[message(...)]` attributes among `attrs` of `item`.
+fn parse_opcode(attrs: &[Attribute], item: impl quote::ToTokens) -> syn::Result<u8> {
+    let mut opcode = None;
+    for option in options(attrs)? {
+        match (option.path.is_ident("opcode"), &option.lit) {
+            (true, Lit::Int(int)) => opcode = Some(int.base10_parse()?),
+            _ => return Err(Error::new_spanned(option, "expected `opcode = <byte>`")),
+        }
+    }
+    opcode.ok_or_else(|| Error::new_spanned(item, "expected #[message(opcode = ...)]"))
+}
+
+fn parse_fields(fields: &Fields) -> syn::Result<Vec<MessageField>> {
+    match fields {
+        Fields::Named(fields) => fields.named.iter().map(parse_field).collect(),
+        Fields::Unit => Ok(Vec::new()),
+        Fields::Unnamed(fields) => Err(Error::new_spanned(
+            fields,
+            "#[derive(Message)] expects named fields",
+        )),
+    }
+}
+
+fn parse_variant(name: &Ident, variant: &syn::Variant) -> syn::Result<MessageVariant> {
+    let ident = &variant.ident;
+    Ok(MessageVariant {
+        path: quote!(#name::#ident),
+        opcode: parse_opcode(&variant.attrs, variant)?,
+        fields: parse_fields(&variant.fields)?,
+    })
 }
 
-fn impl_message_macro(ast: &syn::DeriveInput) -> TokenStream {
+/// Returns the arm of the `match` on the opcode that decodes `variant`.
+fn decode_arm(variant: &MessageVariant) -> TokenStream2 {
+    let MessageVariant {
+        path,
+        opcode,
+        fields,
+    } = variant;
+
+    let layout = fields
+        .iter()
+        .map(|MessageField { ty, offset, .. }| quote!((#offset, <#ty as crate::Field>::LEN)));
+    let reads = fields.iter().map(
+        |MessageField {
+             ident,
+             ty,
+             offset,
+             endian,
+             map,
+         }| {
+            let map = map.as_ref().map(|map| quote!(.map(#map)));
+            let name = ident.to_string();
+            quote! {
+                #ident: <#ty as crate::Field>::read(
+                    &value[#offset..#offset + <#ty as crate::Field>::LEN],
+                    #endian,
+                )
+                #map
+                .map_err(|error| error.in_field(value, #name))?
+            }
+        },
+    );
+
+    quote! {
+        #opcode => {
+            const RESERVED: [bool; 8] = crate::field::reserved_bytes(&[#(#layout),*]);
+            if value.iter().zip(RESERVED).any(|(byte, reserved)| reserved && *byte != 0) {
+                return Err(crate::MessageError::ReservedBytes {
+                    opcode: #opcode,
+                    bytes: value,
+                });
+            }
+            Ok(#path { #(#reads,)* })
+        }
+    }
+}
+
+/// Returns the arm of the `match` on the message that encodes `variant`.
+fn encode_arm(variant: &MessageVariant) -> TokenStream2 {
+    let MessageVariant {
+        path,
+        opcode,
+        fields,
+    } = variant;
+
+    let idents = fields.iter().map(|field| &field.ident);
+    let writes = fields.iter().map(
+        |MessageField {
+             ident,
+             ty,
+             offset,
+             endian,
+             ..
+         }| {
+            quote! {
+                crate::Field::write(
+                    &#ident,
+                    &mut bytes[#offset..#offset + <#ty as crate::Field>::LEN],
+                    #endian,
+                );
+            }
+        },
+    );
+
+    quote! {
+        #path { #(#idents),* } => {
+            let mut bytes = crate::Bytes::default();
+            bytes[0] = #opcode;
+            #(#writes)*
+            bytes
+        }
+    }
+}
+
+fn impl_message_macro(ast: &DeriveInput) -> syn::Result<TokenStream2> {
     let name = &ast.ident;
 
-    let (bytes, messages) = bytes_messages(ast);
+    let variants = match &ast.data {
+        Data::Enum(data_enum) => data_enum
+            .variants
+            .iter()
+            .map(|variant| parse_variant(name, variant))
+            .collect::<syn::Result<Vec<_>>>()?,
+        Data::Struct(data_struct) => vec![MessageVariant {
+            path: quote!(#name),
+            opcode: parse_opcode(&ast.attrs, ast)?,
+            fields: parse_fields(&data_struct.fields)?,
+        }],
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                ast,
+                "#[derive(Message)] expects an enum or a struct",
+            ))
+        }
+    };
+
+    let decode_arms = variants.iter().map(decode_arm);
+    let encode_arms = variants.iter().map(encode_arm);
 
-    let expanded = quote! {
-        impl Message for #name {
-            fn to_bytes(&self) -> Bytes {
-                Bytes::from(*self)
+    Ok(quote! {
+        impl crate::Message for #name {
+            fn to_bytes(&self) -> crate::Bytes {
+                crate::Bytes::from(*self)
             }
         }
 
-        impl TryFrom<Bytes> for #name {
-            type Error = MessageError;
+        impl TryFrom<crate::Bytes> for #name {
+            type Error = crate::MessageError;
 
-            fn try_from(value: Bytes) -> Result<Self, Self::Error> {
-                #[allow(unused_parens)]
-                match value {
-                    #( #bytes => Ok(Self::#messages), )*
-                    bytes => Err(MessageError::UnknownMessage(bytes)),
+            fn try_from(value: crate::Bytes) -> Result<Self, Self::Error> {
+                match value[0] {
+                    #(#decode_arms)*
+                    opcode => Err(crate::MessageError::UnknownOpcode(opcode)),
                 }
             }
         }
 
-        impl From<#name> for Bytes {
+        impl From<#name> for crate::Bytes {
             fn from(value: #name) -> Self {
-                #[allow(unused_parens)]
                 match value {
-                    #( #name::#messages => #bytes, )*
+                    #(#encode_arms)*
                 }
             }
         }
-    };
-
-    TokenStream::from(expanded)
+    })
 }
@@ -1,14 +1,111 @@
-use super::Bytes;
+use std::fmt::{self, Display};
 
+use serde::{Deserialize, Serialize};
+
+use super::{Bytes, Handshake, PROTOCOL_VERSION};
+
+/// Why bytes could not be decoded into a message.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MessageError {
-    UnknownMessage(Bytes),
+    /// No message has this opcode, which is byte 0 of a message or the tag of a frame.
+    UnknownOpcode(u8),
+    /// Bytes of the message with `opcode` that are reserved are not 0.
+    ReservedBytes { opcode: u8, bytes: Bytes },
+    /// `field` of the message with `opcode` holds a value it cannot have.
+    OutOfRange { opcode: u8, field: &'static str },
+    /// `field` of the message with `opcode` is NaN or infinite.
+    InvalidFloat { opcode: u8, field: &'static str },
+    /// A peer introduced itself with a handshake for another protocol version.
+    UnsupportedVersion(Handshake),
+    /// The payload of a frame with `tag` is `length` bytes long, which does not fit.
+    InvalidPayloadLength { tag: u8, length: usize },
+    /// The checksum of a frame with `tag` does not match its payload.
+    ChecksumMismatch { tag: u8 },
+    /// A frame has flags set that are not known.
+    UnknownFlags(u8),
+    /// A datagram of this length does not hold exactly one message.
+    InvalidDatagramLength(usize),
+    /// A datagram holds a message without the stamp that orders it.
+    UnstampedDatagram,
 }
 
 impl std::error::Error for MessageError {}
 
-impl std::fmt::Display for MessageError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Debug::fmt(&self, f)
+impl Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode(opcode) => write!(f, "unknown opcode {opcode:#04x}"),
+            Self::ReservedBytes { opcode, bytes } => write!(
+                f,
+                "reserved bytes of message {opcode:#04x} are not zero in {bytes:?}"
+            ),
+            Self::OutOfRange { opcode, field } => {
+                write!(f, "{field} of message {opcode:#04x} is out of range")
+            }
+            Self::InvalidFloat { opcode, field } => {
+                write!(f, "{field} of message {opcode:#04x} is NaN or infinite")
+            }
+            Self::UnsupportedVersion(peer) => write!(
+                f,
+                "{peer} is incompatible with protocol version {PROTOCOL_VERSION}. Update both to the same release of linkage"
+            ),
+            Self::InvalidPayloadLength { tag, length } => write!(
+                f,
+                "a payload of {length} bytes does not fit frame {tag:#04x}"
+            ),
+            Self::ChecksumMismatch { tag } => write!(f, "checksum of frame {tag:#04x} does not match"),
+            Self::UnknownFlags(flags) => write!(f, "unknown frame flags {flags:#010b}"),
+            Self::InvalidDatagramLength(length) => {
+                write!(f, "a datagram of {length} bytes does not hold exactly one message")
+            }
+            Self::UnstampedDatagram => write!(f, "a datagram holds a message without a stamp"),
+        }
+    }
+}
+
+/// How many messages could not be decoded, for every kind of [`MessageError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodeErrorCounts {
+    pub unknown_opcode: u64,
+    pub reserved_bytes: u64,
+    pub out_of_range: u64,
+    pub invalid_float: u64,
+    pub unsupported_version: u64,
+    /// Frames and datagrams with a length that does not fit their message.
+    pub invalid_length: u64,
+    /// Frames with unknown flags or a checksum that does not match, and datagrams without a stamp.
+    pub corrupt_frame: u64,
+}
+
+impl DecodeErrorCounts {
+    /// Counts `error`, and returns how many errors of its kind there have been so far.
+    pub fn record(&mut self, error: &MessageError) -> u64 {
+        let count = match error {
+            MessageError::UnknownOpcode(_) => &mut self.unknown_opcode,
+            MessageError::ReservedBytes { .. } => &mut self.reserved_bytes,
+            MessageError::OutOfRange { .. } => &mut self.out_of_range,
+            MessageError::InvalidFloat { .. } => &mut self.invalid_float,
+            MessageError::UnsupportedVersion(_) => &mut self.unsupported_version,
+            MessageError::InvalidPayloadLength { .. } | MessageError::InvalidDatagramLength(_) => {
+                &mut self.invalid_length
+            }
+            MessageError::ChecksumMismatch { .. }
+            | MessageError::UnknownFlags(_)
+            | MessageError::UnstampedDatagram => &mut self.corrupt_frame,
+        };
+        *count += 1;
+        *count
+    }
+
+    /// Returns the number of errors of all kinds.
+    pub fn total(&self) -> u64 {
+        self.unknown_opcode
+            + self.reserved_bytes
+            + self.out_of_range
+            + self.invalid_float
+            + self.unsupported_version
+            + self.invalid_length
+            + self.corrupt_frame
     }
 }
@@ -0,0 +1,266 @@
+//! The values that the messages of a `#[derive(Message)]` enum or struct carry in their [`Bytes`].
+//!
+//! [`Bytes`]: crate::Bytes
+
+use crate::{Bytes, Component, FaultKind, MessageError, ServoTarget, Version};
+
+/// The byte order of a field that spans more than one byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    #[default]
+    Big,
+    Little,
+}
+
+/// Why a [`Field`] could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldError {
+    /// The bytes do not hold any value of the field.
+    OutOfRange,
+    /// The bytes hold a float that is NaN or infinite.
+    InvalidFloat,
+    /// Bytes within the field that are reserved are not 0.
+    ReservedBytes,
+}
+
+impl FieldError {
+    /// Returns the error for `field` of the message in `bytes`.
+    pub(crate) fn in_field(self, bytes: Bytes, field: &'static str) -> MessageError {
+        let opcode = bytes[0];
+        match self {
+            Self::OutOfRange => MessageError::OutOfRange { opcode, field },
+            Self::InvalidFloat => MessageError::InvalidFloat { opcode, field },
+            Self::ReservedBytes => MessageError::ReservedBytes { opcode, bytes },
+        }
+    }
+}
+
+/// A value that takes up a fixed number of bytes of a message.
+pub trait Field: Sized {
+    /// The number of bytes the value takes up.
+    const LEN: usize;
+
+    /// Writes the value to `bytes`, which are [`Field::LEN`] long.
+    fn write(&self, bytes: &mut [u8], endian: Endian);
+
+    /// Reads a value from `bytes`, which are [`Field::LEN`] long.
+    fn read(bytes: &[u8], endian: Endian) -> Result<Self, FieldError>;
+}
+
+macro_rules! impl_field_for_number {
+    ($($number:ty),*) => {
+        $(
+            impl Field for $number {
+                const LEN: usize = std::mem::size_of::<$number>();
+
+                fn write(&self, bytes: &mut [u8], endian: Endian) {
+                    let value = match endian {
+                        Endian::Big => self.to_be_bytes(),
+                        Endian::Little => self.to_le_bytes(),
+                    };
+                    bytes.copy_from_slice(&value);
+                }
+
+                fn read(bytes: &[u8], endian: Endian) -> Result<Self, FieldError> {
+                    let bytes = bytes.try_into().map_err(|_| FieldError::OutOfRange)?;
+                    Ok(match endian {
+                        Endian::Big => Self::from_be_bytes(bytes),
+                        Endian::Little => Self::from_le_bytes(bytes),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_field_for_number!(u8, u16, u32, i16);
+
+/// A float that is neither NaN nor infinite.
+impl Field for f32 {
+    const LEN: usize = 4;
+
+    fn write(&self, bytes: &mut [u8], endian: Endian) {
+        self.to_bits().write(bytes, endian);
+    }
+
+    fn read(bytes: &[u8], endian: Endian) -> Result<Self, FieldError> {
+        let value = f32::from_bits(u32::read(bytes, endian)?);
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(FieldError::InvalidFloat)
+        }
+    }
+}
+
+/// A single byte that is either 0 or 1.
+impl Field for bool {
+    const LEN: usize = 1;
+
+    fn write(&self, bytes: &mut [u8], _: Endian) {
+        bytes[0] = *self as u8;
+    }
+
+    fn read(bytes: &[u8], _: Endian) -> Result<Self, FieldError> {
+        match bytes {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(FieldError::OutOfRange),
+        }
+    }
+}
+
+impl Field for FaultKind {
+    const LEN: usize = 1;
+
+    fn write(&self, bytes: &mut [u8], _: Endian) {
+        bytes[0] = *self as u8;
+    }
+
+    fn read(bytes: &[u8], _: Endian) -> Result<Self, FieldError> {
+        let kind = bytes.first().ok_or(FieldError::OutOfRange)?;
+        FaultKind::try_from(*kind).map_err(|_| FieldError::OutOfRange)
+    }
+}
+
+impl Field for Component {
+    const LEN: usize = 1;
+
+    fn write(&self, bytes: &mut [u8], _: Endian) {
+        bytes[0] = *self as u8;
+    }
+
+    fn read(bytes: &[u8], _: Endian) -> Result<Self, FieldError> {
+        let component = bytes.first().ok_or(FieldError::OutOfRange)?;
+        Component::try_from(*component).map_err(|_| FieldError::OutOfRange)
+    }
+}
+
+/// The major, minor and patch version, a byte each.
+impl Field for Version {
+    const LEN: usize = 3;
+
+    fn write(&self, bytes: &mut [u8], _: Endian) {
+        bytes.copy_from_slice(&[self.major, self.minor, self.patch]);
+    }
+
+    fn read(bytes: &[u8], _: Endian) -> Result<Self, FieldError> {
+        let [major, minor, patch] = bytes else {
+            return Err(FieldError::OutOfRange);
+        };
+        Ok(Self {
+            major: *major,
+            minor: *minor,
+            patch: *patch,
+        })
+    }
+}
+
+/// The unit (0 for degrees, 1 for a position), a reserved byte, and the value as an f32.
+impl Field for ServoTarget {
+    const LEN: usize = 6;
+
+    fn write(&self, bytes: &mut [u8], endian: Endian) {
+        let (unit, value) = match self {
+            ServoTarget::Degrees(degrees) => (0, degrees),
+            ServoTarget::Position(position) => (1, position),
+        };
+        bytes[0] = unit;
+        bytes[1] = 0;
+        value.write(&mut bytes[2..], endian);
+    }
+
+    fn read(bytes: &[u8], endian: Endian) -> Result<Self, FieldError> {
+        let [unit, reserved, value @ ..] = bytes else {
+            return Err(FieldError::OutOfRange);
+        };
+        if *reserved != 0 {
+            return Err(FieldError::ReservedBytes);
+        }
+
+        let value = f32::read(value, endian)?;
+        match unit {
+            0 => Ok(ServoTarget::Degrees(value)),
+            1 => Ok(ServoTarget::Position(value)),
+            _ => Err(FieldError::OutOfRange),
+        }
+    }
+}
+
+/// Returns which bytes of a message are reserved, given the `(offset, length)` of each of its
+/// fields. Byte 0 holds the opcode.
+///
+/// Used by `#[derive(Message)]` in a constant, so fields that overlap or do not fit fail to compile.
+pub(crate) const fn reserved_bytes(fields: &[(usize, usize)]) -> [bool; 8] {
+    let mut reserved = [true; 8];
+    reserved[0] = false;
+
+    let mut field = 0;
+    while field < fields.len() {
+        let (offset, length) = fields[field];
+        assert!(offset + length <= 8, "a field does not fit in the message");
+
+        let mut byte = offset;
+        while byte < offset + length {
+            assert!(
+                reserved[byte],
+                "a field overlaps with the opcode or another field"
+            );
+            reserved[byte] = false;
+            byte += 1;
+        }
+        field += 1;
+    }
+
+    reserved
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Message, MessageError};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Message)]
+    enum Example {
+        #[message(opcode = 0x01)]
+        Little {
+            #[message(offset = 1)]
+            flag: bool,
+            #[message(offset = 4, endian = "little")]
+            value: i16,
+        },
+        #[message(opcode = 0x02)]
+        Empty,
+    }
+
+    #[test]
+    fn derived_layout() {
+        let message = Example::Little {
+            flag: true,
+            value: -2,
+        };
+        let bytes = message.to_bytes();
+        assert_eq!(bytes, [0x01, 1, 0, 0, 0xfe, 0xff, 0, 0]);
+        assert_eq!(Example::try_from(bytes), Ok(message));
+        assert_eq!(Example::Empty.to_bytes(), [0x02, 0, 0, 0, 0, 0, 0, 0]);
+
+        let reserved = [0x01, 1, 0, 0, 0xfe, 0xff, 0, 1];
+        assert_eq!(
+            Example::try_from(reserved),
+            Err(MessageError::ReservedBytes {
+                opcode: 0x01,
+                bytes: reserved
+            })
+        );
+        assert_eq!(
+            Example::try_from([0x01, 2, 0, 0, 0xfe, 0xff, 0, 0]),
+            Err(MessageError::OutOfRange {
+                opcode: 0x01,
+                field: "flag"
+            })
+        );
+        assert_eq!(
+            Example::try_from([0x03, 0, 0, 0, 0, 0, 0, 0]),
+            Err(MessageError::UnknownOpcode(0x03))
+        );
+    }
+}
@@ -0,0 +1,724 @@
+//! Frames of any length, for messages that do not fit in [`Bytes`].
+//!
+//! A frame is laid out as follows, with all numbers big-endian:
+//!
+//! |  bytes  | purpose                                                     |
+//! | :-----: | :---------------------------------------------------------- |
+//! |    0    | Tag, telling what kind of message the payload holds         |
+//! |    1    | Flags: bit 0 is set if the frame ends in a checksum, and    |
+//! |         | bit 1 if it holds a stamp                                   |
+//! |   2-3   | Length of the payload                                       |
+//! |  4-15   | [`Stamp`], if bit 1 of the flags is set                     |
+//! |   ...   | Payload                                                     |
+//! | last 4  | CRC-32 (IEEE) of the stamp and the payload, if bit 0 of the |
+//! |         | flags is set                                                |
+//!
+//! The 8-byte messages remain available as [`Encoding::Fixed`]: their first byte is read as the
+//! tag, and the other seven as the payload. They cannot hold a stamp.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::link::STAMP_LEN;
+use crate::{
+    Bytes, CarburetorToLinkage, CockpitToLinkage, Handshake, LinkageToCarburetor, LinkageToCockpit,
+    Message, MessageError, Stamp,
+};
+
+/// The length of the header in front of the payload of a frame.
+pub const HEADER_LEN: usize = 4;
+
+/// The length of the longest payload a frame can hold.
+pub const MAX_PAYLOAD_LEN: usize = u16::MAX as usize;
+
+/// Set in the flags of a frame that ends in a checksum.
+const FLAG_CHECKSUM: u8 = 0x01;
+
+/// Set in the flags of a frame that holds a stamp.
+const FLAG_STAMP: u8 = 0x02;
+
+/// The length of the checksum at the end of a frame.
+const CHECKSUM_LEN: usize = 4;
+
+/// How messages are laid out on a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// Every message is exactly 8 bytes, as [`Bytes`].
+    #[default]
+    Fixed,
+    /// Every message is a length-prefixed frame, which ends in a checksum if `checksum` is set.
+    Framed { checksum: bool },
+}
+
+impl Encoding {
+    /// Returns the length of the longest encoded frame with a payload of at most
+    /// `max_payload_len` bytes, stamp included.
+    pub const fn max_frame_len(self, max_payload_len: usize) -> usize {
+        match self {
+            Self::Fixed => std::mem::size_of::<Bytes>(),
+            Self::Framed { checksum: false } => HEADER_LEN + STAMP_LEN + max_payload_len,
+            Self::Framed { checksum: true } => {
+                HEADER_LEN + STAMP_LEN + max_payload_len + CHECKSUM_LEN
+            }
+        }
+    }
+}
+
+/// A message as a tag and a payload of any length up to [`MAX_PAYLOAD_LEN`], optionally stamped
+/// with when and in which order it was sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub tag: u8,
+    pub stamp: Option<Stamp>,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(tag: u8, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            tag,
+            stamp: None,
+            payload: payload.into(),
+        }
+    }
+
+    /// Returns this frame with `stamp`.
+    pub fn with_stamp(self, stamp: Stamp) -> Self {
+        Self {
+            stamp: Some(stamp),
+            ..self
+        }
+    }
+
+    /// Encodes this frame as described in the [module documentation](self).
+    ///
+    /// Returns [`MessageError::InvalidPayloadLength`] if the payload is longer than
+    /// [`MAX_PAYLOAD_LEN`], or is not seven bytes long for [`Encoding::Fixed`]. The stamp is left
+    /// out for [`Encoding::Fixed`].
+    pub fn encode(&self, encoding: Encoding) -> Result<Vec<u8>, MessageError> {
+        let checksum = match encoding {
+            Encoding::Fixed => return Bytes::try_from(self).map(Vec::from),
+            Encoding::Framed { checksum } => checksum,
+        };
+
+        let length =
+            u16::try_from(self.payload.len()).map_err(|_| MessageError::InvalidPayloadLength {
+                tag: self.tag,
+                length: self.payload.len(),
+            })?;
+        let mut flags = 0;
+        if checksum {
+            flags |= FLAG_CHECKSUM;
+        }
+        if self.stamp.is_some() {
+            flags |= FLAG_STAMP;
+        }
+
+        let mut bytes =
+            Vec::with_capacity(HEADER_LEN + STAMP_LEN + self.payload.len() + CHECKSUM_LEN);
+        bytes.push(self.tag);
+        bytes.push(flags);
+        bytes.extend_from_slice(&length.to_be_bytes());
+        if let Some(stamp) = &self.stamp {
+            bytes.extend_from_slice(&stamp.to_bytes());
+        }
+        bytes.extend_from_slice(&self.payload);
+        if checksum {
+            let checksum = crc32(&bytes[HEADER_LEN..]);
+            bytes.extend_from_slice(&checksum.to_be_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes the frame at the start of `bytes`, and returns it with the number of bytes it took
+    /// up.
+    ///
+    /// Returns `Ok(None)` if `bytes` does not hold the whole frame yet.
+    pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<Option<(Self, usize)>, MessageError> {
+        if encoding == Encoding::Fixed {
+            return Ok(bytes
+                .get(..Bytes::default().len())
+                .map(|message| (Self::new(message[0], &message[1..]), message.len())));
+        }
+
+        let Some(&[tag, flags, length_1, length_2]) = bytes.get(..HEADER_LEN) else {
+            return Ok(None);
+        };
+        if flags & !(FLAG_CHECKSUM | FLAG_STAMP) != 0 {
+            return Err(MessageError::UnknownFlags(flags));
+        }
+
+        let length = u16::from_be_bytes([length_1, length_2]) as usize;
+        let stamp_len = if flags & FLAG_STAMP != 0 {
+            STAMP_LEN
+        } else {
+            0
+        };
+        let checksum_len = if flags & FLAG_CHECKSUM != 0 {
+            CHECKSUM_LEN
+        } else {
+            0
+        };
+        let Some(rest) = bytes.get(HEADER_LEN..HEADER_LEN + stamp_len + length + checksum_len)
+        else {
+            return Ok(None);
+        };
+
+        let (checked, checksum) = rest.split_at(stamp_len + length);
+        if let Ok(checksum) = <[u8; CHECKSUM_LEN]>::try_from(checksum) {
+            if u32::from_be_bytes(checksum) != crc32(checked) {
+                return Err(MessageError::ChecksumMismatch { tag });
+            }
+        }
+
+        let (stamp, payload) = checked.split_at(stamp_len);
+        let frame = Self {
+            tag,
+            stamp: stamp.try_into().ok().map(Stamp::from_bytes),
+            payload: payload.to_vec(),
+        };
+        Ok(Some((frame, HEADER_LEN + rest.len())))
+    }
+}
+
+/// An 8-byte message as a frame: the first byte is the tag, and the others are the payload.
+impl From<Bytes> for Frame {
+    fn from(value: Bytes) -> Self {
+        Self::new(value[0], &value[1..])
+    }
+}
+
+impl TryFrom<&Frame> for Bytes {
+    type Error = MessageError;
+
+    fn try_from(value: &Frame) -> Result<Self, Self::Error> {
+        let mut bytes = Bytes::default();
+        if value.payload.len() != bytes.len() - 1 {
+            return Err(MessageError::InvalidPayloadLength {
+                tag: value.tag,
+                length: value.payload.len(),
+            });
+        }
+
+        bytes[0] = value.tag;
+        bytes[1..].copy_from_slice(&value.payload);
+        Ok(bytes)
+    }
+}
+
+/// A message that can be sent as a [`Frame`].
+pub trait FramedMessage: Sized {
+    fn to_frame(&self) -> Frame;
+
+    fn from_frame(frame: &Frame) -> Result<Self, MessageError>;
+}
+
+/// Sends 8-byte messages as frames, with their opcode as the tag.
+macro_rules! impl_framed_message_for_bytes {
+    ($($message:ty),*) => {$(
+        impl FramedMessage for $message {
+            fn to_frame(&self) -> Frame {
+                Frame::from(self.to_bytes())
+            }
+
+            fn from_frame(frame: &Frame) -> Result<Self, MessageError> {
+                Self::try_from(Bytes::try_from(frame)?)
+            }
+        }
+    )*};
+}
+
+impl_framed_message_for_bytes!(
+    CockpitToLinkage,
+    LinkageToCockpit,
+    LinkageToCarburetor,
+    CarburetorToLinkage,
+    Handshake
+);
+
+/// Reads frames from a stream.
+///
+/// Bytes that have been read are kept until a whole frame has arrived, so reading can be retried
+/// after a timeout or [`ErrorKind::WouldBlock`] without losing data.
+///
+/// After a frame with a malformed header, the reader slides over the stream one byte at a time
+/// until the bytes form a valid frame again, so a corrupted frame does not throw off every frame
+/// after it.
+pub struct FrameReader<R> {
+    reader: R,
+    encoding: Encoding,
+    max_payload_len: usize,
+    buffer: Vec<u8>,
+    /// Whether we are looking for the start of the next valid frame.
+    resyncing: bool,
+    /// The number of bytes skipped while resyncing.
+    skipped: usize,
+    /// The stamp of the last frame that was read.
+    stamp: Option<Stamp>,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R, encoding: Encoding) -> Self {
+        Self {
+            reader,
+            encoding,
+            max_payload_len: MAX_PAYLOAD_LEN,
+            buffer: Vec::new(),
+            resyncing: false,
+            skipped: 0,
+            stamp: None,
+        }
+    }
+
+    /// Treats frames with a payload longer than `max_payload_len` as malformed, so a corrupted
+    /// length is noticed right away, rather than after waiting for that many bytes.
+    pub fn with_max_payload_len(mut self, max_payload_len: usize) -> Self {
+        self.max_payload_len = max_payload_len;
+        self
+    }
+
+    /// Returns the stamp of the last frame that was read, if it had one.
+    pub fn stamp(&self) -> Option<Stamp> {
+        self.stamp
+    }
+
+    /// Reads the next frame.
+    ///
+    /// Returns an [`ErrorKind::UnexpectedEof`] error if the stream ends, and an
+    /// [`ErrorKind::InvalidData`] error holding the [`MessageError`] if the frame is malformed.
+    /// The next call then skips ahead to the next valid frame.
+    pub fn read_frame(&mut self) -> io::Result<Frame> {
+        self.read_with(Ok)
+    }
+
+    /// Reads the next frame, and decodes it into a message.
+    ///
+    /// Returns an [`ErrorKind::InvalidData`] error if the frame is malformed, or does not hold an
+    /// `M`. While skipping ahead after a malformed frame, frames that do not hold an `M` are
+    /// skipped as well, as they are most likely not frames at all. With [`Encoding::Fixed`], which
+    /// has no header to check, a message that cannot be decoded is treated as a malformed frame.
+    pub fn read_message<M: FramedMessage>(&mut self) -> io::Result<M> {
+        self.read_with(|frame| M::from_frame(&frame))
+    }
+
+    fn read_with<T>(&mut self, decode: impl Fn(Frame) -> Result<T, MessageError>) -> io::Result<T> {
+        let mut chunk = [0; 256];
+        loop {
+            if let Some(result) = self.decode_buffered(&decode) {
+                return result.map_err(|error| io::Error::new(ErrorKind::InvalidData, error));
+            }
+
+            match self.reader.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(length) => self.buffer.extend_from_slice(&chunk[..length]),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Decodes the frame at the start of the buffer with `decode`, or returns `None` if more bytes
+    /// are needed.
+    fn decode_buffered<T>(
+        &mut self,
+        decode: &impl Fn(Frame) -> Result<T, MessageError>,
+    ) -> Option<Result<T, MessageError>> {
+        loop {
+            let decoded = match self.check_length() {
+                Ok(()) => Frame::decode(&self.buffer, self.encoding),
+                Err(error) => Err(error),
+            };
+
+            match decoded {
+                Ok(None) => return None,
+                Ok(Some((frame, length))) => {
+                    let stamp = frame.stamp;
+                    let decoded = decode(frame);
+                    if decoded.is_err() && self.resyncing {
+                        self.skip();
+                        continue;
+                    }
+
+                    if decoded.is_err() && self.encoding == Encoding::Fixed {
+                        // The message may just as well be out of step.
+                        self.resyncing = true;
+                        self.stamp = None;
+                        self.skip();
+                        return Some(decoded);
+                    }
+
+                    self.buffer.drain(..length);
+                    self.stamp = stamp;
+                    if self.resyncing {
+                        log::info!("Resynchronised after skipping {} bytes.", self.skipped);
+                        self.resyncing = false;
+                        self.skipped = 0;
+                    }
+                    return Some(decoded);
+                }
+                Err(_) if self.resyncing => self.skip(),
+                Err(error) => {
+                    self.resyncing = true;
+                    self.stamp = None;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+
+    /// Returns an error if the header at the start of the buffer announces a payload longer than
+    /// the maximum.
+    fn check_length(&self) -> Result<(), MessageError> {
+        if self.encoding == Encoding::Fixed {
+            return Ok(());
+        }
+
+        match self.buffer.get(..HEADER_LEN) {
+            Some(&[tag, _, length_1, length_2]) => {
+                let length = u16::from_be_bytes([length_1, length_2]) as usize;
+                if length > self.max_payload_len {
+                    return Err(MessageError::InvalidPayloadLength { tag, length });
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Drops the first byte of the buffer.
+    fn skip(&mut self) {
+        self.buffer.remove(0);
+        self.skipped += 1;
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Writes frames to a stream.
+pub struct FrameWriter<W> {
+    writer: W,
+    encoding: Encoding,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(writer: W, encoding: Encoding) -> Self {
+        Self { writer, encoding }
+    }
+
+    /// Writes `frame` as a whole.
+    ///
+    /// Returns an [`ErrorKind::InvalidInput`] error if it cannot be encoded.
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let bytes = frame
+            .encode(self.encoding)
+            .map_err(|error| io::Error::new(ErrorKind::InvalidInput, error))?;
+        self.writer.write_all(&bytes)
+    }
+
+    pub fn write_message<M: FramedMessage>(&mut self, message: &M) -> io::Result<()> {
+        self.write_frame(&message.to_frame())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Computes the CRC-32 of `bytes`, as used by Ethernet and zlib.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::{self, ErrorKind, Read};
+
+    use std::io::Cursor;
+
+    use super::{crc32, Encoding, Frame, FrameReader, FrameWriter, FramedMessage};
+    use crate::{
+        LinkageToCarburetor, LinkageToCarburetorFrame, Message, MessageError, MotorBatch, Stamp,
+    };
+
+    const FRAMED: Encoding = Encoding::Framed { checksum: false };
+    const CHECKED: Encoding = Encoding::Framed { checksum: true };
+
+    /// Hands out its bytes a few at a time, failing with [`ErrorKind::WouldBlock`] in between.
+    struct Trickle {
+        bytes: VecDeque<u8>,
+        blocked: bool,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.blocked = !self.blocked;
+            if self.blocked {
+                return Err(ErrorKind::WouldBlock.into());
+            }
+
+            let length = buf.len().min(3).min(self.bytes.len());
+            for byte in &mut buf[..length] {
+                *byte = self.bytes.pop_front().unwrap();
+            }
+            Ok(length)
+        }
+    }
+
+    fn instruction(channel: u8, speed: f32) -> LinkageToCarburetor {
+        LinkageToCarburetor::MotorInstruction { channel, speed }
+    }
+
+    fn encode(message: &impl FramedMessage, encoding: Encoding) -> Vec<u8> {
+        message.to_frame().encode(encoding).unwrap()
+    }
+
+    /// Returns the [`MessageError`] of a read that failed on a malformed frame.
+    fn malformed<T: std::fmt::Debug>(result: io::Result<T>) -> MessageError {
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        *error
+            .into_inner()
+            .unwrap()
+            .downcast::<MessageError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let frame = Frame::new(0x45, [1, 2, 3]);
+        for encoding in [FRAMED, CHECKED] {
+            let bytes = frame.encode(encoding).unwrap();
+            assert_eq!(
+                Frame::decode(&bytes, encoding),
+                Ok(Some((frame.clone(), bytes.len())))
+            );
+            // Incomplete frames are not decoded yet.
+            assert_eq!(Frame::decode(&bytes[..bytes.len() - 1], encoding), Ok(None));
+        }
+
+        assert_eq!(frame.encode(FRAMED).unwrap(), [0x45, 0, 0, 3, 1, 2, 3]);
+        let empty = Frame::new(0x45, []);
+        assert_eq!(
+            Frame::decode(&[0x45, 0, 0, 0], FRAMED),
+            Ok(Some((empty, 4)))
+        );
+    }
+
+    #[test]
+    fn stamped_round_trip() {
+        let stamp = Stamp {
+            sequence: 7,
+            sent_at_us: 8,
+            round_trip_us: 9,
+        };
+        let frame = Frame::new(0x45, [1, 2, 3]).with_stamp(stamp);
+        for encoding in [FRAMED, CHECKED] {
+            let bytes = frame.encode(encoding).unwrap();
+            assert_eq!(
+                Frame::decode(&bytes, encoding),
+                Ok(Some((frame.clone(), bytes.len())))
+            );
+        }
+
+        assert_eq!(
+            frame.encode(FRAMED).unwrap(),
+            [0x45, 0x02, 0, 3, 0, 0, 0, 7, 0, 0, 0, 8, 0, 0, 0, 9, 1, 2, 3]
+        );
+        // The checksum covers the stamp as well.
+        let mut bytes = frame.encode(CHECKED).unwrap();
+        bytes[7] ^= 0xFF;
+        assert_eq!(
+            Frame::decode(&bytes, CHECKED),
+            Err(MessageError::ChecksumMismatch { tag: 0x45 })
+        );
+    }
+
+    #[test]
+    fn rejects_corrupted_frames() {
+        let mut bytes = Frame::new(0x45, [1, 2, 3]).encode(CHECKED).unwrap();
+        bytes[5] ^= 0xFF;
+        assert_eq!(
+            Frame::decode(&bytes, CHECKED),
+            Err(MessageError::ChecksumMismatch { tag: 0x45 })
+        );
+        assert_eq!(
+            Frame::decode(&[0x45, 0x80, 0, 0], FRAMED),
+            Err(MessageError::UnknownFlags(0x80))
+        );
+
+        let too_long = Frame::new(0x45, vec![0; super::MAX_PAYLOAD_LEN + 1]);
+        assert!(too_long.encode(FRAMED).is_err());
+    }
+
+    #[test]
+    fn fixed_encoding_is_compatible() {
+        let message = LinkageToCarburetor::EStop;
+        let frame = message.to_frame();
+        assert_eq!(frame.encode(Encoding::Fixed).unwrap(), message.to_bytes());
+        assert_eq!(LinkageToCarburetor::from_frame(&frame), Ok(message));
+        assert!(Frame::new(0x42, [0; 8]).encode(Encoding::Fixed).is_err());
+    }
+
+    #[test]
+    fn reads_across_partial_reads() {
+        let messages = [
+            LinkageToCarburetor::MotorInstruction {
+                channel: 1,
+                speed: 0.5,
+            },
+            LinkageToCarburetor::EStop,
+        ];
+
+        for encoding in [Encoding::Fixed, FRAMED, CHECKED] {
+            let mut writer = FrameWriter::new(Vec::new(), encoding);
+            for message in &messages {
+                writer.write_message(message).unwrap();
+            }
+
+            let mut reader = FrameReader::new(
+                Trickle {
+                    bytes: writer.into_inner().into(),
+                    blocked: false,
+                },
+                encoding,
+            );
+            for message in &messages {
+                let received = loop {
+                    match reader.read_message::<LinkageToCarburetor>() {
+                        Err(error) if error.kind() == ErrorKind::WouldBlock => continue,
+                        result => break result.unwrap(),
+                    }
+                };
+                assert_eq!(&received, message);
+            }
+
+            let end = loop {
+                match reader.read_frame() {
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => continue,
+                    result => break result,
+                }
+            };
+            assert_eq!(end.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn resyncs_after_garbage() {
+        let mut bytes = Vec::new();
+        bytes.extend(encode(&instruction(0, 0.5), FRAMED));
+        bytes.extend([0xff, 0x13, 0x37]);
+        bytes.extend(encode(&instruction(0, 0.25), FRAMED));
+        bytes.extend(encode(&instruction(1, -0.5), FRAMED));
+
+        let mut reader = FrameReader::new(Cursor::new(bytes), FRAMED).with_max_payload_len(64);
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetor>().unwrap(),
+            instruction(0, 0.5)
+        );
+        assert_eq!(
+            malformed(reader.read_frame()),
+            MessageError::InvalidPayloadLength {
+                tag: 0xff,
+                length: 0x3740
+            }
+        );
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetor>().unwrap(),
+            instruction(0, 0.25)
+        );
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetor>().unwrap(),
+            instruction(1, -0.5)
+        );
+        assert_eq!(
+            reader.read_frame().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn skips_frames_without_a_message() {
+        let batch = MotorBatch {
+            speeds: vec![(0, 0.5), (1, -0.5)],
+        };
+        let mut bytes = Vec::new();
+        // An unknown opcode, in a frame that is fine otherwise.
+        bytes.extend([0x4f, 0, 0, 7, 1, 0, 0, 0, 0, 0, 0]);
+        bytes.extend(encode(&batch, FRAMED));
+
+        let mut reader = FrameReader::new(Cursor::new(bytes), FRAMED);
+        assert_eq!(
+            malformed(reader.read_message::<LinkageToCarburetorFrame>()),
+            MessageError::UnknownOpcode(0x4f)
+        );
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetorFrame>().unwrap(),
+            batch.into()
+        );
+    }
+
+    #[test]
+    fn resyncs_fixed_messages() {
+        let mut bytes = vec![0x13, 0x37];
+        bytes.extend(encode(&instruction(0, 0.5), Encoding::Fixed));
+        bytes.extend(encode(&LinkageToCarburetor::EStop, Encoding::Fixed));
+
+        let mut reader = FrameReader::new(Cursor::new(bytes), Encoding::Fixed);
+        malformed(reader.read_message::<LinkageToCarburetor>());
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetor>().unwrap(),
+            instruction(0, 0.5)
+        );
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetor>().unwrap(),
+            LinkageToCarburetor::EStop
+        );
+    }
+
+    #[test]
+    fn keeps_the_stamp() {
+        let stamp = Stamp {
+            sequence: 1,
+            sent_at_us: 2,
+            round_trip_us: 3,
+        };
+        let stamped = |frame: Frame| frame.with_stamp(stamp).encode(FRAMED).unwrap();
+        let mut bytes = stamped(instruction(0, 0.5).to_frame());
+        // An unknown opcode.
+        bytes.extend(stamped(Frame::new(0x4f, [4, 0, 0, 0, 0, 0, 0])));
+
+        let mut reader = FrameReader::new(Cursor::new(bytes), FRAMED);
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetor>().unwrap(),
+            instruction(0, 0.5)
+        );
+        assert_eq!(reader.stamp(), Some(stamp));
+        assert_eq!(
+            malformed(reader.read_message::<LinkageToCarburetor>()),
+            MessageError::UnknownOpcode(0x4f)
+        );
+    }
+}
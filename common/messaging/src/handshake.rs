@@ -0,0 +1,268 @@
+use std::fmt::{self, Display};
+use std::io::{self, ErrorKind, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Bytes, Message, MessageError};
+
+/// The version of the protocol between linkage programs. It goes up with every change to the
+/// messages, and programs refuse to talk to peers that speak another version.
+pub const PROTOCOL_VERSION: u8 = 9;
+
+/// A linkage program, as introduced in a [`Handshake`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Component {
+    Cockpit = 1,
+    LinkageLib = 2,
+    Carburetor = 3,
+    Gauge = 4,
+}
+
+impl TryFrom<u8> for Component {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Cockpit),
+            2 => Ok(Self::LinkageLib),
+            3 => Ok(Self::Carburetor),
+            4 => Ok(Self::Gauge),
+            value => Err(value),
+        }
+    }
+}
+
+impl Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Cockpit => "Cockpit",
+            Self::LinkageLib => "Linkage",
+            Self::Carburetor => "Carburetor",
+            Self::Gauge => "Gauge",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The crate version of a linkage program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl Version {
+    /// Parses a version like `CARGO_PKG_VERSION`. Parts that are missing or do not fit are 0, and
+    /// pre-release and build suffixes are ignored.
+    pub fn parse(version: &str) -> Self {
+        let mut parts = version
+            .split(['.', '-', '+'])
+            .map(|part| part.parse().unwrap_or(0));
+        Self {
+            major: parts.next().unwrap_or(0),
+            minor: parts.next().unwrap_or(0),
+            patch: parts.next().unwrap_or(0),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The first message both sides send when a connection between linkage programs opens, before
+/// any other message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Message)]
+#[message(opcode = 0x01)]
+pub struct Handshake {
+    #[message(offset = 1)]
+    pub protocol_version: u8,
+    #[message(offset = 2)]
+    pub component: Component,
+    #[message(offset = 4)]
+    pub version: Version,
+}
+
+impl Handshake {
+    /// Introduces `component` at crate `version`, speaking [`PROTOCOL_VERSION`].
+    ///
+    /// `version` is usually `env!("CARGO_PKG_VERSION")`, see [`Version::parse`].
+    pub fn new(component: Component, version: &str) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            component,
+            version: Version::parse(version),
+        }
+    }
+
+    /// Returns whether a peer that sent this handshake speaks the same protocol as this program.
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == PROTOCOL_VERSION
+    }
+
+    /// Sends `self` over `stream` and reads the handshake of the peer, which should be
+    /// `expected`.
+    ///
+    /// Returns an [`ErrorKind::InvalidData`] error describing the mismatch if the peer is not
+    /// `expected`, or holding a [`MessageError::UnsupportedVersion`] if it does not speak the same
+    /// protocol version.
+    pub fn exchange<S: Read + Write>(
+        self,
+        stream: &mut S,
+        expected: Component,
+    ) -> io::Result<Handshake> {
+        stream.write_all(&self.to_bytes())?;
+
+        let mut bytes = Bytes::default();
+        stream.read_exact(&mut bytes)?;
+        let Ok(peer) = Handshake::try_from(bytes) else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "expected a handshake from {expected}, but received {bytes:?}. It is probably older than {} {}, which speaks protocol version {}",
+                    self.component, self.version, self.protocol_version
+                ),
+            ));
+        };
+
+        if peer.component != expected {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("expected {expected}, but {peer} connected"),
+            ));
+        }
+        if !peer.is_compatible() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                MessageError::UnsupportedVersion(peer),
+            ));
+        }
+
+        Ok(peer)
+    }
+}
+
+impl Display for Handshake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (protocol version {})",
+            self.component, self.version, self.protocol_version
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind, Read, Write};
+
+    use super::{Component, Handshake, Version, PROTOCOL_VERSION};
+    use crate::{Message, MessageError};
+
+    /// A stream that reads from `incoming`, and writes to `outgoing`.
+    struct Duplex {
+        incoming: Cursor<Vec<u8>>,
+        outgoing: Vec<u8>,
+    }
+
+    impl Duplex {
+        fn new(incoming: impl Into<Vec<u8>>) -> Self {
+            Self {
+                incoming: Cursor::new(incoming.into()),
+                outgoing: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.incoming.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.outgoing.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parses_versions() {
+        let version = |major, minor, patch| Version {
+            major,
+            minor,
+            patch,
+        };
+        assert_eq!(Version::parse("0.1.2"), version(0, 1, 2));
+        assert_eq!(Version::parse("1.2.3-beta.1+abc"), version(1, 2, 3));
+        assert_eq!(Version::parse("1.300"), version(1, 0, 0));
+        assert_eq!(Version::parse("0.1.2").to_string(), "0.1.2");
+    }
+
+    #[test]
+    fn round_trip() {
+        let handshake = Handshake::new(Component::Carburetor, "0.1.2");
+        let bytes = handshake.to_bytes();
+        assert_eq!(bytes, [0x01, PROTOCOL_VERSION, 3, 0, 0, 1, 2, 0]);
+        assert_eq!(Handshake::try_from(bytes), Ok(handshake));
+        assert_eq!(
+            Handshake::try_from([0x01, 1, 9, 0, 0, 1, 2, 0]),
+            Err(MessageError::OutOfRange {
+                opcode: 0x01,
+                field: "component"
+            })
+        );
+        assert!(matches!(
+            Handshake::try_from([0x01, 1, 3, 1, 0, 1, 2, 0]),
+            Err(MessageError::ReservedBytes { .. })
+        ));
+    }
+
+    #[test]
+    fn exchange() {
+        let linkage = Handshake::new(Component::LinkageLib, "0.1.0");
+        let carburetor = Handshake::new(Component::Carburetor, "0.1.2");
+
+        let mut stream = Duplex::new(carburetor.to_bytes());
+        let peer = linkage
+            .exchange(&mut stream, Component::Carburetor)
+            .unwrap();
+        assert_eq!(peer, carburetor);
+        assert_eq!(stream.outgoing, linkage.to_bytes());
+    }
+
+    #[test]
+    fn refuses_incompatible_peers() {
+        let linkage = Handshake::new(Component::LinkageLib, "0.1.0");
+        let refused = |incoming: [u8; 8], expected| {
+            let error = linkage
+                .exchange(&mut Duplex::new(incoming), expected)
+                .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        };
+
+        let mut newer = Handshake::new(Component::Carburetor, "9.0.0");
+        newer.protocol_version += 1;
+        refused(newer.to_bytes(), Component::Carburetor);
+        let error = linkage
+            .exchange(&mut Duplex::new(newer.to_bytes()), Component::Carburetor)
+            .unwrap_err();
+        let error = error.get_ref().unwrap().downcast_ref::<MessageError>();
+        assert_eq!(error, Some(&MessageError::UnsupportedVersion(newer)));
+        // The wrong program.
+        refused(
+            Handshake::new(Component::Gauge, "0.1.2").to_bytes(),
+            Component::Carburetor,
+        );
+        // A program from before handshakes.
+        refused([0x60, 0, 0x40, 0, 0, 0, 0, 0], Component::Carburetor);
+    }
+}
@@ -1,8 +1,24 @@
 mod error;
+mod field;
+pub mod frame;
+mod handshake;
+mod link;
 
-use error::MessageError;
+pub use error::{DecodeErrorCounts, MessageError};
+pub use field::{Endian, Field, FieldError};
+pub use frame::{Encoding, Frame, FrameReader, FrameWriter, FramedMessage};
+pub use handshake::{Component, Handshake, Version, PROTOCOL_VERSION};
+pub use link::{LinkMonitor, LinkStats, Stamp};
+use message_derive::Message;
 use serde::{Deserialize, Serialize};
 
+/// How Cockpit sends its messages to Linkage. Linkage answers with 8-byte messages.
+pub const COCKPIT_TO_LINKAGE_ENCODING: Encoding = Encoding::Framed { checksum: false };
+
+/// How Linkage sends its messages to Carburetor, both over streams and in datagrams. Carburetor
+/// answers with 8-byte messages.
+pub const LINKAGE_TO_CARBURETOR_ENCODING: Encoding = Encoding::Framed { checksum: false };
+
 /// An 8-byte array that serves as the common message sent between linkage programs.
 pub type Bytes = [u8; 8];
 
@@ -12,141 +28,439 @@ pub trait Message: TryFrom<Bytes> + Into<Bytes> {
 }
 
 // Cockpit ------> Linkage Lib
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Message)]
 pub enum CockpitToLinkage {
+    /// An event of the gamepad with `gamepad_id`. The `value` of an axis is within -1.0..=1.0 and
+    /// exactly 0.0 at rest, and that of a button within 0.0..=1.0 and exactly 0.0 when released.
+    #[message(opcode = 0x21)]
     GamepadInputEvent {
+        #[message(offset = 1)]
         gamepad_id: u8,
+        #[message(offset = 2)]
         event_type: u8,
+        #[message(offset = 3)]
         control: u8,
-        value: u8,
+        #[message(offset = 4)]
+        value: f32,
     },
+    /// Sent by Cockpit whenever it has sent nothing else for a while, so Linkage can tell a quiet
+    /// Cockpit from one it lost the connection with.
+    #[message(opcode = 0x22)]
+    Heartbeat,
 }
 
-impl Message for CockpitToLinkage {
-    fn to_bytes(&self) -> Bytes {
-        Bytes::from(*self)
-    }
+// Linkage Lib ------> Cockpit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Message)]
+pub enum LinkageToCockpit {
+    /// Echoes the time in the [`Stamp`] of a frame from Cockpit, so Cockpit can measure the round
+    /// trip.
+    #[message(opcode = 0x30)]
+    Echo {
+        #[message(offset = 4, endian = "big")]
+        sent_at_us: u32,
+    },
 }
 
-impl TryFrom<Bytes> for CockpitToLinkage {
-    type Error = MessageError;
+// Linkage ------> Carburetor
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Message)]
+pub enum LinkageToCarburetor {
+    /// Sets the speed of the motor on `channel`, within -1.0..=1.0.
+    #[message(opcode = 0x40)]
+    MotorInstruction {
+        #[message(offset = 1)]
+        channel: u8,
+        #[message(offset = 4, endian = "big", map = "round_speed")]
+        speed: f32,
+    },
+    /// Turns digital output `pin` on (`true`) or off (`false`).
+    #[message(opcode = 0x41)]
+    DigitalOutput {
+        #[message(offset = 1)]
+        pin: u8,
+        #[message(offset = 2)]
+        value: bool,
+    },
+    /// Immediately puts every channel into neutral and every digital output into its safe state,
+    /// and refuses further instructions until [`LinkageToCarburetor::ResetEStop`] is received.
+    #[message(opcode = 0x42)]
+    EStop,
+    /// Releases the latch set by [`LinkageToCarburetor::EStop`].
+    #[message(opcode = 0x43)]
+    ResetEStop,
+    /// Moves the servo on `channel` to `target`.
+    #[message(opcode = 0x44)]
+    ServoPosition {
+        #[message(offset = 1)]
+        channel: u8,
+        #[message(offset = 2, endian = "big")]
+        target: ServoTarget,
+    },
+}
+
+/// Rounds a speed that was sent as an f32, to prevent -0.00000000012098421 from not registering
+/// as neutral.
+fn round_speed(speed: f32) -> f32 {
+    (speed * 10_000.0).round() / 10_000.0
+}
 
-    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+/// Where a servo should move to, in a [`LinkageToCarburetor::ServoPosition`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ServoTarget {
+    /// An angle in degrees, within the angle range configured for the channel.
+    Degrees(f32),
+    /// A position within 0.0..=1.0, from the start to the end of the angle range of the channel.
+    Position(f32),
+}
+
+// Carburetor ------> Linkage
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Message)]
+pub enum CarburetorToLinkage {
+    /// The instruction with opcode `instruction` for `channel` has been accepted.
+    #[message(opcode = 0x60)]
+    InstructionAck {
+        #[message(offset = 2)]
+        instruction: u8,
+        #[message(offset = 1)]
+        channel: u8,
+    },
+    /// The state of a channel, sent periodically for every channel.
+    #[message(opcode = 0x61)]
+    ChannelStatus {
+        #[message(offset = 1)]
+        channel: u8,
+        /// Whether the channel is running its arming sequence, and does not accept instructions
+        /// yet.
+        #[message(offset = 2)]
+        arming: bool,
+        /// The pulse width that is currently being output, in microseconds.
+        #[message(offset = 4, endian = "big")]
+        pulse_width_us: u16,
+        /// The time since the output of the channel last changed, in milliseconds.
+        #[message(offset = 6, endian = "big")]
+        since_update_ms: u16,
+    },
+    /// Something went wrong with `channel`.
+    ///
+    /// A [`FaultKind::MalformedFrame`] is not about any channel, so its `channel` is always
+    /// [`CONNECTION_FAULT_CHANNEL`]. For faults with a digital output, `channel` is its pin.
+    #[message(opcode = 0x62)]
+    Fault {
+        #[message(offset = 1)]
+        channel: u8,
+        #[message(offset = 2)]
+        kind: FaultKind,
+    },
+    /// The debounced value of digital input `pin`, sent when it changes and periodically for every
+    /// input.
+    #[message(opcode = 0x63)]
+    DigitalInput {
+        #[message(offset = 1)]
+        pin: u8,
+        #[message(offset = 2)]
+        value: bool,
+        /// When the value last changed, in milliseconds since Carburetor started.
+        #[message(offset = 4, endian = "big")]
+        timestamp_ms: u32,
+    },
+    /// The value of analog input `pin`, sent when it changes by more than its threshold and
+    /// periodically for every input.
+    #[message(opcode = 0x66)]
+    AnalogInput {
+        #[message(offset = 1)]
+        pin: u8,
+        /// The reading as a fraction of the full scale of the ADC, within 0.0..=1.0.
+        #[message(offset = 4, endian = "big")]
+        value: f32,
+    },
+    /// Whether the emergency stop is latched, sent when it changes and periodically.
+    #[message(opcode = 0x64)]
+    EStopStatus {
+        #[message(offset = 1)]
+        latched: bool,
+    },
+    /// Echoes the time in the [`Stamp`] of a frame from Linkage, so Linkage can measure the round
+    /// trip.
+    #[message(opcode = 0x65)]
+    Echo {
+        #[message(offset = 4, endian = "big")]
+        sent_at_us: u32,
+    },
+}
+
+/// The channel of a [`CarburetorToLinkage::Fault`] that is about the connection rather than any
+/// channel, like a [`FaultKind::MalformedFrame`]. The bytes of a malformed frame cannot be trusted,
+/// so it is not reported against the channel it seems to be meant for.
+pub const CONNECTION_FAULT_CHANNEL: u8 = 0xff;
+
+/// Describes what went wrong in a [`CarburetorToLinkage::Fault`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FaultKind {
+    /// The instruction was sent to a channel that is not configured.
+    UnknownChannel = 1,
+    /// The instruction contained a speed outside of -1.0..=1.0.
+    OutOfRangeSpeed = 2,
+    /// The output backend failed to apply an instruction.
+    BackendError = 3,
+    /// A frame could not be decoded into a message. Reported on [`CONNECTION_FAULT_CHANNEL`].
+    MalformedFrame = 4,
+    /// The instruction was sent to a digital output pin that is not configured.
+    UnknownPin = 5,
+    /// The instruction was refused because the emergency stop is latched.
+    EStopLatched = 6,
+    /// A servo position was sent to a channel that is not configured as a servo.
+    NotAServo = 7,
+    /// The instruction contained a servo position outside of the range of the channel.
+    OutOfRangePosition = 8,
+    /// The instruction was refused because the channel is still running its arming sequence.
+    ChannelArming = 9,
+    /// The channel appears more than once in a [`MotorBatch`].
+    DuplicateChannel = 10,
+}
+
+impl TryFrom<u8> for FaultKind {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            [0x20, 0, 0, 0, gamepad_id, event_type, control, value] => {
-                Ok(Self::GamepadInputEvent {
-                    gamepad_id,
-                    event_type,
-                    control,
-                    value,
-                })
-            }
-            bytes => Err(MessageError::UnknownMessage(bytes)),
+            1 => Ok(Self::UnknownChannel),
+            2 => Ok(Self::OutOfRangeSpeed),
+            3 => Ok(Self::BackendError),
+            4 => Ok(Self::MalformedFrame),
+            5 => Ok(Self::UnknownPin),
+            6 => Ok(Self::EStopLatched),
+            7 => Ok(Self::NotAServo),
+            8 => Ok(Self::OutOfRangePosition),
+            9 => Ok(Self::ChannelArming),
+            10 => Ok(Self::DuplicateChannel),
+            value => Err(value),
         }
     }
 }
 
-impl From<CockpitToLinkage> for Bytes {
-    fn from(value: CockpitToLinkage) -> Self {
-        match value {
-            // TODO: We could use a f32 byte representation for the value
-            //       just like we do with LinkageToCarburetor::MotorInstruction
-            CockpitToLinkage::GamepadInputEvent {
-                gamepad_id,
-                event_type,
-                control,
-                value,
-            } => [0x20, 0, 0, 0, gamepad_id, event_type, control, value],
-        }
+impl LinkageToCarburetor {
+    /// Returns the opcode of this instruction, as used in [`CarburetorToLinkage::InstructionAck`].
+    pub fn opcode(&self) -> u8 {
+        self.to_bytes()[0]
     }
 }
 
-// Linkage ------> Carburetor
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub enum LinkageToCarburetor {
-    MotorInstruction { channel: u8, speed: f32 },
+/// Sets the speeds of any number of channels at once.
+///
+/// Carburetor checks every channel in the batch before it changes any of them, and then hands the
+/// speeds to their channels back to back. They are not applied atomically: a channel that is
+/// refused does not hold up the others. Every channel may appear only once.
+///
+/// Only fits in a [`Frame`]: its payload is a `(channel, speed)` pair of a u8 and a big-endian f32
+/// for every channel.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MotorBatch {
+    /// The `(channel, speed)` pairs, with every speed within -1.0..=1.0.
+    pub speeds: Vec<(u8, f32)>,
 }
 
-impl TryFrom<Bytes> for LinkageToCarburetor {
-    type Error = MessageError;
+impl MotorBatch {
+    /// The opcode of a batch, which is the tag of its frame.
+    pub const OPCODE: u8 = 0x45;
 
-    /// Decodes the buffer of type and format [`MessageBytes`].
-    ///
-    /// Returns [`None`] if the decoded speed is not valid. The speed is invalid when it is not within
-    /// -1.0..=1.0.
-    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
-        match value {
-            [0x40, channel, 0, 0, speed_byte_1, speed_byte_2, speed_byte_3, speed_byte_4] => {
-                let speed_bytes: [u8; 4] = [speed_byte_1, speed_byte_2, speed_byte_3, speed_byte_4];
-                let speed = f32::from_be_bytes(speed_bytes);
-                // We round the speed to prevent -0.00000000012098421 from not registering as neutral.
-                let speed = (speed * 10_000.0).round() / 10_000.0;
-                Ok(LinkageToCarburetor::MotorInstruction { channel, speed })
-            }
-            bytes => Err(MessageError::UnknownMessage(bytes)),
+    /// The length of the payload for every channel in the batch.
+    pub const SPEED_LEN: usize = 5;
+}
+
+impl FramedMessage for MotorBatch {
+    fn to_frame(&self) -> Frame {
+        let mut payload = Vec::with_capacity(self.speeds.len() * Self::SPEED_LEN);
+        for (channel, speed) in &self.speeds {
+            payload.push(*channel);
+            payload.extend_from_slice(&speed.to_be_bytes());
         }
+        Frame::new(Self::OPCODE, payload)
+    }
+
+    fn from_frame(frame: &Frame) -> Result<Self, MessageError> {
+        if frame.tag != Self::OPCODE || !frame.payload.len().is_multiple_of(Self::SPEED_LEN) {
+            return Err(MessageError::InvalidPayloadLength {
+                tag: frame.tag,
+                length: frame.payload.len(),
+            });
+        }
+
+        let speeds = frame
+            .payload
+            .chunks_exact(Self::SPEED_LEN)
+            .map(|chunk| {
+                let speed = f32::read(&chunk[1..], Endian::Big).map_err(|_| {
+                    MessageError::InvalidFloat {
+                        opcode: Self::OPCODE,
+                        field: "speed",
+                    }
+                })?;
+                // Rounded like the speed of a MotorInstruction.
+                Ok((chunk[0], round_speed(speed)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { speeds })
     }
 }
 
-impl From<LinkageToCarburetor> for Bytes {
+/// Everything Linkage sends to Carburetor, each in a [`Frame`] of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LinkageToCarburetorFrame {
+    Message(LinkageToCarburetor),
+    MotorBatch(MotorBatch),
+}
+
+impl LinkageToCarburetorFrame {
+    /// Returns the opcode of the message.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Self::Message(message) => message.opcode(),
+            Self::MotorBatch(_) => MotorBatch::OPCODE,
+        }
+    }
+}
+
+impl From<LinkageToCarburetor> for LinkageToCarburetorFrame {
     fn from(value: LinkageToCarburetor) -> Self {
-        match value {
-            LinkageToCarburetor::MotorInstruction { channel, speed } => {
-                let speed = speed.to_be_bytes();
-                [0x40, channel, 0, 0, speed[0], speed[1], speed[2], speed[3]]
-            }
+        Self::Message(value)
+    }
+}
+
+impl From<MotorBatch> for LinkageToCarburetorFrame {
+    fn from(value: MotorBatch) -> Self {
+        Self::MotorBatch(value)
+    }
+}
+
+impl FramedMessage for LinkageToCarburetorFrame {
+    fn to_frame(&self) -> Frame {
+        match self {
+            Self::Message(message) => message.to_frame(),
+            Self::MotorBatch(batch) => batch.to_frame(),
         }
     }
+
+    fn from_frame(frame: &Frame) -> Result<Self, MessageError> {
+        match frame.tag {
+            MotorBatch::OPCODE => MotorBatch::from_frame(frame).map(Self::MotorBatch),
+            _ => LinkageToCarburetor::from_frame(frame).map(Self::Message),
+        }
+    }
+}
+
+/// A message sent where it may be lost, duplicated or reordered, like in a UDP datagram. It is
+/// ordered by the sequence number of its stamp, which it shares with the frames sent over the other
+/// transports of the same link.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sequenced<M> {
+    pub stamp: Stamp,
+    pub message: M,
 }
 
-impl Message for LinkageToCarburetor {
-    fn to_bytes(&self) -> Bytes {
-        Bytes::from(*self)
+impl<M> Sequenced<M> {
+    /// Returns the sequence number of the stamp of this message.
+    pub fn sequence(&self) -> u32 {
+        self.stamp.sequence
+    }
+
+    /// Returns whether this message was sent after the one with sequence number `previous`.
+    ///
+    /// The sequence number wraps around, so a message follows any of the 2^31 - 1 sequence numbers
+    /// before it.
+    pub fn follows(&self, previous: u32) -> bool {
+        (self.sequence().wrapping_sub(previous) as i32) > 0
+    }
+}
+
+impl<M: FramedMessage> Sequenced<M> {
+    /// Encodes this message as a stamped frame in `encoding`, which must not be
+    /// [`Encoding::Fixed`], as that cannot hold the stamp.
+    pub fn encode(&self, encoding: Encoding) -> Result<Vec<u8>, MessageError> {
+        self.message
+            .to_frame()
+            .with_stamp(self.stamp)
+            .encode(encoding)
+    }
+
+    /// Decodes a message encoded by [`Sequenced::encode`]. `bytes` must hold exactly one message.
+    pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<Self, MessageError> {
+        match Frame::decode(bytes, encoding)? {
+            Some((frame, length)) if length == bytes.len() => {
+                let stamp = frame.stamp.ok_or(MessageError::UnstampedDatagram)?;
+                Ok(Self {
+                    stamp,
+                    message: M::from_frame(&frame)?,
+                })
+            }
+            _ => Err(MessageError::InvalidDatagramLength(bytes.len())),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{CockpitToLinkage, LinkageToCarburetor, Message};
+    use crate::{
+        CarburetorToLinkage, CockpitToLinkage, DecodeErrorCounts, FaultKind, Frame, FramedMessage,
+        LinkageToCarburetor, LinkageToCarburetorFrame, LinkageToCockpit, Message, MessageError,
+        MotorBatch, Sequenced, ServoTarget, Stamp, LINKAGE_TO_CARBURETOR_ENCODING,
+    };
 
     #[test]
     fn cockpit_to_linkage_try_from_bytes() {
-        let message = CockpitToLinkage::try_from([0x20, 0, 0, 0, 42, 43, 44, 45]).unwrap();
+        let message = CockpitToLinkage::try_from([0x21, 42, 1, 2, 191, 0, 0, 0]).unwrap();
 
         assert_eq!(
             message,
             CockpitToLinkage::GamepadInputEvent {
                 gamepad_id: 42,
-                event_type: 43,
-                control: 44,
-                value: 45,
+                event_type: 1,
+                control: 2,
+                value: -0.5,
             }
         )
     }
 
     #[test]
-    fn cockpit_to_linkage_try_from_bytes_wrong_unused_bytes() {
-        let result = CockpitToLinkage::try_from([0x20, 69, 69, 69, 42, 43, 44, 45]);
-        assert!(result.is_err());
+    fn cockpit_to_linkage_try_from_bytes_invalid_value() {
+        let result = CockpitToLinkage::try_from([0x21, 42, 1, 2, 127, 192, 0, 0]);
+        assert_eq!(
+            result,
+            Err(MessageError::InvalidFloat {
+                opcode: 0x21,
+                field: "value"
+            })
+        );
     }
 
     #[test]
     fn cockpit_to_linkage_try_from_bytes_wrong_instruction() {
-        let result = CockpitToLinkage::try_from([0x10, 0, 0, 0, 42, 43, 44, 45]);
-        assert!(result.is_err());
+        // The opcode of gamepad events that carried their value in a single byte.
+        let result = CockpitToLinkage::try_from([0x20, 0, 0, 0, 42, 43, 44, 45]);
+        assert_eq!(result, Err(MessageError::UnknownOpcode(0x20)));
+    }
+
+    #[test]
+    fn heartbeat_bytes() {
+        let bytes = CockpitToLinkage::Heartbeat.to_bytes();
+
+        assert_eq!(bytes, [0x22, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            CockpitToLinkage::try_from(bytes),
+            Ok(CockpitToLinkage::Heartbeat)
+        );
     }
 
     #[test]
     fn bytes_from_cockpit_to_linkage() {
         let bytes = CockpitToLinkage::GamepadInputEvent {
             gamepad_id: 42,
-            event_type: 43,
-            control: 44,
-            value: 45,
+            event_type: 1,
+            control: 2,
+            value: -0.5,
         }
         .to_bytes();
 
-        assert_eq!(bytes, [0x20, 0, 0, 0, 42, 43, 44, 45])
+        assert_eq!(bytes, [0x21, 42, 1, 2, 191, 0, 0, 0])
     }
 
     #[test]
@@ -162,6 +476,54 @@ mod tests {
         )
     }
 
+    #[test]
+    fn digital_output_round_trip() {
+        let message = LinkageToCarburetor::DigitalOutput {
+            pin: 3,
+            value: true,
+        };
+        let bytes = message.to_bytes();
+        assert_eq!(bytes, [0x41, 3, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(LinkageToCarburetor::try_from(bytes).unwrap(), message);
+        assert!(LinkageToCarburetor::try_from([0x41, 3, 2, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn estop_round_trip() {
+        for message in [LinkageToCarburetor::EStop, LinkageToCarburetor::ResetEStop] {
+            assert_eq!(
+                LinkageToCarburetor::try_from(message.to_bytes()).unwrap(),
+                message
+            );
+        }
+        assert!(LinkageToCarburetor::try_from([0x42, 1, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn servo_position_round_trip() {
+        let messages = [
+            LinkageToCarburetor::ServoPosition {
+                channel: 2,
+                target: ServoTarget::Degrees(90.0),
+            },
+            LinkageToCarburetor::ServoPosition {
+                channel: 2,
+                target: ServoTarget::Position(0.25),
+            },
+        ];
+        for message in messages {
+            assert_eq!(
+                LinkageToCarburetor::try_from(message.to_bytes()).unwrap(),
+                message
+            );
+        }
+        assert_eq!(
+            messages[1].to_bytes(),
+            [0x44, 2, 1, 0, 0x3e, 0x80, 0x00, 0x00]
+        );
+        assert!(LinkageToCarburetor::try_from([0x44, 2, 2, 0, 0, 0, 0, 0]).is_err());
+    }
+
     #[test]
     fn linkage_to_carburetor_from_bytes_wrong_instruction() {
         let result = LinkageToCarburetor::try_from([0x10, 1, 0, 0, 63, 49, 183, 23]);
@@ -184,4 +546,199 @@ mod tests {
 
         assert_eq!(bytes, [0x40, 1, 0, 0, 63, 49, 183, 23])
     }
+
+    #[test]
+    fn carburetor_to_linkage_round_trip() {
+        let messages = [
+            CarburetorToLinkage::InstructionAck {
+                instruction: 0x40,
+                channel: 1,
+            },
+            CarburetorToLinkage::ChannelStatus {
+                channel: 1,
+                arming: true,
+                pulse_width_us: 1500,
+                since_update_ms: 300,
+            },
+            CarburetorToLinkage::Fault {
+                channel: 1,
+                kind: FaultKind::OutOfRangeSpeed,
+            },
+            CarburetorToLinkage::DigitalInput {
+                pin: 2,
+                value: true,
+                timestamp_ms: 123_456,
+            },
+            CarburetorToLinkage::AnalogInput {
+                pin: 3,
+                value: 0.25,
+            },
+            CarburetorToLinkage::EStopStatus { latched: true },
+            CarburetorToLinkage::Echo {
+                sent_at_us: 0xdead_beef,
+            },
+        ];
+
+        for message in messages {
+            assert_eq!(
+                CarburetorToLinkage::try_from(message.to_bytes()),
+                Ok(message)
+            );
+        }
+    }
+
+    #[test]
+    fn bytes_from_carburetor_to_linkage_status() {
+        let bytes = CarburetorToLinkage::ChannelStatus {
+            channel: 1,
+            arming: false,
+            pulse_width_us: 1500,
+            since_update_ms: 300,
+        }
+        .to_bytes();
+
+        assert_eq!(bytes, [0x61, 1, 0, 0, 0x05, 0xdc, 0x01, 0x2c])
+    }
+
+    #[test]
+    fn carburetor_to_linkage_unknown_fault() {
+        let result = CarburetorToLinkage::try_from([0x62, 1, 42, 0, 0, 0, 0, 0]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn sequence_wraps_around() {
+        let sequenced = |sequence| Sequenced {
+            stamp: Stamp {
+                sequence,
+                sent_at_us: 0,
+                round_trip_us: 0,
+            },
+            message: LinkageToCarburetor::EStop,
+        };
+        assert!(sequenced(2).follows(1));
+        assert!(!sequenced(1).follows(1));
+        assert!(!sequenced(1).follows(2));
+        assert!(sequenced(0).follows(u32::MAX));
+        assert!(!sequenced(u32::MAX).follows(0));
+    }
+
+    #[test]
+    fn motor_batch_round_trip() {
+        let batch = MotorBatch {
+            speeds: vec![(0, 0.5), (1, -0.25)],
+        };
+        let frame = batch.to_frame();
+        assert_eq!(frame.tag, 0x45);
+        assert_eq!(frame.payload, [0, 0x3f, 0, 0, 0, 1, 0xbe, 0x80, 0, 0]);
+        assert_eq!(MotorBatch::from_frame(&frame).unwrap(), batch);
+        assert!(MotorBatch::from_frame(&Frame::new(0x45, [0, 0x3f, 0, 0])).is_err());
+
+        assert_eq!(
+            LinkageToCarburetorFrame::from_frame(&frame).unwrap(),
+            LinkageToCarburetorFrame::MotorBatch(batch)
+        );
+        let estop = LinkageToCarburetor::EStop.to_frame();
+        assert_eq!(
+            LinkageToCarburetorFrame::from_frame(&estop).unwrap(),
+            LinkageToCarburetorFrame::Message(LinkageToCarburetor::EStop)
+        );
+    }
+
+    #[test]
+    fn sequenced_frame_round_trip() {
+        let sequenced = Sequenced {
+            stamp: Stamp {
+                sequence: 7,
+                sent_at_us: 8,
+                round_trip_us: 9,
+            },
+            message: LinkageToCarburetorFrame::MotorBatch(MotorBatch {
+                speeds: vec![(2, 1.0)],
+            }),
+        };
+        let bytes = sequenced.encode(LINKAGE_TO_CARBURETOR_ENCODING).unwrap();
+        assert_eq!(
+            bytes,
+            [0x45, 0b10, 0, 5, 0, 0, 0, 7, 0, 0, 0, 8, 0, 0, 0, 9, 2, 0x3f, 0x80, 0, 0]
+        );
+        assert_eq!(
+            Sequenced::decode(&bytes, LINKAGE_TO_CARBURETOR_ENCODING),
+            Ok(sequenced)
+        );
+        assert_eq!(
+            Sequenced::<LinkageToCarburetorFrame>::decode(
+                &bytes[..bytes.len() - 1],
+                LINKAGE_TO_CARBURETOR_ENCODING
+            ),
+            Err(MessageError::InvalidDatagramLength(bytes.len() - 1))
+        );
+    }
+
+    #[test]
+    fn sequenced_needs_a_stamp() {
+        let bytes = LinkageToCarburetor::EStop
+            .to_frame()
+            .encode(LINKAGE_TO_CARBURETOR_ENCODING)
+            .unwrap();
+        assert_eq!(
+            Sequenced::<LinkageToCarburetorFrame>::decode(&bytes, LINKAGE_TO_CARBURETOR_ENCODING),
+            Err(MessageError::UnstampedDatagram)
+        );
+    }
+
+    #[test]
+    fn echo_bytes() {
+        let echo = LinkageToCockpit::Echo {
+            sent_at_us: 0x0102_0304,
+        };
+        assert_eq!(echo.to_bytes(), [0x30, 0, 0, 0, 1, 2, 3, 4]);
+        assert_eq!(LinkageToCockpit::try_from(echo.to_bytes()), Ok(echo));
+    }
+
+    #[test]
+    fn decode_errors_are_typed() {
+        let [nan_1, nan_2, nan_3, nan_4] = f32::NAN.to_be_bytes();
+        let errors = [
+            LinkageToCarburetor::try_from([0x10, 1, 0, 0, 0, 0, 0, 0]),
+            LinkageToCarburetor::try_from([0x40, 1, 69, 0, 0, 0, 0, 0]),
+            LinkageToCarburetor::try_from([0x41, 1, 2, 0, 0, 0, 0, 0]),
+            LinkageToCarburetor::try_from([0x40, 1, 0, 0, nan_1, nan_2, nan_3, nan_4]),
+        ]
+        .map(Result::unwrap_err);
+        assert_eq!(
+            errors,
+            [
+                MessageError::UnknownOpcode(0x10),
+                MessageError::ReservedBytes {
+                    opcode: 0x40,
+                    bytes: [0x40, 1, 69, 0, 0, 0, 0, 0]
+                },
+                MessageError::OutOfRange {
+                    opcode: 0x41,
+                    field: "value"
+                },
+                MessageError::InvalidFloat {
+                    opcode: 0x40,
+                    field: "speed"
+                },
+            ]
+        );
+
+        let batch = Frame::new(MotorBatch::OPCODE, [0, nan_1, nan_2, nan_3, nan_4]);
+        assert_eq!(
+            MotorBatch::from_frame(&batch),
+            Err(MessageError::InvalidFloat {
+                opcode: MotorBatch::OPCODE,
+                field: "speed"
+            })
+        );
+
+        let mut counts = DecodeErrorCounts::default();
+        for error in &errors {
+            assert_eq!(counts.record(error), 1);
+        }
+        assert_eq!(counts.record(&MessageError::UnknownOpcode(0x11)), 2);
+        assert_eq!(counts.total(), 5);
+    }
 }
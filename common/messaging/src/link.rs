@@ -0,0 +1,283 @@
+//! Measuring a link between two programs, from the [`Stamp`]s in its frames.
+//!
+//! The sender stamps every frame with a sequence number and the time it was sent. The receiver
+//! counts the messages that were dropped or reordered from the sequence numbers, and echoes the
+//! times back, from which the sender measures the round trip. The sender passes the round trip
+//! along in its stamps, so the receiver can estimate how long every message took to arrive.
+
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// The length of a [`Stamp`] in a frame.
+pub const STAMP_LEN: usize = 12;
+
+/// How much of every new sample goes into the smoothed round trip, as in RFC 6298.
+const ROUND_TRIP_GAIN: i64 = 8;
+
+/// How much of every new sample goes into the jitter and the mean offset, as in RFC 3550.
+const JITTER_GAIN: i64 = 16;
+
+/// How many sequence numbers up to the highest one received are remembered, to tell duplicates
+/// apart from frames that were reordered.
+const SEEN_WINDOW: u32 = u64::BITS;
+
+/// When and in which order a frame was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stamp {
+    /// Goes up by one for every stamped frame on a link, and wraps around.
+    pub sequence: u32,
+    /// When the frame was sent, in microseconds since the sender started measuring. Wraps around
+    /// after about 71 minutes.
+    pub sent_at_us: u32,
+    /// The round trip of the link as last measured by the sender, in microseconds, or 0 if it has
+    /// not been measured yet.
+    pub round_trip_us: u32,
+}
+
+impl Stamp {
+    /// Returns the stamp as three big-endian u32s.
+    pub fn to_bytes(&self) -> [u8; STAMP_LEN] {
+        let mut bytes = [0; STAMP_LEN];
+        bytes[0..4].copy_from_slice(&self.sequence.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.sent_at_us.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.round_trip_us.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; STAMP_LEN]) -> Self {
+        let [s1, s2, s3, s4, t1, t2, t3, t4, r1, r2, r3, r4] = bytes;
+        Self {
+            sequence: u32::from_be_bytes([s1, s2, s3, s4]),
+            sent_at_us: u32::from_be_bytes([t1, t2, t3, t4]),
+            round_trip_us: u32::from_be_bytes([r1, r2, r3, r4]),
+        }
+    }
+}
+
+/// What one end of a link measured. A sender only knows what it sent and the round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkStats {
+    /// The number of stamped frames sent.
+    pub sent: u64,
+    /// The number of stamped frames received, not counting duplicates.
+    pub received: u64,
+    /// The number of sequence numbers that were skipped and never arrived.
+    pub dropped: u64,
+    /// The number of frames that arrived after a frame that was sent later.
+    pub reordered: u64,
+    /// The smoothed round trip, in microseconds.
+    pub round_trip_us: Option<u32>,
+    /// The estimated time the last frame took to arrive, in microseconds: half the round trip,
+    /// corrected for how much sooner or later than usual it arrived.
+    pub one_way_us: Option<u32>,
+    /// How much the time frames take to arrive varies, in microseconds.
+    pub jitter_us: u32,
+}
+
+/// Stamps the frames sent over a link and measures their echoes, or measures the stamps of the
+/// frames received over it.
+#[derive(Debug, Clone)]
+pub struct LinkMonitor {
+    epoch: Instant,
+    next_sequence: u32,
+    stats: LinkStats,
+    /// The highest sequence number received.
+    highest: Option<u32>,
+    /// Which of the [`SEEN_WINDOW`] sequence numbers up to the highest one were received, with
+    /// bit `n` for the highest sequence number minus `n`.
+    seen: u64,
+    /// The difference between our clock and the clock of the sender for the last frame.
+    last_offset_us: Option<u32>,
+    /// The smoothed difference between our clock and the clock of the sender.
+    mean_offset_us: u32,
+}
+
+impl Default for LinkMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkMonitor {
+    /// Starts measuring, with the clock of this end at 0.
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            next_sequence: 0,
+            stats: LinkStats::default(),
+            highest: None,
+            seen: 0,
+            last_offset_us: None,
+            mean_offset_us: 0,
+        }
+    }
+
+    /// Returns the time on the clock of this end, in microseconds.
+    fn now_us(&self) -> u32 {
+        self.epoch.elapsed().as_micros() as u32
+    }
+
+    /// Returns the stamp for the next frame to send.
+    pub fn stamp(&mut self) -> Stamp {
+        let stamp = Stamp {
+            sequence: self.next_sequence,
+            sent_at_us: self.now_us(),
+            round_trip_us: self.stats.round_trip_us.unwrap_or_default(),
+        };
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.stats.sent += 1;
+        stamp
+    }
+
+    /// Measures the round trip of a frame that was stamped at `sent_at_us`, from its echo.
+    pub fn echoed(&mut self, sent_at_us: u32) {
+        let sample = self.now_us().wrapping_sub(sent_at_us);
+        self.stats.round_trip_us = Some(match self.stats.round_trip_us {
+            Some(smoothed) => smooth(smoothed, sample, ROUND_TRIP_GAIN),
+            None => sample,
+        });
+    }
+
+    /// Measures a frame that arrived with `stamp`. Duplicates are ignored, and so are frames that
+    /// are too far behind to tell whether they are.
+    pub fn received(&mut self, stamp: Stamp) {
+        match self.highest {
+            Some(highest) if (stamp.sequence.wrapping_sub(highest) as i32) <= 0 => {
+                let age = highest.wrapping_sub(stamp.sequence);
+                if age >= SEEN_WINDOW || self.seen & (1 << age) != 0 {
+                    return;
+                }
+                // It was counted as dropped when a later frame arrived first.
+                self.seen |= 1 << age;
+                self.stats.reordered += 1;
+                self.stats.dropped = self.stats.dropped.saturating_sub(1);
+            }
+            highest => {
+                let advance =
+                    highest.map_or(SEEN_WINDOW, |highest| stamp.sequence.wrapping_sub(highest));
+                self.stats.dropped += highest.map_or(0, |_| advance as u64 - 1);
+                self.seen = self.seen.checked_shl(advance).unwrap_or_default() | 1;
+                self.highest = Some(stamp.sequence);
+            }
+        }
+        self.stats.received += 1;
+
+        // The offset is the difference between the clocks plus the time the frame took, so how
+        // much it differs from the mean is how much sooner or later than usual the frame arrived.
+        let offset = self.now_us().wrapping_sub(stamp.sent_at_us);
+        let Some(last_offset) = self.last_offset_us.replace(offset) else {
+            self.mean_offset_us = offset;
+            self.update_one_way(stamp, 0);
+            return;
+        };
+        let transit_change = offset.wrapping_sub(last_offset) as i32;
+        self.stats.jitter_us = smooth(
+            self.stats.jitter_us,
+            transit_change.unsigned_abs(),
+            JITTER_GAIN,
+        );
+        self.mean_offset_us = smooth(self.mean_offset_us, offset, JITTER_GAIN);
+        self.update_one_way(stamp, offset.wrapping_sub(self.mean_offset_us) as i32);
+    }
+
+    fn update_one_way(&mut self, stamp: Stamp, deviation_us: i32) {
+        if stamp.round_trip_us != 0 {
+            self.stats.round_trip_us = Some(stamp.round_trip_us);
+            let one_way = (stamp.round_trip_us / 2) as i64 + deviation_us as i64;
+            self.stats.one_way_us = Some(one_way.max(0) as u32);
+        }
+    }
+
+    /// Returns what was measured so far.
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+}
+
+/// Moves `smoothed` by 1/`gain` of the way towards `sample`, on clocks that wrap around.
+fn smooth(smoothed: u32, sample: u32, gain: i64) -> u32 {
+    let difference = sample.wrapping_sub(smoothed) as i32 as i64;
+    smoothed.wrapping_add_signed((difference / gain) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{LinkMonitor, Stamp, STAMP_LEN};
+
+    fn stamp(sequence: u32) -> Stamp {
+        Stamp {
+            sequence,
+            sent_at_us: 0,
+            round_trip_us: 0,
+        }
+    }
+
+    #[test]
+    fn stamp_bytes() {
+        let stamp = Stamp {
+            sequence: 1,
+            sent_at_us: 0x0203_0405,
+            round_trip_us: 6,
+        };
+        let bytes: [u8; STAMP_LEN] = [0, 0, 0, 1, 2, 3, 4, 5, 0, 0, 0, 6];
+        assert_eq!(stamp.to_bytes(), bytes);
+        assert_eq!(Stamp::from_bytes(bytes), stamp);
+    }
+
+    #[test]
+    fn counts_dropped_and_reordered_frames() {
+        let mut monitor = LinkMonitor::new();
+        for sequence in [u32::MAX - 1, u32::MAX, 2, 1, 4, 4, 1, u32::MAX] {
+            monitor.received(stamp(sequence));
+        }
+
+        let stats = monitor.stats();
+        // The second 4, 1 and u32::MAX are duplicates.
+        assert_eq!(stats.received, 5);
+        // 0 and 3 never arrived, and 1 arrived after 2.
+        assert_eq!(stats.dropped, 2);
+        assert_eq!(stats.reordered, 1);
+        assert_eq!(stats.one_way_us, None);
+    }
+
+    #[test]
+    fn ignores_frames_too_far_behind() {
+        let mut monitor = LinkMonitor::new();
+        for sequence in [100, 200, 150, 100, 150] {
+            monitor.received(stamp(sequence));
+        }
+
+        let stats = monitor.stats();
+        // 100 cannot be told apart from a duplicate anymore, while the first 150 still can.
+        assert_eq!(stats.received, 3);
+        assert_eq!(stats.dropped, 98);
+        assert_eq!(stats.reordered, 1);
+    }
+
+    #[test]
+    fn measures_round_trip_and_one_way() {
+        let mut sender = LinkMonitor::new();
+        let first = sender.stamp();
+        assert_eq!((first.sequence, first.round_trip_us), (0, 0));
+        thread::sleep(Duration::from_millis(1));
+        sender.echoed(first.sent_at_us);
+        let round_trip = sender.stats().round_trip_us.unwrap();
+        assert!(round_trip >= 1_000);
+
+        let second = sender.stamp();
+        assert_eq!(second.sequence, 1);
+        assert_eq!(second.round_trip_us, round_trip);
+        assert_eq!(sender.stats().sent, 2);
+
+        let mut receiver = LinkMonitor::new();
+        receiver.received(second);
+        let stats = receiver.stats();
+        assert_eq!(stats.round_trip_us, Some(round_trip));
+        assert_eq!(stats.one_way_us, Some(round_trip / 2));
+    }
+}
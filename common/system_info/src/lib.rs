@@ -1,4 +1,8 @@
+use config::AddressPort;
+use messaging::{DecodeErrorCounts, Handshake, LinkStats};
 use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
 use std::path::Path;
 use std::time::Duration;
 use std::{process::Command, thread};
@@ -9,6 +13,9 @@ pub struct ServiceInfo {
     pub carburetor_status: bool,
     pub gauge_status: bool,
     pub linkage_socket_status: bool,
+    /// Whether the emergency stop of Carburetor is latched.
+    #[serde(default)]
+    pub carburetor_estop_latched: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -37,6 +44,64 @@ pub struct Memory {
     pub mem: Option<Mem>,
 }
 
+/// How long Carburetor took to write pulse widths to its outputs.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// The upper bounds of every bucket in µs, except the last one, which has no upper bound.
+    pub bounds_us: Vec<u64>,
+    /// The number of writes in every bucket. Has one more element than `bounds_us`.
+    pub counts: Vec<u64>,
+}
+
+/// A pulse width a channel of Carburetor started outputting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSample {
+    /// Milliseconds since Carburetor started.
+    pub at_ms: u64,
+    pub pulse_width_us: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMetrics {
+    pub channel: u8,
+    /// Instructions received for this channel in the last second.
+    pub messages_per_second: u32,
+    /// Instructions received for this channel since Carburetor started.
+    pub messages: u64,
+    /// The most recent changes of the output, oldest first.
+    pub history: Vec<OutputSample>,
+}
+
+/// Runtime metrics of Carburetor, served on its local metrics endpoint.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct CarburetorMetrics {
+    pub uptime_ms: u64,
+    /// Connections from Linkage accepted since Carburetor started.
+    pub connections: u64,
+    /// Whether Linkage is connected right now.
+    pub connected: bool,
+    /// Messages received from Linkage in the last second.
+    pub messages_per_second: u32,
+    /// Frames that could not be decoded into a message.
+    pub malformed_frames: u64,
+    /// Why frames and handshakes from Linkage could not be decoded.
+    #[serde(default)]
+    pub decode_errors: DecodeErrorCounts,
+    /// Messages that were decoded, but could not be carried out.
+    pub rejected_instructions: u64,
+    pub apply_latency: LatencyHistogram,
+    pub channels: Vec<ChannelMetrics>,
+    /// The handshake Carburetor introduces itself with.
+    #[serde(default)]
+    pub handshake: Option<Handshake>,
+    /// The handshake of the Linkage that connected last.
+    #[serde(default)]
+    pub linkage_handshake: Option<Handshake>,
+    /// What Carburetor measured of the frames from the Linkage that is connected.
+    #[serde(default)]
+    pub linkage_link: LinkStats,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SystemInfo {
     pub cpu: Option<Cpu>,
@@ -44,16 +109,36 @@ pub struct SystemInfo {
     pub uptime: Option<u64>,
     pub service_info: ServiceInfo,
     pub robot_code_exists: bool,
+    /// The metrics of Carburetor, if it is running.
+    #[serde(default)]
+    pub carburetor_metrics: Option<CarburetorMetrics>,
+    /// The handshakes of the linkage programs on the robot, which tell their versions: the one of
+    /// Gauge, and the ones of Carburetor and the Linkage that connected to it last, if known.
+    #[serde(default)]
+    pub components: Vec<Handshake>,
 }
 
 impl SystemInfo {
-    pub fn new(system: &System, delay: Duration) -> Self {
+    pub fn new(
+        system: &System,
+        delay: Duration,
+        carburetor_metrics_port: AddressPort,
+        gauge: Handshake,
+    ) -> Self {
         let service_info = ServiceInfo {
             carburetor_status: service_is_active("carburetor.service"),
             gauge_status: service_is_active("gauge.service"),
             linkage_socket_status: service_is_active("linkage.socket"),
+            carburetor_estop_latched: carburetor_estop_latched(),
         };
 
+        let carburetor_metrics = get_carburetor_metrics(carburetor_metrics_port);
+        let mut components = vec![gauge];
+        if let Some(metrics) = &carburetor_metrics {
+            components.extend(metrics.handshake);
+            components.extend(metrics.linkage_handshake);
+        }
+
         Self {
             cpu: get_cpu(system, delay),
             memory: Memory {
@@ -63,6 +148,8 @@ impl SystemInfo {
             uptime: get_uptime(system),
             service_info,
             robot_code_exists: robot_code_exists(),
+            carburetor_metrics,
+            components,
         }
     }
 }
@@ -71,6 +158,44 @@ fn robot_code_exists() -> bool {
     Path::new("/home/linkage/robot_code/main").exists()
 }
 
+fn carburetor_estop_latched() -> bool {
+    match config::carburetor_estop_path() {
+        Ok(path) => path.exists(),
+        Err(error) => {
+            println!(
+                "Failed to find the Carburetor emergency stop latch: {}",
+                error
+            );
+            false
+        }
+    }
+}
+
+fn get_carburetor_metrics(port: AddressPort) -> Option<CarburetorMetrics> {
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, u16::try_from(port).ok()?));
+    // Carburetor not running is not an error.
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT).ok()?;
+
+    let mut json = String::new();
+    let read = stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| stream.read_to_string(&mut json));
+    if let Err(error) = read {
+        println!("Failed to read Carburetor metrics: {}", error);
+        return None;
+    }
+
+    match serde_json::from_str(&json) {
+        Ok(metrics) => Some(metrics),
+        Err(error) => {
+            println!("Failed to decode Carburetor metrics: {}", error);
+            None
+        }
+    }
+}
+
 fn service_is_active(service_name: &str) -> bool {
     let exit_status = Command::new("systemctl")
         .args(["is-active", "--quiet", service_name])
@@ -163,9 +288,11 @@ pub fn decode_system_info_from_string(data: String) -> SystemInfo {
 
 #[cfg(test)]
 mod tests {
+    use messaging::{Component, DecodeErrorCounts, Handshake, LinkStats};
+
     use crate::{
-        decode_system_info_from_string, encode_system_info, Cpu, Mem, Memory, ServiceInfo, Swap,
-        SystemInfo,
+        decode_system_info_from_string, encode_system_info, CarburetorMetrics, ChannelMetrics, Cpu,
+        LatencyHistogram, Mem, Memory, OutputSample, ServiceInfo, Swap, SystemInfo,
     };
 
     #[test]
@@ -192,8 +319,46 @@ mod tests {
                 carburetor_status: true,
                 gauge_status: true,
                 linkage_socket_status: true,
+                carburetor_estop_latched: true,
             },
             robot_code_exists: true,
+            carburetor_metrics: Some(CarburetorMetrics {
+                uptime_ms: 51,
+                connections: 52,
+                connected: true,
+                messages_per_second: 53,
+                malformed_frames: 54,
+                decode_errors: DecodeErrorCounts {
+                    unknown_opcode: 54,
+                    ..Default::default()
+                },
+                rejected_instructions: 55,
+                apply_latency: LatencyHistogram {
+                    bounds_us: vec![56],
+                    counts: vec![57, 58],
+                },
+                channels: vec![ChannelMetrics {
+                    channel: 59,
+                    messages_per_second: 60,
+                    messages: 61,
+                    history: vec![OutputSample {
+                        at_ms: 62,
+                        pulse_width_us: 63,
+                    }],
+                }],
+                handshake: Some(Handshake::new(Component::Carburetor, "0.1.2")),
+                linkage_handshake: Some(Handshake::new(Component::LinkageLib, "0.1.0")),
+                linkage_link: LinkStats {
+                    sent: 0,
+                    received: 64,
+                    dropped: 65,
+                    reordered: 66,
+                    round_trip_us: Some(67),
+                    one_way_us: Some(68),
+                    jitter_us: 69,
+                },
+            }),
+            components: vec![Handshake::new(Component::Gauge, "0.1.2")],
         };
 
         let encoded = encode_system_info(&mock_system_info);
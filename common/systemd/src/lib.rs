@@ -0,0 +1,254 @@
+//! Running as a systemd service: taking over the sockets systemd opened for the service (socket
+//! activation), and telling systemd how the service is doing (`sd_notify`).
+//!
+//! Everything here does nothing when the process was not started by systemd, so it can still be
+//! started by hand.
+
+use std::env;
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{SocketAddr, UnixDatagram, UnixListener};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The first file descriptor passed with socket activation.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Whether the sockets passed with socket activation were taken already.
+static LISTEN_FDS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Takes the sockets systemd passed to this process, in the order they are listed in the socket
+/// unit.
+///
+/// Only the first call takes them, and later calls return none. The environment variables that
+/// describe them are left alone, as changing the environment is unsound once other threads run.
+/// Child processes do not mistake them for their own, as they are only meant for `LISTEN_PID`.
+pub fn take_listen_fds() -> Vec<OwnedFd> {
+    if LISTEN_FDS_TAKEN.swap(true, Ordering::SeqCst) {
+        return Vec::new();
+    }
+
+    let count = listen_fds_count(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        process::id(),
+    );
+    (0..count)
+        // SAFETY: systemd passes `count` open file descriptors from `LISTEN_FDS_START` on to this
+        // process, and `LISTEN_FDS_TAKEN` makes sure they are only taken once.
+        .map(|n| unsafe { OwnedFd::from_raw_fd(LISTEN_FDS_START + n as RawFd) })
+        .collect()
+}
+
+/// Returns the number of sockets passed to the process with id `own_pid`, according to the
+/// `LISTEN_PID` and `LISTEN_FDS` environment variables.
+fn listen_fds_count(listen_pid: Option<&str>, listen_fds: Option<&str>, own_pid: u32) -> usize {
+    // The sockets may have been meant for a parent process.
+    if listen_pid.and_then(|pid| pid.parse().ok()) != Some(own_pid) {
+        return 0;
+    }
+
+    listen_fds.and_then(|fds| fds.parse().ok()).unwrap_or(0)
+}
+
+/// A listening socket passed by systemd.
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl From<OwnedFd> for Listener {
+    fn from(fd: OwnedFd) -> Self {
+        let listener = UnixListener::from(fd);
+        // Only succeeds for Unix sockets.
+        match listener.local_addr() {
+            Ok(_) => Self::Unix(listener),
+            Err(_) => Self::Tcp(TcpListener::from(OwnedFd::from(listener))),
+        }
+    }
+}
+
+/// Takes the first listening socket systemd passed to this process, if any.
+pub fn take_listener() -> Option<Listener> {
+    take_listen_fds().into_iter().next().map(Listener::from)
+}
+
+/// Tells systemd how the service is doing, if it asked for that.
+#[derive(Debug)]
+pub struct Notifier {
+    socket: Option<(UnixDatagram, SocketAddr)>,
+    /// How often the watchdog has to be fed, if systemd watches the service.
+    watchdog_interval: Option<Duration>,
+    last_fed: Mutex<Option<Instant>>,
+}
+
+impl Notifier {
+    /// Creates a notifier for the socket in `NOTIFY_SOCKET`, that feeds the watchdog if
+    /// `WATCHDOG_USEC` is set for this process.
+    pub fn from_env() -> Self {
+        let watchdog_timeout = env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse().ok())
+            .map(Duration::from_micros)
+            .filter(|_| match env::var("WATCHDOG_PID") {
+                Ok(pid) => pid.parse() == Ok(process::id()),
+                Err(_) => true,
+            });
+
+        match env::var("NOTIFY_SOCKET") {
+            Ok(path) => Self::new(&path, watchdog_timeout),
+            Err(_) => Self::disabled(),
+        }
+    }
+
+    /// Creates a notifier for the socket at `path`, which is in the abstract namespace if it starts
+    /// with `@`. If `watchdog_timeout` is given, systemd expects the watchdog to be fed within it.
+    pub fn new(path: &str, watchdog_timeout: Option<Duration>) -> Self {
+        let socket = UnixDatagram::unbound().and_then(|socket| Ok((socket, address(path)?)));
+        match socket {
+            Ok(socket) => Self {
+                socket: Some(socket),
+                // Feeding at half the timeout leaves room for the main loop to be late.
+                watchdog_interval: watchdog_timeout.map(|timeout| timeout / 2),
+                last_fed: Mutex::default(),
+            },
+            Err(error) => {
+                log::warn!("Cannot notify systemd on {path:?}: {error}");
+                Self::disabled()
+            }
+        }
+    }
+
+    /// Creates a notifier that does nothing.
+    pub fn disabled() -> Self {
+        Self {
+            socket: None,
+            watchdog_interval: None,
+            last_fed: Mutex::default(),
+        }
+    }
+
+    /// Tells systemd that the service has started up.
+    pub fn ready(&self) {
+        self.notify("READY=1");
+    }
+
+    /// Tells systemd that the service is shutting down.
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    /// Tells systemd that the service is still alive, so it does not get restarted. Meant to be
+    /// called every time around the main loop: it only notifies systemd as often as needed.
+    pub fn feed_watchdog(&self) {
+        let Some(interval) = self.watchdog_interval else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut last_fed = self.last_fed.lock().unwrap();
+        if last_fed.is_some_and(|last_fed| now.duration_since(last_fed) < interval) {
+            return;
+        }
+
+        *last_fed = Some(now);
+        self.notify("WATCHDOG=1");
+    }
+
+    fn notify(&self, state: &str) {
+        let Some((socket, address)) = &self.socket else {
+            return;
+        };
+
+        if let Err(error) = socket.send_to_addr(state.as_bytes(), address) {
+            log::warn!("Failed to notify systemd of {state}: {error}");
+        }
+    }
+}
+
+fn address(path: &str) -> std::io::Result<SocketAddr> {
+    match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)
+        }
+        _ => SocketAddr::from_pathname(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::os::fd::OwnedFd;
+    use std::os::unix::net::{UnixDatagram, UnixListener};
+    use std::time::Duration;
+    use std::{env, fs, process};
+
+    use super::{listen_fds_count, Listener, Notifier};
+
+    #[test]
+    fn counts_sockets_for_this_process() {
+        assert_eq!(listen_fds_count(Some("42"), Some("2"), 42), 2);
+        assert_eq!(listen_fds_count(Some("41"), Some("2"), 42), 0);
+        assert_eq!(listen_fds_count(None, Some("2"), 42), 0);
+        assert_eq!(listen_fds_count(Some("42"), None, 42), 0);
+        assert_eq!(listen_fds_count(Some("42"), Some("two"), 42), 0);
+    }
+
+    #[test]
+    fn recognises_listeners() {
+        let tcp = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        assert!(matches!(
+            Listener::from(OwnedFd::from(tcp)),
+            Listener::Tcp(_)
+        ));
+
+        let path = env::temp_dir().join(format!("systemd-test-{}.sock", process::id()));
+        let unix = UnixListener::bind(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Listener::from(OwnedFd::from(unix)),
+            Listener::Unix(_)
+        ));
+    }
+
+    #[test]
+    fn notifies_fake_systemd() {
+        let path = env::temp_dir().join(format!("systemd-test-{}-notify", process::id()));
+        let systemd = UnixDatagram::bind(&path).unwrap();
+        systemd.set_nonblocking(true).unwrap();
+        let notifier = Notifier::new(path.to_str().unwrap(), Some(Duration::from_secs(10)));
+
+        let mut buffer = [0; 64];
+        let mut received = || match systemd.recv(&mut buffer) {
+            Ok(length) => Some(String::from_utf8_lossy(&buffer[..length]).into_owned()),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => None,
+            Err(error) => panic!("{error}"),
+        };
+
+        notifier.ready();
+        assert_eq!(received().as_deref(), Some("READY=1"));
+        notifier.feed_watchdog();
+        assert_eq!(received().as_deref(), Some("WATCHDOG=1"));
+        // It has not been half the timeout since the last time.
+        notifier.feed_watchdog();
+        assert_eq!(received(), None);
+        notifier.stopping();
+        assert_eq!(received().as_deref(), Some("STOPPING=1"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn disabled_does_nothing() {
+        let notifier = Notifier::disabled();
+        notifier.ready();
+        notifier.feed_watchdog();
+        notifier.stopping();
+    }
+}
@@ -1,4 +1,5 @@
 use config::AddressPort;
+use messaging::{Component, Handshake};
 use std::io::{ErrorKind, Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -11,24 +12,37 @@ const UPDATE_INTERVAL_MILLIS: u64 = 500;
 
 pub struct Gauge {
     port: AddressPort,
+    carburetor_metrics_port: AddressPort,
 }
 
 impl Gauge {
-    pub fn new(port: AddressPort) -> Self {
-        Self { port }
+    pub fn new(port: AddressPort, carburetor_metrics_port: AddressPort) -> Self {
+        Self {
+            port,
+            carburetor_metrics_port,
+        }
     }
 
     pub fn start(&self) {
         let (tx, rx) = crossbeam::channel::unbounded();
         let system = System::new();
         let client_count = Arc::new(AtomicUsize::new(0));
+        let carburetor_metrics_port = self.carburetor_metrics_port;
+        let notifier = Arc::new(systemd::Notifier::from_env());
 
         // Continuously the updated system information over the channel.
         std::thread::spawn({
             let client_count = Arc::clone(&client_count);
+            let notifier = Arc::clone(&notifier);
             move || loop {
-                let system_info =
-                    SystemInfo::new(&system, Duration::from_millis(UPDATE_INTERVAL_MILLIS));
+                notifier.feed_watchdog();
+
+                let system_info = SystemInfo::new(
+                    &system,
+                    Duration::from_millis(UPDATE_INTERVAL_MILLIS),
+                    carburetor_metrics_port,
+                    Handshake::new(Component::Gauge, env!("CARGO_PKG_VERSION")),
+                );
 
                 if client_count.load(Ordering::Relaxed) == 0 {
                     continue;
@@ -38,8 +52,14 @@ impl Gauge {
             }
         });
 
-        // Start listening for clients (Cockpit).
-        let listener = TcpListener::bind(format!("0.0.0.0:{}", self.port)).unwrap();
+        // Start listening for clients (Cockpit), on the socket systemd opened for us if there is one.
+        let listener = match systemd::take_listener() {
+            Some(systemd::Listener::Tcp(listener)) => listener,
+            Some(systemd::Listener::Unix(_)) => panic!("Gauge can only listen on a TCP socket"),
+            None => TcpListener::bind(format!("0.0.0.0:{}", self.port)).unwrap(),
+        };
+        notifier.ready();
+
         for stream in listener.incoming() {
             let rx = rx.clone();
             let stream = stream.unwrap();
@@ -2,6 +2,6 @@ use gauge::Gauge;
 
 fn main() {
     let config = config::config().unwrap();
-    let gauge = Gauge::new(config.gauge().port());
+    let gauge = Gauge::new(config.gauge().port(), config.carburetor().metrics_port());
     gauge.start();
 }
@@ -12,7 +12,9 @@ readonly LINKAGE_PATH=${LINKAGE_HOME}/linkage
 
 readonly LINKAGE_SYSTEMD_SOCKET=${LINKAGE_PATH}/lib/linkage.socket
 readonly LINKAGE_SYSTEMD_SOCKET_SERVICE=${LINKAGE_PATH}/lib/linkage@.service
+readonly CARBURETOR_SOCKET=${LINKAGE_PATH}/carburetor/carburetor.socket
 readonly CARBURETOR_SERVICE=${LINKAGE_PATH}/carburetor/carburetor.service
+readonly GAUGE_SOCKET=${LINKAGE_PATH}/gauge/gauge.socket
 readonly GAUGE_SERVICE=${LINKAGE_PATH}/gauge/gauge.service
 
 readonly CARBURETOR_PATH=${LINKAGE_HOME}/carburetor
@@ -40,14 +42,20 @@ sudo chmod +x ${GAUGE_PATH}
 # Setup services
 sudo cp ${LINKAGE_SYSTEMD_SOCKET} /etc/systemd/system/
 sudo cp ${LINKAGE_SYSTEMD_SOCKET_SERVICE} /etc/systemd/system/
+sudo cp ${CARBURETOR_SOCKET} /etc/systemd/system/
 sudo cp ${CARBURETOR_SERVICE} /etc/systemd/system/
+sudo cp ${GAUGE_SOCKET} /etc/systemd/system/
 sudo cp ${GAUGE_SERVICE} /etc/systemd/system/
 
 sudo systemctl daemon-reload
 sudo systemctl enable linkage.socket
 sudo systemctl restart linkage.socket
+sudo systemctl enable carburetor.socket
+sudo systemctl restart carburetor.socket
 sudo systemctl enable carburetor.service
 sudo systemctl restart carburetor.service
+sudo systemctl enable gauge.socket
+sudo systemctl restart gauge.socket
 sudo systemctl enable gauge.service
 sudo systemctl restart gauge.service
 
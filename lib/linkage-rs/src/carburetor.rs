@@ -1,24 +1,140 @@
-use std::io::{self, Write};
-use std::net::TcpStream;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::os::unix::net::UnixStream;
 use std::sync::mpsc::Receiver;
 
-use config::Address;
-use messaging::{Bytes, LinkageToCarburetor};
+use config::{Address, Transport};
+use messaging::{
+    Bytes, CarburetorToLinkage, Component, FrameWriter, FramedMessage, Handshake,
+    LinkageToCarburetor, LinkageToCarburetorFrame, Sequenced, Stamp,
+    LINKAGE_TO_CARBURETOR_ENCODING,
+};
+
+use crate::state::RobotStateHandle;
+
+/// A frame for Carburetor, and how it must be delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Outgoing {
+    pub(crate) frame: LinkageToCarburetorFrame,
+    /// Whether the frame is sent over the stream even with the udp transport, so it cannot be lost.
+    pub(crate) reliable: bool,
+}
+
+impl Outgoing {
+    /// Returns `frame`, which is sent as a datagram if it holds motor speeds, as a lost one is made
+    /// up for by the next.
+    pub(crate) fn new(frame: LinkageToCarburetorFrame) -> Self {
+        let is_speed = matches!(
+            frame,
+            LinkageToCarburetorFrame::MotorBatch(_)
+                | LinkageToCarburetorFrame::Message(LinkageToCarburetor::MotorInstruction { .. })
+        );
+        Self {
+            frame,
+            reliable: !is_speed,
+        }
+    }
+
+    /// Returns `frame`, which is always sent over the stream, like speeds that nothing follows up
+    /// on.
+    pub(crate) fn reliable(frame: LinkageToCarburetorFrame) -> Self {
+        Self {
+            frame,
+            reliable: true,
+        }
+    }
+}
+
+/// Where messages for Carburetor are written to.
+struct MessageSink {
+    stream: FrameWriter<Box<dyn Write + Send>>,
+    /// With the udp transport, where motor speeds are sent instead of the stream.
+    datagrams: Option<UdpSocket>,
+}
+
+impl MessageSink {
+    fn new(stream: impl Write + Send + 'static, datagrams: Option<UdpSocket>) -> Self {
+        Self {
+            stream: FrameWriter::new(Box::new(stream), LINKAGE_TO_CARBURETOR_ENCODING),
+            datagrams,
+        }
+    }
+
+    /// Sends `message` to Carburetor. Unless it must be reliable, it goes out as a datagram if
+    /// there are any. Everything else, like the emergency stop, is sent over the stream, so it
+    /// cannot be lost. Carburetor orders the datagrams by the sequence number of their `stamp`.
+    fn send(&mut self, message: Outgoing, stamp: Stamp) -> io::Result<()> {
+        let Outgoing { frame, reliable } = message;
+        match &self.datagrams {
+            Some(socket) if !reliable => {
+                let bytes = Sequenced {
+                    stamp,
+                    message: frame,
+                }
+                .encode(LINKAGE_TO_CARBURETOR_ENCODING)
+                .map_err(|error| io::Error::new(ErrorKind::InvalidInput, error))?;
+                socket.send(&bytes)?;
+            }
+            _ => self
+                .stream
+                .write_frame(&frame.to_frame().with_stamp(stamp))?,
+        }
+        Ok(())
+    }
+}
 
 pub(crate) fn open_connection(
-    message_receiver: Receiver<LinkageToCarburetor>,
+    message_receiver: Receiver<Outgoing>,
     address: &Address,
+    transport: Transport,
+    state: RobotStateHandle,
 ) -> io::Result<()> {
-    let mut stream = TcpStream::connect(address.to_string())?;
+    let (stream, mut sink): (Box<dyn Read + Send>, _) = match address {
+        Address::Inet { .. } => {
+            let mut stream = TcpStream::connect(address.to_string())?;
+            handshake(&mut stream)?;
+            let datagrams = match transport {
+                Transport::Tcp => None,
+                Transport::Udp => {
+                    // Carburetor only accepts datagrams from the host that is connected over TCP,
+                    // so they are sent from the same address.
+                    let socket = UdpSocket::bind(SocketAddr::new(stream.local_addr()?.ip(), 0))?;
+                    socket.connect(stream.peer_addr()?)?;
+                    log::info!("Sending motor speeds to Carburetor as datagrams.");
+                    Some(socket)
+                }
+            };
+            let sink = MessageSink::new(stream.try_clone()?, datagrams);
+            (Box::new(stream), sink)
+        }
+        Address::Unix { path } => {
+            if transport == Transport::Udp {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "the udp transport needs a TCP address for Carburetor, not a Unix socket",
+                ));
+            }
+
+            let mut stream = UnixStream::connect(path)?;
+            handshake(&mut stream)?;
+            let sink = MessageSink::new(stream.try_clone()?, None);
+            (Box::new(stream), sink)
+        }
+    };
 
     log::info!("Opened connection with Carburetor on '{address}'");
 
+    std::thread::spawn({
+        let state = state.clone();
+        move || handle_carburetor_messages(stream, state)
+    });
+
     std::thread::spawn(move || loop {
         match message_receiver.recv() {
             Ok(message) => {
-                let bytes: Bytes = message.into();
-                if let Err(error) = stream.write(&bytes) {
-                    log::error!("Failed to write message to Carburetor stream: {error}");
+                let stamp = state.lock().unwrap().carburetor_status.stamp();
+                if let Err(error) = sink.send(message, stamp) {
+                    log::error!("Failed to send message to Carburetor: {error}");
                 }
             }
             Err(error) => log::error!("Failed to receive LinkageToCarburetor message: {error}"),
@@ -27,3 +143,117 @@ pub(crate) fn open_connection(
 
     Ok(())
 }
+
+/// Exchanges handshakes with Carburetor, refusing to talk to it if it speaks another protocol
+/// version.
+fn handshake(stream: &mut (impl Read + Write)) -> io::Result<()> {
+    let carburetor = Handshake::new(Component::LinkageLib, env!("CARGO_PKG_VERSION"))
+        .exchange(stream, Component::Carburetor)?;
+    log::info!("Connected to {carburetor}.");
+    Ok(())
+}
+
+fn handle_carburetor_messages(mut stream: impl Read, state: RobotStateHandle) {
+    let mut message_bytes = Bytes::default();
+    loop {
+        match stream.read_exact(&mut message_bytes) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                log::error!("Carburetor closed the connection.");
+                break;
+            }
+            Err(error) => {
+                log::error!("Failed to read message from Carburetor stream: {error}");
+                break;
+            }
+        }
+
+        match CarburetorToLinkage::try_from(message_bytes) {
+            Ok(message) => state
+                .lock()
+                .unwrap()
+                .carburetor_status
+                .handle_carburetor_message(message),
+            Err(error) => {
+                let count = state
+                    .lock()
+                    .unwrap()
+                    .carburetor_status
+                    .decode_failed(&error);
+                log::error!(
+                    "Failed to decode message {message_bytes:?} from Carburetor: {error} ({count} like it so far)."
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use messaging::{
+        FrameReader, LinkageToCarburetorFrame, MotorBatch, Sequenced, Stamp,
+        LINKAGE_TO_CARBURETOR_ENCODING,
+    };
+
+    use super::MessageSink;
+    use crate::state::RobotState;
+
+    #[test]
+    fn disables_over_the_stream_with_datagrams() {
+        let carburetor = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        carburetor
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let datagrams = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        datagrams.connect(carburetor.local_addr().unwrap()).unwrap();
+        let (stream, carburetor_stream) = UnixStream::pair().unwrap();
+        let mut sink = MessageSink::new(stream, Some(datagrams));
+
+        let (sender, receiver) = channel();
+        let mut state = RobotState::new(sender, BTreeMap::new());
+        state.set_motor_speed(0, 0.5);
+        state.flush_motor_speeds();
+        state.disable();
+        drop(state);
+        for (sequence, message) in receiver.into_iter().enumerate() {
+            let stamp = Stamp {
+                sequence: sequence as u32,
+                sent_at_us: 0,
+                round_trip_us: 0,
+            };
+            sink.send(message, stamp).unwrap();
+        }
+        drop(sink);
+
+        let mut buffer = [0; 64];
+        let length = carburetor.recv(&mut buffer).unwrap();
+        let speeds = Sequenced::<LinkageToCarburetorFrame>::decode(
+            &buffer[..length],
+            LINKAGE_TO_CARBURETOR_ENCODING,
+        )
+        .unwrap();
+        assert_eq!(
+            speeds.message,
+            MotorBatch {
+                speeds: vec![(0, 0.5)]
+            }
+            .into()
+        );
+        assert!(carburetor.recv(&mut buffer).is_err());
+
+        let mut reader = FrameReader::new(carburetor_stream, LINKAGE_TO_CARBURETOR_ENCODING);
+        assert_eq!(
+            reader.read_message::<LinkageToCarburetorFrame>().unwrap(),
+            MotorBatch {
+                speeds: vec![(0, 0.0)]
+            }
+            .into()
+        );
+    }
+}
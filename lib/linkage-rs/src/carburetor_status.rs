@@ -0,0 +1,193 @@
+//! Feedback from Carburetor about the instructions it received, the state of its channels and the
+//! values of its digital and analog inputs.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use messaging::{
+    CarburetorToLinkage, DecodeErrorCounts, LinkMonitor, LinkStats, MessageError, Stamp,
+};
+pub use messaging::{FaultKind, CONNECTION_FAULT_CHANNEL};
+
+/// The maximum number of unread faults that are kept around.
+const MAX_FAULTS: usize = 64;
+
+/// The state of a Carburetor channel, as last reported by Carburetor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelStatus {
+    /// Whether the channel is running its arming sequence, during which Carburetor does not apply
+    /// instructions to it.
+    pub arming: bool,
+    /// The pulse width that is being output.
+    pub pulse_width: Duration,
+    /// The time since the output of the channel last changed, at the moment it was reported.
+    pub since_update: Duration,
+    /// When this status was received.
+    pub received_at: Instant,
+}
+
+/// The value of a Carburetor digital input, as last reported by Carburetor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitalInputStatus {
+    /// Whether the input is high.
+    pub value: bool,
+    /// When the value last changed, relative to when Carburetor started.
+    pub changed_at: Duration,
+    /// When this status was received.
+    pub received_at: Instant,
+}
+
+/// The value of a Carburetor analog input, as last reported by Carburetor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalogInputStatus {
+    /// The reading as a fraction of the full scale of the ADC, within 0.0..=1.0.
+    pub value: f32,
+    /// When this status was received.
+    pub received_at: Instant,
+}
+
+/// A fault reported by Carburetor, for example because it rejected an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CarburetorFault {
+    /// The channel, or digital output pin, the fault is about. [`CONNECTION_FAULT_CHANNEL`] for
+    /// faults about the connection, like a [`FaultKind::MalformedFrame`].
+    pub channel: u8,
+    pub kind: FaultKind,
+    /// When this fault was received.
+    pub received_at: Instant,
+}
+
+/// Keeps track of everything Carburetor reported.
+#[derive(Debug, Default)]
+pub struct CarburetorStatus {
+    channels: HashMap<u8, ChannelStatus>,
+    acknowledged: HashMap<u8, Instant>,
+    digital_inputs: HashMap<u8, DigitalInputStatus>,
+    analog_inputs: HashMap<u8, AnalogInputStatus>,
+    estop_latched: Option<bool>,
+    faults: VecDeque<CarburetorFault>,
+    decode_errors: DecodeErrorCounts,
+    /// Stamps the frames sent to Carburetor, and measures the echoes of their stamps.
+    link: LinkMonitor,
+}
+
+impl CarburetorStatus {
+    /// Returns the last reported status of `channel`, if any.
+    pub fn channel(&self, channel: u8) -> Option<&ChannelStatus> {
+        self.channels.get(&channel)
+    }
+
+    /// Returns when an instruction for `channel` was last acknowledged by Carburetor, if ever.
+    pub fn last_acknowledged(&self, channel: u8) -> Option<Instant> {
+        self.acknowledged.get(&channel).copied()
+    }
+
+    /// Returns the last reported value of digital input `pin`, if any.
+    pub fn digital_input(&self, pin: u8) -> Option<&DigitalInputStatus> {
+        self.digital_inputs.get(&pin)
+    }
+
+    /// Returns the last reported value of analog input `pin`, if any.
+    pub fn analog_input(&self, pin: u8) -> Option<&AnalogInputStatus> {
+        self.analog_inputs.get(&pin)
+    }
+
+    /// Returns whether the emergency stop of Carburetor is latched, or [`None`] if Carburetor has
+    /// not reported it yet.
+    pub fn estop_latched(&self) -> Option<bool> {
+        self.estop_latched
+    }
+
+    /// Returns the faults that have not been taken yet, oldest first.
+    pub fn faults(&self) -> impl Iterator<Item = &CarburetorFault> {
+        self.faults.iter()
+    }
+
+    /// Removes and returns the faults that have not been taken yet, oldest first.
+    pub fn take_faults(&mut self) -> Vec<CarburetorFault> {
+        self.faults.drain(..).collect()
+    }
+
+    /// Returns how many messages from Carburetor could not be decoded, for every reason.
+    pub fn decode_errors(&self) -> DecodeErrorCounts {
+        self.decode_errors
+    }
+
+    /// Returns what was measured of the link to Carburetor: how many frames were sent, and their
+    /// round trip.
+    pub fn link(&self) -> LinkStats {
+        self.link.stats()
+    }
+
+    /// Returns the stamp for the next frame sent to Carburetor.
+    pub(crate) fn stamp(&mut self) -> Stamp {
+        self.link.stamp()
+    }
+
+    /// Records a message from Carburetor that could not be decoded because of `error`, and returns
+    /// how many errors of its kind there have been so far.
+    pub(crate) fn decode_failed(&mut self, error: &MessageError) -> u64 {
+        self.decode_errors.record(error)
+    }
+
+    pub(crate) fn handle_carburetor_message(&mut self, message: CarburetorToLinkage) {
+        let received_at = Instant::now();
+        match message {
+            CarburetorToLinkage::InstructionAck { channel, .. } => {
+                self.acknowledged.insert(channel, received_at);
+            }
+            CarburetorToLinkage::ChannelStatus {
+                channel,
+                arming,
+                pulse_width_us,
+                since_update_ms,
+            } => {
+                self.channels.insert(
+                    channel,
+                    ChannelStatus {
+                        arming,
+                        pulse_width: Duration::from_micros(pulse_width_us as u64),
+                        since_update: Duration::from_millis(since_update_ms as u64),
+                        received_at,
+                    },
+                );
+            }
+            CarburetorToLinkage::Fault { channel, kind } => {
+                log::warn!("Carburetor reported a fault on channel {channel}: {kind:?}");
+                if self.faults.len() == MAX_FAULTS {
+                    self.faults.pop_front();
+                }
+                self.faults.push_back(CarburetorFault {
+                    channel,
+                    kind,
+                    received_at,
+                });
+            }
+            CarburetorToLinkage::DigitalInput {
+                pin,
+                value,
+                timestamp_ms,
+            } => {
+                self.digital_inputs.insert(
+                    pin,
+                    DigitalInputStatus {
+                        value,
+                        changed_at: Duration::from_millis(timestamp_ms as u64),
+                        received_at,
+                    },
+                );
+            }
+            CarburetorToLinkage::AnalogInput { pin, value } => {
+                self.analog_inputs
+                    .insert(pin, AnalogInputStatus { value, received_at });
+            }
+            CarburetorToLinkage::EStopStatus { latched } => {
+                if self.estop_latched != Some(latched) {
+                    log::warn!("Carburetor emergency stop latched: {latched}");
+                }
+                self.estop_latched = Some(latched);
+            }
+            CarburetorToLinkage::Echo { sent_at_us } => self.link.echoed(sent_at_us),
+        }
+    }
+}
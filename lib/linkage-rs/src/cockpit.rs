@@ -1,16 +1,23 @@
 use config::AddressPort;
-use messaging::{Bytes, CockpitToLinkage};
-use std::io::{self, Read};
+use messaging::{
+    CockpitToLinkage, Component, FrameReader, FramedMessage, Handshake, LinkMonitor,
+    LinkageToCockpit, Message, MessageError, COCKPIT_TO_LINKAGE_ENCODING,
+};
+use std::io::{self, ErrorKind, Write};
 use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
 
 use crate::state::RobotStateHandle;
 
+/// Cockpit is lost if it sends nothing for this long. It sends a heartbeat every 100 ms when it has
+/// nothing else to send.
+const COCKPIT_TIMEOUT: Duration = Duration::from_millis(500);
+
 pub(crate) fn start_listener(state: RobotStateHandle, port: &AddressPort) -> io::Result<()> {
     let listener = TcpListener::bind(format!("0.0.0.0:{port}"))?;
 
     std::thread::spawn(move || {
         for cockpit_stream in listener.incoming() {
-            log::info!("Cockpit connected!");
             handle_cockpit_client(cockpit_stream.unwrap(), state.clone());
         }
     });
@@ -19,15 +26,95 @@ pub(crate) fn start_listener(state: RobotStateHandle, port: &AddressPort) -> io:
 }
 
 fn handle_cockpit_client(mut cockpit_stream: TcpStream, state: RobotStateHandle) {
-    let mut message_bytes = Bytes::default();
-    while let Ok(()) = cockpit_stream.read_exact(&mut message_bytes) {
-        match CockpitToLinkage::try_from(message_bytes) {
+    if let Err(error) = cockpit_stream.set_read_timeout(Some(COCKPIT_TIMEOUT)) {
+        log::error!("Refusing Cockpit, as it cannot be timed out: {error}.");
+        return;
+    }
+
+    let handshake = Handshake::new(Component::LinkageLib, env!("CARGO_PKG_VERSION"));
+    match handshake.exchange(&mut cockpit_stream, Component::Cockpit) {
+        Ok(cockpit) => log::info!("{cockpit} connected."),
+        Err(error) => {
+            log::error!("Refusing Cockpit: {error}.");
+            if let Some(error) = error
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<MessageError>())
+            {
+                state.lock().unwrap().cockpit_decode_errors.record(error);
+            }
+            return;
+        }
+    }
+    {
+        let mut state = state.lock().unwrap();
+        // Every Cockpit numbers its frames from 0.
+        state.cockpit_link = LinkMonitor::new();
+        state.enable();
+    }
+
+    // Echoes go back over the same connection, so Cockpit can measure the round trip.
+    let mut echoes = match cockpit_stream.try_clone() {
+        Ok(echoes) => echoes,
+        Err(error) => {
+            log::error!("Refusing Cockpit, as it cannot be answered: {error}.");
+            return;
+        }
+    };
+    let mut reader = FrameReader::new(cockpit_stream, COCKPIT_TO_LINKAGE_ENCODING);
+    loop {
+        let frame = match reader.read_frame() {
+            Ok(frame) => frame,
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                log::error!("Lost Cockpit, as it sent nothing for {COCKPIT_TIMEOUT:?}.");
+                break;
+            }
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                log::info!("Cockpit closed the connection.");
+                break;
+            }
+            Err(error) if error.kind() == ErrorKind::InvalidData => {
+                if let Some(error) = error
+                    .get_ref()
+                    .and_then(|e| e.downcast_ref::<MessageError>())
+                {
+                    let count = state.lock().unwrap().cockpit_decode_errors.record(error);
+                    log::error!(
+                        "Failed to decode frame from Cockpit: {error} ({count} like it so far)."
+                    );
+                }
+                // The reader skips ahead to the next valid frame.
+                continue;
+            }
+            Err(error) => {
+                log::error!("Lost Cockpit: {error}.");
+                break;
+            }
+        };
+
+        if let Some(stamp) = frame.stamp {
+            state.lock().unwrap().cockpit_link.received(stamp);
+            let echo = LinkageToCockpit::Echo {
+                sent_at_us: stamp.sent_at_us,
+            };
+            if let Err(error) = echoes.write_all(&echo.to_bytes()) {
+                log::warn!("Failed to echo a stamp to Cockpit: {error}.");
+            }
+        }
+
+        match CockpitToLinkage::from_frame(&frame) {
             Ok(message) => state
                 .lock()
                 .unwrap()
                 .gamepad_manager
                 .handle_cockpit_message(message),
-            Err(error) => log::error!("Failed to parse bytes into message: {error}"),
+            Err(error) => {
+                let count = state.lock().unwrap().cockpit_decode_errors.record(&error);
+                log::error!(
+                    "Failed to decode message {frame:?} from Cockpit: {error} ({count} like it so far)."
+                );
+            }
         }
     }
+
+    state.lock().unwrap().disable();
 }
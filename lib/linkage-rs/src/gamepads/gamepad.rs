@@ -3,8 +3,12 @@ use std::io::{self, ErrorKind};
 
 use enum_iterator::Sequence;
 
-const AXIS_DEFAULT: u8 = 127;
-const BUTTON_DEFAULT: u8 = 0;
+/// The value of an axis at rest.
+const AXIS_DEFAULT: f32 = 0.0;
+/// The value of a button that is released.
+const BUTTON_DEFAULT: f32 = 0.0;
+/// Above this value, a button counts as pressed.
+const BUTTON_PRESSED_THRESHOLD: f32 = 0.5;
 
 pub type GamepadId = u8;
 
@@ -82,14 +86,16 @@ pub enum AxisControl {
 #[derive(Default, Debug, Clone)]
 pub struct GamepadData {
     gamepad_id: GamepadId,
-    pub buttons: HashMap<u8, u8>,
-    pub axis: HashMap<u8, u8>,
+    /// The value of every button, within 0.0..=1.0 and 0.0 when released.
+    pub buttons: HashMap<u8, f32>,
+    /// The value of every axis, within -1.0..=1.0 and 0.0 at rest.
+    pub axis: HashMap<u8, f32>,
 }
 
 impl GamepadData {
     pub(crate) fn new(gamepad_id: GamepadId) -> Self {
-        let mut axis = HashMap::<u8, u8>::new();
-        let mut buttons = HashMap::<u8, u8>::new();
+        let mut axis = HashMap::<u8, f32>::new();
+        let mut buttons = HashMap::<u8, f32>::new();
 
         for control in enum_iterator::all::<AxisControl>() {
             axis.insert(control as u8, AXIS_DEFAULT);
@@ -110,12 +116,12 @@ impl GamepadData {
         &mut self,
         event_type: u8,
         control: u8,
-        value: u8,
+        value: f32,
     ) -> io::Result<()> {
         if event_type == EventType::ButtonChanged as u8 {
-            self.buttons.insert(control, value);
+            self.buttons.insert(control, value.clamp(0.0, 1.0));
         } else if event_type == EventType::AxisChanged as u8 {
-            self.axis.insert(control, value);
+            self.axis.insert(control, value.clamp(-1.0, 1.0));
         }
 
         Ok(())
@@ -151,41 +157,39 @@ pub trait Gamepad {
     ///
     /// # Returns
     /// A boolean value indicating whether the specified button is pressed (true) or not (false).
-    fn control_button_value(&self, map: &HashMap<u8, u8>, control: u8) -> bool {
+    fn control_button_value(&self, map: &HashMap<u8, f32>, control: u8) -> bool {
         match map.get(&control) {
-            Some(value) => value > &127,
+            Some(value) => *value > BUTTON_PRESSED_THRESHOLD,
             None => false,
         }
     }
 
-    /// Returns the float value of an axis control, mapped to the specified range.
+    /// Returns the float value of an axis control, which is within -1.0..=1.0 and exactly 0.0 at
+    /// rest.
     ///
     /// # Parameters
     /// - `map`: A reference to the map with the control you want to check.
     /// - `control`: The control ID for the axis to be checked.
-    /// - `axis_range`: A tuple representing the range `(min, max)` to map the axis value to.
     ///
     /// # Returns
-    /// A float value representing the axis value mapped to the specified range.
-    fn control_axis_value(
-        &self,
-        map: &HashMap<u8, u8>,
-        control: u8,
-        axis_range: (f32, f32),
-    ) -> f32 {
-        match map.get(&control) {
-            Some(value) => {
-                fn map_range(from_range: (f32, f32), to_range: (f32, f32), s: f32) -> f32 {
-                    to_range.0
-                        + (s - from_range.0) * (to_range.1 - to_range.0)
-                            / (from_range.1 - from_range.0)
-                }
-
-                let clamped = f32::clamp(value.to_owned() as f32, 0f32, 255f32);
-                map_range((0f32, 255f32), axis_range, clamped)
-            }
-            None => 0f32,
-        }
+    /// A float value representing the axis value, or 0.0 if the axis is unknown.
+    fn control_axis_value(&self, map: &HashMap<u8, f32>, control: u8) -> f32 {
+        map.get(&control)
+            .map_or(AXIS_DEFAULT, |value| value.clamp(-1.0, 1.0))
+    }
+
+    /// Returns the float value of an analog button control, like a trigger, which is within
+    /// 0.0..=1.0 and exactly 0.0 when released.
+    ///
+    /// # Parameters
+    /// - `map`: A reference to the map with the control you want to check.
+    /// - `control`: The control ID for the button to be checked.
+    ///
+    /// # Returns
+    /// A float value representing how far the button is pressed, or 0.0 if the button is unknown.
+    fn control_analog_button_value(&self, map: &HashMap<u8, f32>, control: u8) -> f32 {
+        map.get(&control)
+            .map_or(BUTTON_DEFAULT, |value| value.clamp(0.0, 1.0))
     }
 }
 
@@ -198,3 +202,83 @@ pub(crate) enum EventType {
     Connected = 2,
     Disconnected = 3,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AxisControl, ButtonControl, EventType, Gamepad, GamepadData};
+    use crate::gamepads::PsController;
+
+    #[test]
+    fn starts_at_rest() {
+        let data = GamepadData::new(3);
+        assert_eq!(data.gamepad_id(), 3);
+        assert_eq!(data.axis.len(), enum_iterator::cardinality::<AxisControl>());
+        assert_eq!(
+            data.buttons.len(),
+            enum_iterator::cardinality::<ButtonControl>()
+        );
+        assert!(data.axis.values().all(|value| *value == 0.0));
+        assert!(data.buttons.values().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn clamps_events() {
+        let mut data = GamepadData::new(0);
+        let axis = AxisControl::LeftStickX as u8;
+        let button = ButtonControl::South as u8;
+
+        data.handle_cockpit_message(EventType::AxisChanged as u8, axis, 1.5)
+            .unwrap();
+        assert_eq!(data.axis[&axis], 1.0);
+        data.handle_cockpit_message(EventType::AxisChanged as u8, axis, -3.0)
+            .unwrap();
+        assert_eq!(data.axis[&axis], -1.0);
+        data.handle_cockpit_message(EventType::ButtonChanged as u8, button, -0.5)
+            .unwrap();
+        assert_eq!(data.buttons[&button], 0.0);
+        data.handle_cockpit_message(EventType::ButtonChanged as u8, button, 2.0)
+            .unwrap();
+        assert_eq!(data.buttons[&button], 1.0);
+
+        // Other events leave the controls alone.
+        data.handle_cockpit_message(EventType::Connected as u8, axis, 0.25)
+            .unwrap();
+        assert_eq!(data.axis[&axis], -1.0);
+    }
+
+    #[test]
+    fn reads_axes() {
+        let mut data = GamepadData::new(0);
+        let axis = AxisControl::LeftStickY as u8;
+        let gamepad = PsController::new(data.clone());
+        assert_eq!(gamepad.control_axis_value(&data.axis, axis), 0.0);
+        assert_eq!(gamepad.left_joystick_y().to_bits(), 0.0f32.to_bits());
+
+        data.handle_cockpit_message(EventType::AxisChanged as u8, axis, -0.25)
+            .unwrap();
+        assert_eq!(gamepad.control_axis_value(&data.axis, axis), -0.25);
+
+        // Values that did not come through an event are clamped too, and unknown axes are at rest.
+        data.axis.insert(axis, 4.0);
+        assert_eq!(gamepad.control_axis_value(&data.axis, axis), 1.0);
+        assert_eq!(gamepad.control_axis_value(&data.axis, 200), 0.0);
+    }
+
+    #[test]
+    fn presses_buttons_above_the_threshold() {
+        let mut data = GamepadData::new(0);
+        let button = ButtonControl::North as u8;
+        for (value, pressed) in [(0.0, false), (0.5, false), (0.51, true), (1.0, true)] {
+            data.handle_cockpit_message(EventType::ButtonChanged as u8, button, value)
+                .unwrap();
+            assert_eq!(
+                PsController::new(data.clone()).triangle(),
+                pressed,
+                "{value}"
+            );
+        }
+
+        let gamepad = PsController::new(data.clone());
+        assert!(!gamepad.control_button_value(&data.buttons, 200));
+    }
+}
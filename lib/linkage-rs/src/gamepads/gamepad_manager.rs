@@ -85,6 +85,15 @@ impl GamepadManager {
                     Err(_) => log::error!("Invalid event_type: {event_type}"),
                 }
             }
+            // Only tells us Cockpit is still there.
+            CockpitToLinkage::Heartbeat => {}
+        }
+    }
+
+    /// Puts every connected gamepad back at rest, as if none of its controls are touched.
+    pub(crate) fn reset(&mut self) {
+        for gamepad in self.gamepads.iter_mut().flatten() {
+            *gamepad = GamepadData::new(gamepad.gamepad_id());
         }
     }
 
@@ -116,10 +116,9 @@ impl PsController {
     /// # Returns
     /// A value between 0.0 and 1.0 representing not pressed or fully pressed respectively.
     pub fn left_trigger(&self) -> f32 {
-        self.control_axis_value(
+        self.control_analog_button_value(
             &self.gamepad_data.buttons,
             ButtonControl::LeftTrigger2 as u8,
-            (0f32, 1f32),
         )
     }
 
@@ -128,10 +127,9 @@ impl PsController {
     /// # Returns
     /// A value between 0.0 and 1.0 representing not pressed or fully pressed respectively.
     pub fn right_trigger(&self) -> f32 {
-        self.control_axis_value(
+        self.control_analog_button_value(
             &self.gamepad_data.buttons,
             ButtonControl::RightTrigger2 as u8,
-            (0f32, 1f32),
         )
     }
 
@@ -141,11 +139,7 @@ impl PsController {
     /// A value between -1.0 and 1.0 representing completely left or completely right
     /// respectively with 0.0 being the center.
     pub fn left_joystick_x(&self) -> f32 {
-        self.control_axis_value(
-            &self.gamepad_data.axis,
-            AxisControl::LeftStickX as u8,
-            (-1f32, 1f32),
-        )
+        self.control_axis_value(&self.gamepad_data.axis, AxisControl::LeftStickX as u8)
     }
 
     /// Returns the y-axis of the left joystick
@@ -154,11 +148,7 @@ impl PsController {
     /// A value between -1.0 and 1.0 representing completely up or completely down
     /// respectively with 0.0 being the center.
     pub fn left_joystick_y(&self) -> f32 {
-        self.control_axis_value(
-            &self.gamepad_data.axis,
-            AxisControl::LeftStickY as u8,
-            (-1f32, 1f32),
-        )
+        self.control_axis_value(&self.gamepad_data.axis, AxisControl::LeftStickY as u8)
     }
 
     /// Returns the state of the left joystick button.
@@ -175,11 +165,7 @@ impl PsController {
     /// A value between -1.0 and 1.0 representing completely up or completely down
     /// respectively with 0.0 being the center.
     pub fn right_joystick_x(&self) -> f32 {
-        self.control_axis_value(
-            &self.gamepad_data.axis,
-            AxisControl::RightStickX as u8,
-            (-1f32, 1f32),
-        )
+        self.control_axis_value(&self.gamepad_data.axis, AxisControl::RightStickX as u8)
     }
 
     /// Returns the y-axis of the right joystick
@@ -188,11 +174,7 @@ impl PsController {
     /// A value between -1.0 and 1.0 representing completely up or completely down
     /// respectively with 0.0 being the center.
     pub fn right_joystick_y(&self) -> f32 {
-        self.control_axis_value(
-            &self.gamepad_data.axis,
-            AxisControl::RightStickY as u8,
-            (-1f32, 1f32),
-        )
+        self.control_axis_value(&self.gamepad_data.axis, AxisControl::RightStickY as u8)
     }
 
     /// Returns the state of the right joystick button.
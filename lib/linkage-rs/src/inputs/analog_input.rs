@@ -0,0 +1,66 @@
+use crate::carburetor_status::AnalogInputStatus;
+use crate::state::RobotStateHandle;
+
+/// Used to read an analog input, like a potentiometer.
+///
+/// The pin numbers are the ones configured in `carburetor.analog_inputs`. Carburetor reports the
+/// input whenever it changes by more than its threshold.
+pub struct AnalogInput {
+    state: RobotStateHandle,
+    pin: u8,
+}
+
+impl AnalogInput {
+    /// Creates a new [`AnalogInput`] with the specified shared [RobotState][`crate::state::RobotState`] and pin.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared [RobotState][`crate::state::RobotState`].
+    /// * `pin` - The analog input pin that should be read.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of [`AnalogInput`].
+    pub fn new(state: RobotStateHandle, pin: u8) -> Self {
+        Self { state, pin }
+    }
+
+    /// Returns the reading as a fraction of the full scale of the ADC, within 0.0..=1.0, or
+    /// [`None`] if Carburetor has not reported it yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use linkage_rs::inputs::AnalogInput;
+    /// use linkage_rs::prelude::*;
+    ///
+    /// #[derive(Default)]
+    /// struct ExampleSubsystem;
+    ///
+    /// impl Subsystem for ExampleSubsystem {
+    ///     fn tick(&mut self, state: RobotStateHandle) {
+    ///         let arm_angle = AnalogInput::new(state.clone(), 0); // Read analog input 0.
+    ///         if let Some(position) = arm_angle.get() {
+    ///             println!("The arm is at {:.0}% of its travel.", position * 100.0);
+    ///         }
+    ///     }
+    /// }
+    ///
+    ///  Robot::new()
+    ///     .add_subsystem(ExampleSubsystem::default())
+    ///     .run();
+    /// ```
+    pub fn get(&self) -> Option<f32> {
+        self.status().map(|status| status.value)
+    }
+
+    /// Returns the last reported status of the input, including when it was received.
+    pub fn status(&self) -> Option<AnalogInputStatus> {
+        self.state
+            .lock()
+            .unwrap()
+            .carburetor_status
+            .analog_input(self.pin)
+            .copied()
+    }
+}
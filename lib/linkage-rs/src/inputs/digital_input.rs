@@ -0,0 +1,65 @@
+use crate::carburetor_status::DigitalInputStatus;
+use crate::state::RobotStateHandle;
+
+/// Used to read a digital input, like a beam break.
+///
+/// The pin numbers are the ones configured in `carburetor.digital_inputs`. Carburetor debounces the
+/// input, and reports it whenever it changes.
+pub struct DigitalInput {
+    state: RobotStateHandle,
+    pin: u8,
+}
+
+impl DigitalInput {
+    /// Creates a new [`DigitalInput`] with the specified shared [RobotState][`crate::state::RobotState`] and pin.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared [RobotState][`crate::state::RobotState`].
+    /// * `pin` - The digital input pin that should be read.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of [`DigitalInput`].
+    pub fn new(state: RobotStateHandle, pin: u8) -> Self {
+        Self { state, pin }
+    }
+
+    /// Returns whether the input is high, or [`None`] if Carburetor has not reported it yet.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use linkage_rs::inputs::DigitalInput;
+    /// use linkage_rs::prelude::*;
+    ///
+    /// #[derive(Default)]
+    /// struct ExampleSubsystem;
+    ///
+    /// impl Subsystem for ExampleSubsystem {
+    ///     fn tick(&mut self, state: RobotStateHandle) {
+    ///         let beam_break = DigitalInput::new(state.clone(), 0); // Read digital input 0.
+    ///         if beam_break.get() == Some(false) {
+    ///             println!("Something is in the way!");
+    ///         }
+    ///     }
+    /// }
+    ///
+    ///  Robot::new()
+    ///     .add_subsystem(ExampleSubsystem::default())
+    ///     .run();
+    /// ```
+    pub fn get(&self) -> Option<bool> {
+        self.status().map(|status| status.value)
+    }
+
+    /// Returns the last reported status of the input, including when it last changed.
+    pub fn status(&self) -> Option<DigitalInputStatus> {
+        self.state
+            .lock()
+            .unwrap()
+            .carburetor_status
+            .digital_input(self.pin)
+            .copied()
+    }
+}
@@ -0,0 +1,76 @@
+use super::DigitalInput;
+use crate::state::RobotStateHandle;
+
+/// Used to read a limit switch connected to a digital input.
+pub struct LimitSwitch {
+    input: DigitalInput,
+    normally_closed: bool,
+}
+
+impl LimitSwitch {
+    /// Creates a new [`LimitSwitch`] for a normally open switch, which makes the input high when
+    /// pressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared [RobotState][`crate::state::RobotState`].
+    /// * `pin` - The digital input pin the switch is connected to.
+    pub fn new(state: RobotStateHandle, pin: u8) -> Self {
+        Self {
+            input: DigitalInput::new(state, pin),
+            normally_closed: false,
+        }
+    }
+
+    /// Creates a new [`LimitSwitch`] for a normally closed switch, which makes the input low when
+    /// pressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared [RobotState][`crate::state::RobotState`].
+    /// * `pin` - The digital input pin the switch is connected to.
+    pub fn normally_closed(state: RobotStateHandle, pin: u8) -> Self {
+        Self {
+            input: DigitalInput::new(state, pin),
+            normally_closed: true,
+        }
+    }
+
+    /// Returns whether the switch is pressed.
+    ///
+    /// As long as Carburetor has not reported the input, the switch is considered pressed, so a
+    /// mechanism is not driven past its limit before its position is known.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use linkage_rs::inputs::LimitSwitch;
+    /// use linkage_rs::motors::SparkMotorController;
+    /// use linkage_rs::prelude::*;
+    ///
+    /// #[derive(Default)]
+    /// struct ExampleSubsystem;
+    ///
+    /// impl Subsystem for ExampleSubsystem {
+    ///     fn tick(&mut self, state: RobotStateHandle) {
+    ///         let top = LimitSwitch::new(state.clone(), 0);
+    ///         let lift = SparkMotorController::new(state.clone(), 0);
+    ///         if top.is_pressed() {
+    ///             lift.set_speed_percentage(0.0);
+    ///         } else {
+    ///             lift.set_speed_percentage(0.5);
+    ///         }
+    ///     }
+    /// }
+    ///
+    ///  Robot::new()
+    ///     .add_subsystem(ExampleSubsystem::default())
+    ///     .run();
+    /// ```
+    pub fn is_pressed(&self) -> bool {
+        match self.input.get() {
+            Some(value) => value != self.normally_closed,
+            None => true,
+        }
+    }
+}
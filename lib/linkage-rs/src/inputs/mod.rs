@@ -0,0 +1,9 @@
+//! Interfaces for reading sensors, like limit switches, beam breaks and potentiometers.
+
+mod analog_input;
+mod digital_input;
+mod limit_switch;
+
+pub use analog_input::AnalogInput;
+pub use digital_input::DigitalInput;
+pub use limit_switch::LimitSwitch;
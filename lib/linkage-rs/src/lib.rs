@@ -1,7 +1,10 @@
 pub(crate) mod carburetor;
+pub mod carburetor_status;
 pub(crate) mod cockpit;
 pub mod gamepads;
+pub mod inputs;
 pub mod motors;
+pub mod outputs;
 pub mod prelude;
 pub mod robot;
 pub mod state;
@@ -1,5 +1,7 @@
 //! Interfaces for controlling motors.
 
+mod servo;
 mod spark_motor_controller;
 
+pub use servo::Servo;
 pub use spark_motor_controller::SparkMotorController;
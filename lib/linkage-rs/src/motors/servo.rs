@@ -0,0 +1,68 @@
+use messaging::ServoTarget;
+
+use crate::state::RobotStateHandle;
+
+/// Used to control a hobby servo, by the angle it should turn to.
+///
+/// The channel must be configured as a servo in `carburetor.channels`, which also sets the range
+/// of angles it can reach. Positions outside of that range are refused by Carburetor.
+pub struct Servo {
+    state: RobotStateHandle,
+    channel: u8,
+}
+
+impl Servo {
+    /// Creates a new [`Servo`] with the specified shared [RobotState][`crate::state::RobotState`] and channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared [RobotState][`crate::state::RobotState`].
+    /// * `channel` - The channel that this servo is connected to.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of [`Servo`].
+    pub fn new(state: RobotStateHandle, channel: u8) -> Self {
+        Self { state, channel }
+    }
+
+    /// Turns the servo to an angle in degrees, within the range configured for its channel.
+    /// Ignored while the robot is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use linkage_rs::motors::Servo;
+    /// use linkage_rs::prelude::*;
+    ///
+    /// #[derive(Default)]
+    /// struct ExampleSubsystem;
+    ///
+    /// impl Subsystem for ExampleSubsystem {
+    ///     fn setup(&mut self, state: RobotStateHandle) {
+    ///         let claw = Servo::new(state.clone(), 2); // Create a new Servo on channel 2.
+    ///         claw.set_angle(90.0); // Turn the claw to 90°.
+    ///     }
+    /// }
+    ///
+    ///  Robot::new()
+    ///     .add_subsystem(ExampleSubsystem::default())
+    ///     .run();
+    /// ```
+    pub fn set_angle(&self, degrees: f32) {
+        self.send(ServoTarget::Degrees(degrees));
+    }
+
+    /// Turns the servo to a position between 0.0 (the smallest configured angle) and 1.0 (the
+    /// largest configured angle). Ignored while the robot is disabled.
+    pub fn set_position(&self, position: f32) {
+        self.send(ServoTarget::Position(position));
+    }
+
+    fn send(&self, target: ServoTarget) {
+        self.state
+            .lock()
+            .unwrap()
+            .set_servo_position(self.channel, target);
+    }
+}
@@ -1,5 +1,3 @@
-use messaging::LinkageToCarburetor;
-
 use crate::state::RobotStateHandle;
 
 /// Used control Spark-branded motor controllers.
@@ -27,6 +25,12 @@ impl SparkMotorController {
 
     /// Sets the motor speed as a percentage.
     ///
+    /// The speeds of all motors that are set during a tick are sent to Carburetor together once the
+    /// tick is over, so they change at the same time. Only the last speed set for a motor during a
+    /// tick is used. Servos, digital outputs and the emergency stop are not held back until then:
+    /// setting one sends the speeds that are pending first, so Carburetor carries out every
+    /// instruction in the order it was given. An emergency stop drops the pending speeds instead.
+    ///
     /// # Arguments
     ///
     /// * `speed` - The desired motor speed as a percentage (between -1.0 and 1.0).
@@ -53,13 +57,9 @@ impl SparkMotorController {
     ///     .run();
     /// ```
     pub fn set_speed_percentage(&self, speed: f32) {
-        let sender = self.state.lock().unwrap().carburetor_message_sender.clone();
-
-        sender
-            .send(LinkageToCarburetor::MotorInstruction {
-                channel: self.channel,
-                speed,
-            })
-            .unwrap();
+        self.state
+            .lock()
+            .unwrap()
+            .set_motor_speed(self.channel, speed);
     }
 }
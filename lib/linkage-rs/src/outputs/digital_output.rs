@@ -0,0 +1,66 @@
+use crate::state::RobotStateHandle;
+
+/// Used to control a digital output, like a pneumatic solenoid, a relay or a status LED.
+///
+/// The pin numbers are the ones configured in `carburetor.digital_outputs`. When the connection
+/// with Carburetor is lost, it puts every output back into its configured safe state. When the
+/// robot is disabled, Linkage does the same.
+pub struct DigitalOutput {
+    state: RobotStateHandle,
+    pin: u8,
+}
+
+impl DigitalOutput {
+    /// Creates a new [`DigitalOutput`] with the specified shared [RobotState][`crate::state::RobotState`] and pin.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared [RobotState][`crate::state::RobotState`].
+    /// * `pin` - The digital output pin that this output should control.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of [`DigitalOutput`].
+    pub fn new(state: RobotStateHandle, pin: u8) -> Self {
+        Self { state, pin }
+    }
+
+    /// Turns the output on (`true`) or off (`false`). Ignored while the robot is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use linkage_rs::outputs::DigitalOutput;
+    /// use linkage_rs::prelude::*;
+    ///
+    /// #[derive(Default)]
+    /// struct ExampleSubsystem;
+    ///
+    /// impl Subsystem for ExampleSubsystem {
+    ///     fn setup(&mut self, state: RobotStateHandle) {
+    ///         let solenoid = DigitalOutput::new(state.clone(), 0); // Create a new DigitalOutput on pin 0.
+    ///         solenoid.set(true); // Open the solenoid.
+    ///     }
+    /// }
+    ///
+    ///  Robot::new()
+    ///     .add_subsystem(ExampleSubsystem::default())
+    ///     .run();
+    /// ```
+    pub fn set(&self, value: bool) {
+        self.state
+            .lock()
+            .unwrap()
+            .set_digital_output(self.pin, value);
+    }
+
+    /// Turns the output on.
+    pub fn set_high(&self) {
+        self.set(true);
+    }
+
+    /// Turns the output off.
+    pub fn set_low(&self) {
+        self.set(false);
+    }
+}
@@ -0,0 +1,5 @@
+//! Interfaces for controlling on/off outputs, like solenoids, relays and LEDs.
+
+mod digital_output;
+
+pub use digital_output::DigitalOutput;
@@ -1,12 +1,13 @@
 //! The entrypoint for your robot code, and encapsulates the event loop.
 
-use std::io::{self, Read};
+use std::io::Read;
+use std::net::TcpStream;
 use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
 
 use crate::carburetor;
 use crate::cockpit;
-use crate::state::RobotState;
+use crate::state::{RobotState, RobotStateHandle};
 use crate::subsystem::Subsystem;
 
 /// A struct representing the main robot object.
@@ -25,6 +26,7 @@ pub struct Robot {
     subsystems: Vec<Box<dyn Subsystem>>,
     setup_handler: Option<Box<dyn Fn()>>,
     tick_handler: Option<Box<dyn Fn()>>,
+    disabled_handler: Option<Box<dyn Fn()>>,
     shutdown_handler: Option<Box<dyn Fn()>>,
     is_running: bool,
 }
@@ -82,6 +84,21 @@ impl Robot {
         self
     }
 
+    /// Sets the disabled handler function for the robot. This will be called before the next tick
+    /// whenever the robot is disabled because Cockpit was lost.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_disabled` - The disabled handler function.
+    ///
+    /// # Returns
+    ///
+    /// * The `Robot` instance with the disabled handler set.
+    pub fn on_disabled<F: Fn() + 'static>(mut self, disabled_handler: F) -> Self {
+        self.disabled_handler = Some(Box::new(disabled_handler));
+        self
+    }
+
     /// Sets the shutdown handler function for the robot. This will be called once all subsystems
     /// have been shut down.
     ///
@@ -99,12 +116,24 @@ impl Robot {
 
     /// Runs the main loop of the robot, executing the setup, tick, and shutdown handlers.
     pub fn run(mut self) {
+        let activation = systemd::take_listen_fds().into_iter().next();
+        let notifier = systemd::Notifier::from_env();
+
         let config = config::config().expect("failed to load config");
         logging::Logger::new(config.linkage_lib().logger_port().to_owned()).start();
 
         let (carburetor_message_sender, carburetor_message_receiver) = channel();
 
-        let state = Arc::new(Mutex::new(RobotState::new(carburetor_message_sender)));
+        let digital_output_safe_states = config
+            .carburetor()
+            .digital_outputs()
+            .iter()
+            .map(|output| (output.pin(), output.safe_state()))
+            .collect();
+        let state = Arc::new(Mutex::new(RobotState::new(
+            carburetor_message_sender,
+            digital_output_safe_states,
+        )));
 
         cockpit::start_listener(state.clone(), config.linkage_lib().port())
             .expect("failed to start listening for Cockpit connections.");
@@ -112,6 +141,8 @@ impl Robot {
         carburetor::open_connection(
             carburetor_message_receiver,
             config.linkage_lib().carburetor_address(),
+            config.linkage_lib().carburetor_transport(),
+            state.clone(),
         )
         .expect("failed to open connection with Carburetor.");
 
@@ -128,29 +159,29 @@ impl Robot {
         })
         .expect("failed to set termination handler");
 
-        // NOTE: This makes sure we close the connection when te systemd socket
-        //       is closed. It will close when the stdin stream is closed.
-        std::thread::spawn(move || {
-            let mut stdin = io::stdin();
-            let mut buffer = [0; 1024];
-
-            loop {
-                match stdin.read(&mut buffer) {
-                    Ok(n) => {
-                        if n == 0 {
-                            term_tx
-                                .send(())
-                                .expect("could not send termination signal over channel");
+        // When started by `linkage.socket`, systemd passes the connection that started us. The
+        // robot code stops once that connection is closed.
+        if let Some(connection) = activation {
+            std::thread::spawn(move || {
+                let mut connection = TcpStream::from(connection);
+                let mut buffer = [0; 1024];
+
+                loop {
+                    match connection.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::debug!("Error reading from the activating connection: {}", e);
                             break;
                         }
                     }
-                    Err(e) => {
-                        log::debug!("Error reading from stdin: {}", e);
-                        break;
-                    }
                 }
-            }
-        });
+
+                term_tx
+                    .send(())
+                    .expect("could not send termination signal over channel");
+            });
+        }
 
         if let Some(setup) = &self.setup_handler {
             setup();
@@ -159,14 +190,20 @@ impl Robot {
         for subsystem in self.subsystems.iter_mut() {
             subsystem.setup(state.clone());
         }
+        state.lock().unwrap().flush_motor_speeds();
+        notifier.ready();
 
         while self.is_running {
+            notifier.feed_watchdog();
+
+            self.handle_disabled(&state);
             if let Some(tick) = &self.tick_handler {
                 tick();
             }
             for subsystem in self.subsystems.iter_mut() {
                 subsystem.tick(state.clone());
             }
+            state.lock().unwrap().flush_motor_speeds();
 
             std::thread::sleep(std::time::Duration::from_millis(20));
 
@@ -175,12 +212,29 @@ impl Robot {
             }
         }
 
+        notifier.stopping();
         for subsystem in self.subsystems.iter_mut() {
             subsystem.shutdown(state.clone());
         }
         if let Some(shutdown) = &self.shutdown_handler {
             shutdown();
         }
+        state.lock().unwrap().flush_motor_speeds();
+    }
+
+    /// Calls the disabled handler and lets every subsystem know, if the robot was disabled since
+    /// the last tick.
+    fn handle_disabled(&mut self, state: &RobotStateHandle) {
+        if !state.lock().unwrap().take_disabled() {
+            return;
+        }
+
+        if let Some(disabled) = &self.disabled_handler {
+            disabled();
+        }
+        for subsystem in self.subsystems.iter_mut() {
+            subsystem.disabled(state.clone());
+        }
     }
 
     /// Shuts down the robot, stopping its main loop.
@@ -188,3 +242,49 @@ impl Robot {
         self.is_running = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+
+    use super::Robot;
+    use crate::state::{RobotState, RobotStateHandle};
+    use crate::subsystem::Subsystem;
+
+    /// Counts how often it was told the robot was disabled.
+    struct DisabledCounter(Rc<Cell<usize>>);
+
+    impl Subsystem for DisabledCounter {
+        fn disabled(&mut self, _state: RobotStateHandle) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn calls_the_disabled_hook_once() {
+        let handled = Rc::new(Cell::new(0));
+        let subsystem = Rc::new(Cell::new(0));
+        let mut robot = Robot::new()
+            .on_disabled({
+                let handled = handled.clone();
+                move || handled.set(handled.get() + 1)
+            })
+            .add_subsystem(DisabledCounter(subsystem.clone()));
+        let (sender, _receiver) = channel();
+        let state = Arc::new(Mutex::new(RobotState::new(sender, BTreeMap::new())));
+
+        robot.handle_disabled(&state);
+        assert_eq!(handled.get(), 0);
+        assert_eq!(subsystem.get(), 0);
+
+        state.lock().unwrap().disable();
+        robot.handle_disabled(&state);
+        robot.handle_disabled(&state);
+        assert_eq!(handled.get(), 1);
+        assert_eq!(subsystem.get(), 1);
+    }
+}
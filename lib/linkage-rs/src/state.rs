@@ -1,10 +1,16 @@
 //! Shared data used to access data like gamepad input within for example subsystems.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 
-use messaging::LinkageToCarburetor;
+use messaging::{
+    DecodeErrorCounts, LinkMonitor, LinkStats, LinkageToCarburetor, LinkageToCarburetorFrame,
+    MotorBatch, ServoTarget,
+};
 
+use crate::carburetor::Outgoing;
+use crate::carburetor_status::CarburetorStatus;
 use crate::gamepads::GamepadManager;
 
 /// A thread-safe handle to a [`RobotState`].
@@ -15,14 +21,287 @@ pub type RobotStateHandle = Arc<Mutex<RobotState>>;
 pub struct RobotState {
     /// Responsible for managing the connected [Gamepad][`crate::gamepads::gamepad::Gamepad`]s.
     pub gamepad_manager: GamepadManager,
-    pub(crate) carburetor_message_sender: Sender<LinkageToCarburetor>,
+    /// Feedback from Carburetor, like rejected instructions and the state of its channels.
+    pub carburetor_status: CarburetorStatus,
+    /// How many messages from Cockpit could not be decoded, for every reason, including handshakes
+    /// that were refused.
+    pub cockpit_decode_errors: DecodeErrorCounts,
+    /// Measures the stamps of the frames from the Cockpit that is connected.
+    pub(crate) cockpit_link: LinkMonitor,
+    carburetor_message_sender: Sender<Outgoing>,
+    /// The motor speeds set since the last [`RobotState::flush_motor_speeds`].
+    pending_speeds: BTreeMap<u8, f32>,
+    /// The channels of every motor that was ever sent a speed.
+    driven_channels: BTreeSet<u8>,
+    /// The state every configured digital output is put into when the robot is disabled, by pin.
+    digital_output_safe_states: BTreeMap<u8, bool>,
+    enabled: bool,
+    /// Whether the robot was disabled since the subsystems were last told.
+    disabled_unseen: bool,
 }
 
 impl RobotState {
-    pub(crate) fn new(carburetor_message_sender: Sender<LinkageToCarburetor>) -> Self {
+    pub(crate) fn new(
+        carburetor_message_sender: Sender<Outgoing>,
+        digital_output_safe_states: BTreeMap<u8, bool>,
+    ) -> Self {
         Self {
             gamepad_manager: GamepadManager::new(),
+            carburetor_status: CarburetorStatus::default(),
+            cockpit_decode_errors: DecodeErrorCounts::default(),
+            cockpit_link: LinkMonitor::new(),
             carburetor_message_sender,
+            pending_speeds: BTreeMap::new(),
+            driven_channels: BTreeSet::new(),
+            digital_output_safe_states,
+            enabled: true,
+            disabled_unseen: false,
         }
     }
+
+    /// Returns whether the robot is enabled. It is disabled once Cockpit is lost, until a Cockpit
+    /// connects again. While disabled, motor speeds, servo positions and digital outputs are
+    /// ignored, every motor is in neutral and every digital output is in its safe state.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns what was measured of the frames from the Cockpit that is connected: how many were
+    /// dropped or arrived out of order, and how long they took to arrive.
+    pub fn cockpit_link(&self) -> LinkStats {
+        self.cockpit_link.stats()
+    }
+
+    /// Enables the robot, after a Cockpit connected.
+    pub(crate) fn enable(&mut self) {
+        if !self.enabled {
+            log::info!("Enabling the robot.");
+            self.enabled = true;
+        }
+    }
+
+    /// Disables the robot, after Cockpit was lost: the gamepads are put back at rest, every motor
+    /// into neutral and every digital output into its safe state.
+    pub(crate) fn disable(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        log::warn!("Disabling the robot.");
+        self.enabled = false;
+        self.disabled_unseen = true;
+        self.gamepad_manager.reset();
+        self.pending_speeds.clear();
+        if !self.driven_channels.is_empty() {
+            let speeds = self
+                .driven_channels
+                .iter()
+                .map(|channel| (*channel, 0.0))
+                .collect();
+            // Nothing follows up on these speeds while disabled, so they must not be lost.
+            self.send_outgoing(Outgoing::reliable(MotorBatch { speeds }.into()));
+        }
+        for (&pin, &value) in &self.digital_output_safe_states {
+            self.send(LinkageToCarburetor::DigitalOutput { pin, value });
+        }
+    }
+
+    /// Returns whether the robot was disabled since this was last called.
+    pub(crate) fn take_disabled(&mut self) -> bool {
+        std::mem::take(&mut self.disabled_unseen)
+    }
+
+    /// Makes Carburetor put every motor into neutral and every digital output into its safe state
+    /// immediately, and refuse further instructions until [`RobotState::reset_emergency_stop`] is
+    /// called. Motor speeds that are still waiting for the end of the tick are dropped.
+    pub fn emergency_stop(&mut self) {
+        self.pending_speeds.clear();
+        self.send(LinkageToCarburetor::EStop);
+    }
+
+    /// Releases the emergency stop, so Carburetor accepts instructions again.
+    pub fn reset_emergency_stop(&mut self) {
+        self.send_to_carburetor(LinkageToCarburetor::ResetEStop);
+    }
+
+    /// Sets the speed of the motor on `channel` once the current tick is over, together with the
+    /// other motors that were set during the tick. Ignored while the robot is disabled.
+    pub(crate) fn set_motor_speed(&mut self, channel: u8, speed: f32) {
+        if self.enabled {
+            self.pending_speeds.insert(channel, speed);
+        }
+    }
+
+    /// Turns the servo on `channel` to `target`. Ignored while the robot is disabled.
+    pub(crate) fn set_servo_position(&mut self, channel: u8, target: ServoTarget) {
+        if self.enabled {
+            self.send_to_carburetor(LinkageToCarburetor::ServoPosition { channel, target });
+        }
+    }
+
+    /// Sets the digital output on `pin` to `value`. Ignored while the robot is disabled.
+    pub(crate) fn set_digital_output(&mut self, pin: u8, value: bool) {
+        if self.enabled {
+            self.send_to_carburetor(LinkageToCarburetor::DigitalOutput { pin, value });
+        }
+    }
+
+    /// Sends the motor speeds that were set since the last flush to Carburetor, as one batch.
+    pub(crate) fn flush_motor_speeds(&mut self) {
+        if self.pending_speeds.is_empty() {
+            return;
+        }
+
+        self.driven_channels.extend(self.pending_speeds.keys());
+        let speeds = std::mem::take(&mut self.pending_speeds)
+            .into_iter()
+            .collect();
+        self.send(MotorBatch { speeds });
+    }
+
+    /// Sends `message` to Carburetor, after the motor speeds that were set so far, so every
+    /// instruction is carried out in the order it was given.
+    fn send_to_carburetor(&mut self, message: impl Into<LinkageToCarburetorFrame>) {
+        self.flush_motor_speeds();
+        self.send(message);
+    }
+
+    fn send(&self, message: impl Into<LinkageToCarburetorFrame>) {
+        self.send_outgoing(Outgoing::new(message.into()));
+    }
+
+    fn send_outgoing(&self, message: Outgoing) {
+        if let Err(error) = self.carburetor_message_sender.send(message) {
+            log::error!("Failed to send {:?} to Carburetor: {error}", error.0.frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::mpsc::{channel, Receiver};
+
+    use messaging::{
+        CockpitToLinkage, LinkageToCarburetor, LinkageToCarburetorFrame, MotorBatch, ServoTarget,
+    };
+
+    use super::RobotState;
+    use crate::carburetor::Outgoing;
+    use crate::gamepads::{AssociatedGamepad, PsController};
+
+    fn state() -> (RobotState, Receiver<Outgoing>) {
+        let (sender, receiver) = channel();
+        let safe_states = BTreeMap::from([(0, false), (1, true)]);
+        (RobotState::new(sender, safe_states), receiver)
+    }
+
+    fn actuate(state: &mut RobotState) {
+        state.set_motor_speed(2, 0.5);
+        state.set_servo_position(3, ServoTarget::Position(0.25));
+        state.set_digital_output(0, true);
+        state.flush_motor_speeds();
+    }
+
+    #[test]
+    fn disabling_stops_every_actuator() {
+        let (mut state, receiver) = state();
+        actuate(&mut state);
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            [
+                Outgoing::new(LinkageToCarburetorFrame::MotorBatch(MotorBatch {
+                    speeds: vec![(2, 0.5)]
+                })),
+                Outgoing::new(
+                    LinkageToCarburetor::ServoPosition {
+                        channel: 3,
+                        target: ServoTarget::Position(0.25)
+                    }
+                    .into()
+                ),
+                Outgoing::new(
+                    LinkageToCarburetor::DigitalOutput {
+                        pin: 0,
+                        value: true
+                    }
+                    .into()
+                ),
+            ]
+        );
+
+        state.set_motor_speed(2, 0.75);
+        state.disable();
+        assert!(!state.is_enabled());
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            [
+                Outgoing::reliable(LinkageToCarburetorFrame::MotorBatch(MotorBatch {
+                    speeds: vec![(2, 0.0)]
+                })),
+                Outgoing::new(
+                    LinkageToCarburetor::DigitalOutput {
+                        pin: 0,
+                        value: false
+                    }
+                    .into()
+                ),
+                Outgoing::new(
+                    LinkageToCarburetor::DigitalOutput {
+                        pin: 1,
+                        value: true
+                    }
+                    .into()
+                ),
+            ]
+        );
+
+        actuate(&mut state);
+        assert_eq!(receiver.try_iter().count(), 0);
+
+        state.enable();
+        assert!(state.is_enabled());
+        actuate(&mut state);
+        assert_eq!(receiver.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn disabling_puts_the_gamepads_at_rest() {
+        let (mut state, _receiver) = state();
+        state
+            .gamepad_manager
+            .handle_cockpit_message(CockpitToLinkage::GamepadInputEvent {
+                gamepad_id: 7,
+                event_type: 1,
+                control: 1,
+                value: -0.5,
+            });
+        let gamepad: PsController = state
+            .gamepad_manager
+            .get(AssociatedGamepad::Primary)
+            .unwrap();
+        assert_eq!(gamepad.left_joystick_x(), -0.5);
+
+        state.disable();
+        let gamepad: PsController = state
+            .gamepad_manager
+            .get(AssociatedGamepad::Primary)
+            .unwrap();
+        assert_eq!(gamepad.id(), 7);
+        assert_eq!(gamepad.left_joystick_x(), 0.0);
+    }
+
+    #[test]
+    fn tells_once_it_was_disabled() {
+        let (mut state, _receiver) = state();
+        assert!(!state.take_disabled());
+
+        state.disable();
+        state.disable();
+        assert!(state.take_disabled());
+        assert!(!state.take_disabled());
+
+        state.enable();
+        assert!(!state.take_disabled());
+    }
 }
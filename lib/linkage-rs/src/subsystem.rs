@@ -53,6 +53,16 @@ pub trait Subsystem {
     #[allow(unused_variables)]
     fn tick(&mut self, state: RobotStateHandle) {}
 
+    /// Called when the [Robot][`crate::robot::Robot`] is disabled because Cockpit was lost, before
+    /// the next tick. By then the gamepads are at rest and every motor is in neutral.
+    /// Override this method to stop anything else the subsystem is doing.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - A handle to the shared RobotState.
+    #[allow(unused_variables)]
+    fn disabled(&mut self, state: RobotStateHandle) {}
+
     /// Called before the [Robot][`crate::robot::Robot`] is shutting down.
     /// Override this method to perform cleanup tasks.
     ///